@@ -0,0 +1,270 @@
+#[macro_use]
+extern crate serde_derive;
+
+use std::io::Read;
+use std::time::Duration;
+
+// Mirrors the JSON produced by res/templates/tiger.json.liquid
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Sheet {
+    pub sheet_image: String,
+    pub frames: Vec<Frame>,
+    pub animations: Vec<Animation>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Frame {
+    #[serde(default)]
+    pub id: String,
+    pub source: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub hitboxes: Vec<Hitbox>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Hitbox {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Animation {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub is_looping: bool,
+    pub duration: u32,
+    pub keyframes: Vec<Keyframe>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Keyframe {
+    pub frame: usize,
+    #[serde(default)]
+    pub frame_id: String,
+    pub duration: u32,
+    pub x_offset: i32,
+    pub y_offset: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CurrentFrame<'a> {
+    pub keyframe_index: usize,
+    pub rect: Rect,
+    pub offset: (i32, i32),
+    pub hitboxes: &'a [Hitbox],
+}
+
+impl Sheet {
+    pub fn from_json(json: &str) -> Result<Sheet, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn from_reader<R: Read>(reader: R) -> Result<Sheet, serde_json::Error> {
+        serde_json::from_reader(reader)
+    }
+
+    pub fn get_animation<T: AsRef<str>>(&self, name: T) -> Option<&Animation> {
+        self.animations.iter().find(|a| a.name == name.as_ref())
+    }
+
+    pub fn get_frame(&self, index: usize) -> Option<&Frame> {
+        self.frames.get(index)
+    }
+}
+
+impl Animation {
+    pub fn get_keyframe_at(&self, time: Duration) -> Option<(usize, &Keyframe)> {
+        let duration = match self.duration {
+            0 => return None,
+            d => d,
+        };
+        let time = if self.is_looping {
+            Duration::from_millis(time.as_millis() as u64 % u64::from(duration))
+        } else {
+            time
+        };
+        let mut cursor = Duration::new(0, 0);
+        for (index, keyframe) in self.keyframes.iter().enumerate() {
+            cursor += Duration::from_millis(u64::from(keyframe.duration));
+            if time < cursor {
+                return Some((index, keyframe));
+            }
+        }
+        self.keyframes.iter().enumerate().next_back()
+    }
+}
+
+// Plays back an animation the same way the editor's timeline does
+#[derive(Clone, Debug)]
+pub struct AnimationPlayer<'a> {
+    sheet: &'a Sheet,
+    animation: &'a Animation,
+    clock: Duration,
+    is_playing: bool,
+}
+
+impl<'a> AnimationPlayer<'a> {
+    pub fn new<T: AsRef<str>>(sheet: &'a Sheet, animation_name: T) -> Option<AnimationPlayer<'a>> {
+        sheet
+            .get_animation(animation_name)
+            .map(|animation| AnimationPlayer {
+                sheet,
+                animation,
+                clock: Duration::new(0, 0),
+                is_playing: true,
+            })
+    }
+
+    pub fn get_animation(&self) -> &'a Animation {
+        self.animation
+    }
+
+    pub fn get_clock(&self) -> Duration {
+        self.clock
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.is_playing
+    }
+
+    pub fn play(&mut self) {
+        self.is_playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.is_playing = false;
+    }
+
+    pub fn seek(&mut self, time: Duration) {
+        self.clock = time;
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        if !self.is_playing {
+            return;
+        }
+        self.clock += delta;
+        match self.animation.duration {
+            d if d > 0 => {
+                let clock_ms = self.clock.as_millis();
+                // Loop animation
+                if self.animation.is_looping {
+                    self.clock = Duration::from_millis((clock_ms % u128::from(d)) as u64)
+
+                // Stop playhead at the end of animation
+                } else if clock_ms >= u128::from(d) {
+                    self.is_playing = false;
+                    self.clock = Duration::from_millis(u64::from(d))
+                }
+            }
+
+            // Reset playhead
+            _ => {
+                self.is_playing = false;
+                self.clock = Duration::new(0, 0);
+            }
+        }
+    }
+
+    pub fn get_current_frame(&self) -> Option<CurrentFrame<'a>> {
+        let (keyframe_index, keyframe) = self.animation.get_keyframe_at(self.clock)?;
+        let frame = self.sheet.get_frame(keyframe.frame)?;
+        Some(CurrentFrame {
+            keyframe_index,
+            rect: Rect {
+                x: frame.x,
+                y: frame.y,
+                width: frame.width,
+                height: frame.height,
+            },
+            offset: (keyframe.x_offset, keyframe.y_offset),
+            hitboxes: &frame.hitboxes,
+        })
+    }
+}
+
+#[cfg(test)]
+fn test_sheet(is_looping: bool) -> Sheet {
+    Sheet::from_json(&format!(
+        r#"{{
+            "sheet_image": "sheet.png",
+            "frames": [
+                {{ "source": "a.png", "x": 0, "y": 0, "width": 16, "height": 32, "hitboxes": [] }},
+                {{
+                    "source": "b.png", "x": 16, "y": 0, "width": 16, "height": 32,
+                    "hitboxes": [{{ "name": "hurt", "x": 2, "y": 4, "width": 8, "height": 8 }}]
+                }}
+            ],
+            "animations": [
+                {{
+                    "name": "walk",
+                    "is_looping": {},
+                    "duration": 300,
+                    "keyframes": [
+                        {{ "frame": 0, "duration": 100, "x_offset": -8, "y_offset": -32 }},
+                        {{ "frame": 1, "duration": 200, "x_offset": -8, "y_offset": -30 }}
+                    ]
+                }}
+            ]
+        }}"#,
+        is_looping
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_looping_playback() {
+    let sheet = test_sheet(true);
+    let mut player = AnimationPlayer::new(&sheet, "walk").unwrap();
+
+    let frame = player.get_current_frame().unwrap();
+    assert_eq!(frame.keyframe_index, 0);
+    assert_eq!(frame.offset, (-8, -32));
+
+    player.tick(Duration::from_millis(150));
+    let frame = player.get_current_frame().unwrap();
+    assert_eq!(frame.keyframe_index, 1);
+    assert_eq!(frame.rect.x, 16);
+    assert_eq!(frame.hitboxes[0].name, "hurt");
+
+    player.tick(Duration::from_millis(200));
+    assert!(player.is_playing());
+    assert_eq!(player.get_clock(), Duration::from_millis(50));
+    assert_eq!(player.get_current_frame().unwrap().keyframe_index, 0);
+}
+
+#[test]
+fn test_non_looping_playback() {
+    let sheet = test_sheet(false);
+    let mut player = AnimationPlayer::new(&sheet, "walk").unwrap();
+
+    player.tick(Duration::from_millis(250));
+    assert!(player.is_playing());
+    assert_eq!(player.get_current_frame().unwrap().keyframe_index, 1);
+
+    player.tick(Duration::from_millis(250));
+    assert!(!player.is_playing());
+    assert_eq!(player.get_clock(), Duration::from_millis(300));
+    assert_eq!(player.get_current_frame().unwrap().keyframe_index, 1);
+
+    player.tick(Duration::from_millis(100));
+    assert_eq!(player.get_clock(), Duration::from_millis(300));
+}
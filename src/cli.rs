@@ -0,0 +1,85 @@
+use failure::Error;
+use std::path::Path;
+
+use crate::sheet::{self, compat};
+use crate::state::Document;
+
+#[derive(Fail, Debug)]
+pub enum CliError {
+    #[fail(display = "Usage: tiger validate <sheet.tiger>")]
+    InvalidValidateArguments,
+    #[fail(display = "Sheet has {} problem(s)", _0)]
+    ProblemsFound(usize),
+    #[fail(
+        display = "Usage: tiger merge <base.tiger> <ours.tiger> <theirs.tiger> [<output.tiger>]"
+    )]
+    InvalidMergeArguments,
+    #[fail(display = "Merge has {} conflict(s)", _0)]
+    MergeConflicts(usize),
+    #[fail(display = "Usage: tiger diff <old.tiger> <new.tiger>")]
+    InvalidDiffArguments,
+}
+
+fn validate(args: &[String]) -> Result<(), Error> {
+    if args.is_empty() {
+        return Err(CliError::InvalidValidateArguments.into());
+    }
+    let mut num_problems = 0;
+    for path in args {
+        let document = Document::open(Path::new(path))?;
+        for problem in document.sheet.validate() {
+            println!("{}: {}", path, problem);
+            num_problems += 1;
+        }
+    }
+    if num_problems > 0 {
+        return Err(CliError::ProblemsFound(num_problems).into());
+    }
+    Ok(())
+}
+
+// Conflicting edits keep our side, the output defaults to our file like git merge drivers expect
+fn merge(args: &[String]) -> Result<(), Error> {
+    if args.len() != 3 && args.len() != 4 {
+        return Err(CliError::InvalidMergeArguments.into());
+    }
+    let base = compat::read_sheet(&args[0])?;
+    let ours = compat::read_sheet(&args[1])?;
+    let theirs = compat::read_sheet(&args[2])?;
+    let output = args.get(3).unwrap_or(&args[1]);
+
+    let (merged, conflicts) = sheet::merge_sheets(&base, &ours, &theirs);
+    compat::write_sheet(output, &merged)?;
+    for conflict in &conflicts {
+        println!("{}: {}", output, conflict);
+    }
+    if !conflicts.is_empty() {
+        return Err(CliError::MergeConflicts(conflicts.len()).into());
+    }
+    Ok(())
+}
+
+fn diff(args: &[String]) -> Result<(), Error> {
+    // Git external diff tools receive: path old-file old-hex old-mode new-file new-hex new-mode
+    let (old_path, new_path) = match args.len() {
+        2 => (&args[0], &args[1]),
+        7 => (&args[1], &args[4]),
+        _ => return Err(CliError::InvalidDiffArguments.into()),
+    };
+    let old = Document::open(Path::new(old_path))?;
+    let new = Document::open(Path::new(new_path))?;
+    for change in sheet::diff_sheets(&old.sheet, &new.sheet) {
+        println!("{}", change);
+    }
+    Ok(())
+}
+
+// Returns None when the arguments do not name a command-line task and the editor should start
+pub fn run(args: &[String]) -> Option<Result<(), Error>> {
+    match args.get(1).map(String::as_str) {
+        Some("validate") => Some(validate(&args[2..])),
+        Some("merge") => Some(merge(&args[2..])),
+        Some("diff") => Some(diff(&args[2..])),
+        _ => None,
+    }
+}
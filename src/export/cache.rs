@@ -0,0 +1,245 @@
+use failure::Error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::export::{PackedFrame, TextureLayout};
+use crate::sheet::{ExportFormat, ExportSettings, Sheet};
+use crate::utils::StableHasher;
+
+const CACHE_FILE_EXTENSION: &str = "tigercache";
+
+#[derive(Serialize, Deserialize)]
+struct CachedFrame {
+    sheet_index: usize,
+    source: PathBuf,
+    position_in_sheet: (u32, u32),
+    size_in_sheet: (u32, u32),
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedTexture {
+    images_hash: u64,
+    frames: Vec<CachedFrame>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedMetadata {
+    metadata_hash: u64,
+}
+
+// Covers every sheet sharing the texture, since the layout depends on all of them.
+// Contents are hashed because edits do not always change modification times.
+pub fn hash_images(sheets: &[Sheet]) -> Result<u64, Error> {
+    let mut hasher = StableHasher::default();
+    for sheet in sheets {
+        sheet.frames_iter().count().hash(&mut hasher);
+        for frame in sheet.frames_iter() {
+            let source = frame.get_source();
+            source.hash(&mut hasher);
+            let modified = std::fs::metadata(source)?
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            modified.hash(&mut hasher);
+            std::fs::read(source)?.hash(&mut hasher);
+        }
+    }
+    Ok(hasher.finish())
+}
+
+// Names of the partials included by a template, ignoring includes which are not string literals
+fn included_partials(template: &str) -> Vec<String> {
+    template
+        .split("{%")
+        .skip(1)
+        .filter_map(|tag| {
+            let tag = tag.trim_start_matches('-').trim_start();
+            if !tag.starts_with("include") {
+                return None;
+            }
+            let argument = tag["include".len()..].trim_start();
+            let quote = argument
+                .chars()
+                .next()
+                .filter(|c| *c == '"' || *c == '\'')?;
+            argument[1..].split(quote).next().map(str::to_owned)
+        })
+        .collect()
+}
+
+// Partials are resolved relative to the directory of the main template, like during export
+fn hash_template<T: AsRef<Path>>(template: T, hasher: &mut StableHasher) -> Result<(), Error> {
+    let directory = template.as_ref().parent().unwrap_or_else(|| Path::new(""));
+    let mut visited = HashSet::new();
+    let mut pending = vec![template.as_ref().to_owned()];
+    while let Some(path) = pending.pop() {
+        if !visited.insert(path.clone()) {
+            continue;
+        }
+        let content = std::fs::read_to_string(&path)?;
+        content.hash(hasher);
+        pending.extend(
+            included_partials(&content)
+                .into_iter()
+                .map(|p| directory.join(p)),
+        );
+    }
+    Ok(())
+}
+
+pub fn hash_metadata_inputs(
+    sheet: &Sheet,
+    export_settings: &ExportSettings,
+    images_hash: u64,
+) -> Result<u64, Error> {
+    let mut hasher = StableHasher::default();
+    images_hash.hash(&mut hasher);
+    serde_json::to_string(sheet)?.hash(&mut hasher);
+    serde_json::to_string(export_settings)?.hash(&mut hasher);
+    match &export_settings.format {
+        ExportFormat::Template(p) => hash_template(p, &mut hasher)?,
+    }
+    Ok(hasher.finish())
+}
+
+// Hashes are stored next to exported files, so that exports are skipped across sessions
+fn cache_path(destination: &Path) -> PathBuf {
+    let mut file_name = destination.file_name().unwrap_or_default().to_owned();
+    file_name.push(".");
+    file_name.push(CACHE_FILE_EXTENSION);
+    destination.with_file_name(file_name)
+}
+
+// Caches of exported files which were deleted since are ignored
+fn read_cache<T: DeserializeOwned>(destination: &Path) -> Option<T> {
+    if !destination.exists() {
+        return None;
+    }
+    let file = File::open(cache_path(destination)).ok()?;
+    serde_json::from_reader(BufReader::new(file)).ok()
+}
+
+fn write_cache<T: Serialize>(destination: &Path, cache: &T) -> Result<(), Error> {
+    let file = BufWriter::new(File::create(cache_path(destination))?);
+    serde_json::to_writer(file, cache)?;
+    Ok(())
+}
+
+pub fn read_texture_layout<T: AsRef<Path>>(
+    texture_destination: T,
+    images_hash: u64,
+) -> Option<TextureLayout> {
+    let cached_texture: CachedTexture = read_cache(texture_destination.as_ref())?;
+    if cached_texture.images_hash != images_hash {
+        return None;
+    }
+    Some(
+        cached_texture
+            .frames
+            .into_iter()
+            .map(|f| {
+                (
+                    (f.sheet_index, f.source),
+                    PackedFrame {
+                        position_in_sheet: f.position_in_sheet,
+                        size_in_sheet: f.size_in_sheet,
+                    },
+                )
+            })
+            .collect(),
+    )
+}
+
+pub fn write_texture_layout<T: AsRef<Path>>(
+    texture_destination: T,
+    images_hash: u64,
+    layout: &TextureLayout,
+) -> Result<(), Error> {
+    let mut frames: Vec<CachedFrame> = layout
+        .iter()
+        .map(|((sheet_index, source), packed_frame)| CachedFrame {
+            sheet_index: *sheet_index,
+            source: source.clone(),
+            position_in_sheet: packed_frame.position_in_sheet,
+            size_in_sheet: packed_frame.size_in_sheet,
+        })
+        .collect();
+    frames.sort_by(|a, b| (a.sheet_index, &a.source).cmp(&(b.sheet_index, &b.source)));
+    write_cache(
+        texture_destination.as_ref(),
+        &CachedTexture {
+            images_hash,
+            frames,
+        },
+    )
+}
+
+pub fn is_metadata_up_to_date<T: AsRef<Path>>(metadata_destination: T, metadata_hash: u64) -> bool {
+    read_cache(metadata_destination.as_ref())
+        .map(|c: CachedMetadata| c.metadata_hash == metadata_hash)
+        .unwrap_or(false)
+}
+
+pub fn write_metadata_hash<T: AsRef<Path>>(
+    metadata_destination: T,
+    metadata_hash: u64,
+) -> Result<(), Error> {
+    write_cache(
+        metadata_destination.as_ref(),
+        &CachedMetadata { metadata_hash },
+    )
+}
+
+#[test]
+fn test_included_partials() {
+    let template = r#"{% include "header.liquid" %}
+{%- include 'frame.liquid' -%}
+{% if x %}include "nope"{% endif %}
+{% include dynamic_name %}"#;
+    assert_eq!(
+        included_partials(template),
+        vec!["header.liquid".to_owned(), "frame.liquid".to_owned()]
+    );
+}
+
+#[test]
+fn test_export_cache_files() {
+    let directory = std::env::temp_dir().join(format!("tiger-export-cache-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let texture = directory.join("atlas.png");
+    let metadata = directory.join("atlas.json");
+
+    let mut layout = TextureLayout::new();
+    layout.insert(
+        (0, "walk_0.png".into()),
+        PackedFrame {
+            position_in_sheet: (8, 0),
+            size_in_sheet: (8, 16),
+        },
+    );
+    write_texture_layout(&texture, 1, &layout).unwrap();
+    write_metadata_hash(&metadata, 2).unwrap();
+
+    // Nothing is up to date until exported files exist
+    assert!(read_texture_layout(&texture, 1).is_none());
+    assert!(!is_metadata_up_to_date(&metadata, 2));
+
+    std::fs::write(&texture, b"").unwrap();
+    std::fs::write(&metadata, b"").unwrap();
+    let cached_layout = read_texture_layout(&texture, 1).unwrap();
+    assert_eq!(
+        cached_layout[&(0, "walk_0.png".into())].position_in_sheet,
+        (8, 0)
+    );
+    assert!(read_texture_layout(&texture, 3).is_none());
+    assert!(is_metadata_up_to_date(&metadata, 2));
+    assert!(!is_metadata_up_to_date(&metadata, 3));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
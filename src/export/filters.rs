@@ -0,0 +1,152 @@
+use liquid::compiler::{FilterResult, FnFilterValue};
+use liquid::value::{Scalar, Value};
+use std::path::Path;
+
+fn invalid_input<T: AsRef<str>>(filter_name: &str, cause: T) -> liquid::Error {
+    liquid::Error::with_msg(format!("Invalid input for filter `{}`", filter_name))
+        .context("cause", cause.as_ref().to_owned())
+}
+
+fn check_no_arguments(filter_name: &str, arguments: &[Value]) -> Result<(), liquid::Error> {
+    if !arguments.is_empty() {
+        return Err(liquid::Error::with_msg(format!(
+            "Filter `{}` does not take any argument",
+            filter_name
+        )));
+    }
+    Ok(())
+}
+
+fn split_words(input: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous: Option<char> = None;
+    for c in input.chars() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::replace(&mut current, String::new()));
+            }
+            previous = None;
+            continue;
+        }
+        let is_boundary = match previous {
+            Some(p) => c.is_uppercase() && (p.is_lowercase() || p.is_numeric()),
+            None => false,
+        };
+        if is_boundary && !current.is_empty() {
+            words.push(std::mem::replace(&mut current, String::new()));
+        }
+        current.extend(c.to_lowercase());
+        previous = Some(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn to_snake_case(input: &str) -> String {
+    split_words(input).join("_")
+}
+
+fn to_camel_case(input: &str) -> String {
+    let mut output = String::new();
+    for (index, word) in split_words(input).iter().enumerate() {
+        if index == 0 {
+            output.push_str(word);
+            continue;
+        }
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            output.extend(first.to_uppercase());
+            output.push_str(chars.as_str());
+        }
+    }
+    output
+}
+
+pub fn json_escape(input: &Value, arguments: &[Value]) -> FilterResult {
+    check_no_arguments("json_escape", arguments)?;
+    let quoted = serde_json::to_string(input.to_str().as_ref())
+        .map_err(|e| invalid_input("json_escape", e.to_string()))?;
+    let escaped = quoted[1..quoted.len() - 1].to_owned();
+    Ok(Value::Scalar(Scalar::new(escaped)))
+}
+
+pub fn ms_to_seconds(input: &Value, arguments: &[Value]) -> FilterResult {
+    check_no_arguments("ms_to_seconds", arguments)?;
+    let milliseconds = input
+        .as_scalar()
+        .and_then(|s| s.to_float())
+        .ok_or_else(|| invalid_input("ms_to_seconds", "Number expected"))?;
+    Ok(Value::Scalar(Scalar::new(milliseconds / 1000.0)))
+}
+
+pub fn snake_case(input: &Value, arguments: &[Value]) -> FilterResult {
+    check_no_arguments("snake_case", arguments)?;
+    Ok(Value::Scalar(Scalar::new(to_snake_case(&input.to_str()))))
+}
+
+pub fn camel_case(input: &Value, arguments: &[Value]) -> FilterResult {
+    check_no_arguments("camel_case", arguments)?;
+    Ok(Value::Scalar(Scalar::new(to_camel_case(&input.to_str()))))
+}
+
+pub fn basename(input: &Value, arguments: &[Value]) -> FilterResult {
+    check_no_arguments("basename", arguments)?;
+    let input = input.to_str();
+    let basename = Path::new(input.as_ref())
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok(Value::Scalar(Scalar::new(basename)))
+}
+
+pub fn strip_extension(input: &Value, arguments: &[Value]) -> FilterResult {
+    check_no_arguments("strip_extension", arguments)?;
+    let input = input.to_str();
+    let path = Path::new(input.as_ref());
+    let stripped = match path.file_stem() {
+        Some(stem) => path.with_file_name(stem).to_string_lossy().into_owned(),
+        None => input.to_string(),
+    };
+    Ok(Value::Scalar(Scalar::new(stripped)))
+}
+
+pub fn register(parser_builder: liquid::ParserBuilder) -> liquid::ParserBuilder {
+    parser_builder
+        .filter("json_escape", json_escape as FnFilterValue)
+        .filter("ms_to_seconds", ms_to_seconds as FnFilterValue)
+        .filter("snake_case", snake_case as FnFilterValue)
+        .filter("camel_case", camel_case as FnFilterValue)
+        .filter("basename", basename as FnFilterValue)
+        .filter("strip_extension", strip_extension as FnFilterValue)
+}
+
+#[test]
+fn test_case_conversions() {
+    assert_eq!(to_snake_case("Idle Left"), "idle_left");
+    assert_eq!(to_snake_case("runCycle2"), "run_cycle2");
+    assert_eq!(to_snake_case("  attack--heavy "), "attack_heavy");
+    assert_eq!(to_camel_case("idle left"), "idleLeft");
+    assert_eq!(to_camel_case("Run_cycle_2"), "runCycle2");
+}
+
+#[test]
+fn test_filters() {
+    let apply = |filter: FnFilterValue, input: &'static str| {
+        filter(&Value::Scalar(Scalar::new(input)), &[])
+            .unwrap()
+            .to_str()
+            .into_owned()
+    };
+    assert_eq!(apply(json_escape, "say \"hi\"\n"), "say \\\"hi\\\"\\n");
+    assert_eq!(apply(basename, "frames/walk_0.png"), "walk_0.png");
+    assert_eq!(apply(strip_extension, "frames/walk_0.png"), "frames/walk_0");
+    assert_eq!(apply(strip_extension, "walk_0"), "walk_0");
+
+    let seconds = ms_to_seconds(&Value::Scalar(Scalar::new(1500)), &[]).unwrap();
+    assert_eq!(seconds.as_scalar().and_then(|s| s.to_float()), Some(1.5));
+    assert!(ms_to_seconds(&Value::Scalar(Scalar::new("fast")), &[]).is_err());
+    assert!(basename(&Value::Scalar(Scalar::new("a")), &[Value::Nil]).is_err());
+}
@@ -4,22 +4,26 @@ use liquid::value::{Scalar, Value};
 use pathdiff::diff_paths;
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::sheet::{Animation, AnimationFrame, ExportFormat, ExportSettings, Frame, Hitbox, Sheet};
 
+mod cache;
+mod filters;
 mod pack;
+pub use cache::*;
 pub use pack::*;
 
 type LiquidData = HashMap<Cow<'static, str>, Value>;
-type TextureLayout = HashMap<PathBuf, PackedFrame>;
+// Frames are keyed by the index of their sheet among the sheets sharing the texture
+pub type TextureLayout = HashMap<(usize, PathBuf), PackedFrame>;
 
 #[derive(Fail, Debug)]
 pub enum ExportError {
-    #[fail(display = "Template parsing error")]
-    TemplateParsingError,
-    #[fail(display = "Template rendering error")]
-    TemplateRenderingError,
+    #[fail(display = "Template parsing error: {}", _0)]
+    TemplateParsingError(String),
+    #[fail(display = "Template rendering error: {}", _0)]
+    TemplateRenderingError(String),
     #[fail(display = "An animation references a frame which is not part of the sheet")]
     InvalidFrameReference,
     #[fail(display = "The sheet contains a frame which was not packed into the texture atlas")]
@@ -28,12 +32,49 @@ pub enum ExportError {
     AbsoluteToRelativePath,
 }
 
+// Texture coordinates of a frame, as fractions of the atlas size
+fn insert_uv_data(map: &mut LiquidData, packed_frame: &PackedFrame, atlas_size: Vector2D<u32>) {
+    let atlas_size: Vector2D<f64> = vec2(
+        f64::from(atlas_size.x.max(1)),
+        f64::from(atlas_size.y.max(1)),
+    );
+    map.insert(
+        "uv_x".into(),
+        Value::Scalar(Scalar::new(
+            f64::from(packed_frame.position_in_sheet.0) / atlas_size.x,
+        )),
+    );
+    map.insert(
+        "uv_y".into(),
+        Value::Scalar(Scalar::new(
+            f64::from(packed_frame.position_in_sheet.1) / atlas_size.y,
+        )),
+    );
+    map.insert(
+        "uv_width".into(),
+        Value::Scalar(Scalar::new(
+            f64::from(packed_frame.size_in_sheet.0) / atlas_size.x,
+        )),
+    );
+    map.insert(
+        "uv_height".into(),
+        Value::Scalar(Scalar::new(
+            f64::from(packed_frame.size_in_sheet.1) / atlas_size.y,
+        )),
+    );
+}
+
 fn liquid_data_from_hitbox(
     hitbox: &Hitbox,
     packed_frame: &PackedFrame,
 ) -> Result<LiquidData, Error> {
     let mut map = LiquidData::new();
 
+    map.insert(
+        "id".into(),
+        Value::Scalar(Scalar::new(hitbox.get_id().to_string())),
+    );
+
     map.insert(
         "name".into(),
         Value::Scalar(Scalar::new(hitbox.get_name().to_owned())),
@@ -75,12 +116,29 @@ fn liquid_data_from_hitbox(
     Ok(map)
 }
 
+fn get_packed_frame<'a>(
+    texture_layout: &'a TextureLayout,
+    sheet_index: usize,
+    frame: &Frame,
+) -> Result<&'a PackedFrame, Error> {
+    Ok(texture_layout
+        .get(&(sheet_index, frame.get_source().to_owned()))
+        .ok_or(ExportError::FrameWasNotPacked)?)
+}
+
 fn liquid_data_from_frame(
     sheet: &Sheet,
+    sheet_index: usize,
     frame: &Frame,
     texture_layout: &TextureLayout,
+    atlas_size: Vector2D<u32>,
 ) -> Result<LiquidData, Error> {
     let mut frame_data = LiquidData::new();
+    frame_data.insert(
+        "id".into(),
+        Value::Scalar(Scalar::new(frame.get_id().to_string())),
+    );
+
     frame_data.insert(
         "source".into(),
         Value::Scalar(Scalar::new(
@@ -94,9 +152,14 @@ fn liquid_data_from_frame(
         .ok_or(ExportError::InvalidFrameReference)?;
     frame_data.insert("index".into(), Value::Scalar(Scalar::new(index as i32)));
 
-    let frame_layout = texture_layout
-        .get(frame.get_source())
-        .ok_or(ExportError::FrameWasNotPacked)?;
+    let file_stem = frame
+        .get_source()
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    frame_data.insert("file_stem".into(), Value::Scalar(Scalar::new(file_stem)));
+
+    let frame_layout = get_packed_frame(texture_layout, sheet_index, frame)?;
 
     frame_data.insert(
         "x".into(),
@@ -118,12 +181,11 @@ fn liquid_data_from_frame(
         Value::Scalar(Scalar::new(frame_layout.size_in_sheet.1 as i32)),
     );
 
+    insert_uv_data(&mut frame_data, frame_layout, atlas_size);
+
     let mut hitboxes = Vec::new();
     for hitbox in frame.hitboxes_iter() {
-        let packed_frame = texture_layout
-            .get(frame.get_source())
-            .ok_or(ExportError::FrameWasNotPacked)?;
-        let hitbox_data = liquid_data_from_hitbox(hitbox, packed_frame)?;
+        let hitbox_data = liquid_data_from_hitbox(hitbox, frame_layout)?;
         hitboxes.push(Value::Object(hitbox_data));
     }
     frame_data.insert("hitboxes".into(), Value::Array(hitboxes));
@@ -133,14 +195,29 @@ fn liquid_data_from_frame(
 
 fn liquid_data_from_animation_frame(
     sheet: &Sheet,
+    sheet_index: usize,
     animation_frame: &AnimationFrame,
+    index: usize,
+    start_time: u64,
     texture_layout: &TextureLayout,
+    atlas_size: Vector2D<u32>,
 ) -> Result<LiquidData, Error> {
-    let packed_frame = texture_layout
-        .get(animation_frame.get_frame())
-        .ok_or(ExportError::FrameWasNotPacked)?;
+    let frame = sheet
+        .get_frame(animation_frame.get_frame())
+        .ok_or(ExportError::InvalidFrameReference)?;
+
+    let packed_frame = get_packed_frame(texture_layout, sheet_index, frame)?;
 
     let mut map = LiquidData::new();
+    map.insert("index".into(), Value::Scalar(Scalar::new(index as i32)));
+    map.insert(
+        "frame_id".into(),
+        Value::Scalar(Scalar::new(frame.get_id().to_string())),
+    );
+    map.insert(
+        "start_time".into(),
+        Value::Scalar(Scalar::new(start_time as i32)),
+    );
     map.insert(
         "duration".into(),
         Value::Scalar(Scalar::new(animation_frame.get_duration() as i32)),
@@ -168,11 +245,9 @@ fn liquid_data_from_animation_frame(
         Value::Scalar(Scalar::new(top_left_offset.y)),
     );
 
-    let frame = sheet
-        .get_frame(animation_frame.get_frame())
-        .ok_or(ExportError::InvalidFrameReference)?;
+    insert_uv_data(&mut map, packed_frame, atlas_size);
 
-    let frame_data = liquid_data_from_frame(sheet, frame, texture_layout)?;
+    let frame_data = liquid_data_from_frame(sheet, sheet_index, frame, texture_layout, atlas_size)?;
     map.insert("frame".into(), Value::Object(frame_data));
 
     Ok(map)
@@ -180,11 +255,18 @@ fn liquid_data_from_animation_frame(
 
 fn liquid_data_from_animation(
     sheet: &Sheet,
+    sheet_index: usize,
     animation: &Animation,
     texture_layout: &TextureLayout,
+    atlas_size: Vector2D<u32>,
 ) -> Result<LiquidData, Error> {
     let mut map = LiquidData::new();
 
+    map.insert(
+        "id".into(),
+        Value::Scalar(Scalar::new(animation.get_id().to_string())),
+    );
+
     map.insert(
         "name".into(),
         Value::Scalar(Scalar::new(animation.get_name().to_owned())),
@@ -195,9 +277,23 @@ fn liquid_data_from_animation(
         Value::Scalar(Scalar::new(animation.is_looping())),
     );
 
+    map.insert(
+        "duration".into(),
+        Value::Scalar(Scalar::new(animation.get_duration().unwrap_or(0) as i32)),
+    );
+
     let mut frames = Vec::new();
-    for animation_frame in animation.frames_iter() {
-        let frame = liquid_data_from_animation_frame(sheet, animation_frame, texture_layout)?;
+    let frame_times = animation.get_frame_times();
+    for (index, animation_frame) in animation.frames_iter().enumerate() {
+        let frame = liquid_data_from_animation_frame(
+            sheet,
+            sheet_index,
+            animation_frame,
+            index,
+            frame_times[index],
+            texture_layout,
+            atlas_size,
+        )?;
         frames.push(Value::Object(frame));
     }
     map.insert("keyframes".into(), Value::Array(frames));
@@ -207,8 +303,10 @@ fn liquid_data_from_animation(
 
 fn liquid_data_from_sheet(
     sheet: &Sheet,
+    sheet_index: usize,
     export_settings: &ExportSettings,
     texture_layout: &TextureLayout,
+    atlas_size: Vector2D<u32>,
 ) -> Result<LiquidData, Error> {
     let mut map = LiquidData::new();
 
@@ -217,8 +315,10 @@ fn liquid_data_from_sheet(
         for frame in sheet.frames_iter() {
             frames.push(Value::Object(liquid_data_from_frame(
                 sheet,
+                sheet_index,
                 frame,
                 texture_layout,
+                atlas_size,
             )?));
         }
         let frames_value = Value::Array(frames);
@@ -228,7 +328,13 @@ fn liquid_data_from_sheet(
     {
         let mut animations = Vec::new();
         for animation in sheet.animations_iter() {
-            let animation_data = liquid_data_from_animation(sheet, animation, texture_layout)?;
+            let animation_data = liquid_data_from_animation(
+                sheet,
+                sheet_index,
+                animation,
+                texture_layout,
+                atlas_size,
+            )?;
             animations.push(Value::Object(animation_data));
         }
         let animations_value = Value::Array(animations);
@@ -248,25 +354,158 @@ fn liquid_data_from_sheet(
     Ok(map)
 }
 
+// The atlas size is the size of the packed texture, which frames may not fill entirely
 pub fn export_sheet(
     sheet: &Sheet,
+    sheet_index: usize,
     export_settings: &ExportSettings,
     texture_layout: &TextureLayout,
+    atlas_size: Vector2D<u32>,
 ) -> Result<String, Error> {
     let template;
     match &export_settings.format {
         ExportFormat::Template(p) => {
-            template = liquid::ParserBuilder::with_liquid()
+            let partials_directory = p.parent().unwrap_or_else(|| Path::new(""));
+            let partials_source =
+                liquid::compiler::FilesystemInclude::new(partials_directory.to_path_buf());
+            template = filters::register(liquid::ParserBuilder::with_liquid())
+                .include_source(Box::new(partials_source))
                 .build()
                 .parse_file(p)
-                .map_err(|_| ExportError::TemplateParsingError)?;
+                .map_err(|e| ExportError::TemplateParsingError(e.to_string()))?;
         }
     }
 
-    let globals: LiquidData = liquid_data_from_sheet(sheet, export_settings, texture_layout)?;
+    let globals: LiquidData = liquid_data_from_sheet(
+        sheet,
+        sheet_index,
+        export_settings,
+        texture_layout,
+        atlas_size,
+    )?;
     let output = template
         .render(&globals)
-        .map_err(|_| ExportError::TemplateRenderingError)?;
+        .map_err(|e| ExportError::TemplateRenderingError(e.to_string()))?;
 
     Ok(output)
 }
+
+#[test]
+fn test_uv_data() {
+    let mut sheet = Sheet::default();
+    let frame = sheet.add_frame("/art/walk_0.png");
+    sheet
+        .add_animation_with_name("walk")
+        .insert_frame(frame, 0)
+        .unwrap();
+
+    let mut texture_layout = TextureLayout::new();
+    texture_layout.insert(
+        (0, "/art/walk_0.png".into()),
+        PackedFrame {
+            position_in_sheet: (16, 0),
+            size_in_sheet: (16, 32),
+        },
+    );
+    let mut export_settings = ExportSettings::new();
+    export_settings.texture_destination = "/game/hero.png".into();
+    export_settings.metadata_paths_root = "/game".into();
+
+    // Frames do not fill the whole atlas
+    let data = liquid_data_from_sheet(&sheet, 0, &export_settings, &texture_layout, vec2(64, 64));
+    let data = Value::Object(data.unwrap());
+    let field = |value: &Value, name: &str| match value {
+        Value::Object(o) => o[name].clone(),
+        _ => panic!("Expected an object"),
+    };
+    let first = |value: Value| match value {
+        Value::Array(a) => a[0].clone(),
+        _ => panic!("Expected an array"),
+    };
+    let frame_data = first(field(&data, "frames"));
+    let keyframe_data = first(field(&first(field(&data, "animations")), "keyframes"));
+    for uv_data in &[frame_data, keyframe_data] {
+        assert_eq!(field(uv_data, "uv_x"), Value::Scalar(Scalar::new(0.25)));
+        assert_eq!(field(uv_data, "uv_y"), Value::Scalar(Scalar::new(0.0)));
+        assert_eq!(field(uv_data, "uv_width"), Value::Scalar(Scalar::new(0.25)));
+        assert_eq!(field(uv_data, "uv_height"), Value::Scalar(Scalar::new(0.5)));
+    }
+}
+
+#[test]
+fn test_runtime_playback_matches_editor() {
+    use crate::state::{Document, DocumentCommand};
+    use std::time::Duration;
+
+    let directory = std::env::temp_dir().join("tiger-runtime-playback");
+    let template = Path::new(env!("CARGO_MANIFEST_DIR")).join("res/templates/tiger.json.liquid");
+
+    for is_looping in &[true, false] {
+        let mut document = Document::new(directory.join("hero.tiger"));
+        let mut texture_layout = TextureLayout::new();
+        let mut frames = Vec::new();
+        let names = ["walk_0.png", "walk_1.png", "walk_2.png"];
+        for (index, name) in names.iter().enumerate() {
+            let source = directory.join(name);
+            frames.push(document.sheet.add_frame(&source));
+            texture_layout.insert(
+                (0, source),
+                PackedFrame {
+                    position_in_sheet: (index as u32 * 16, 0),
+                    size_in_sheet: (16, 32),
+                },
+            );
+        }
+
+        let animation = document.sheet.add_animation_with_name("walk");
+        animation.set_is_looping(*is_looping);
+        for (index, (frame, duration)) in frames.iter().zip(&[100, 250, 40]).enumerate() {
+            animation.insert_frame(*frame, index).unwrap();
+            let keyframe = animation.get_frame_mut(index).unwrap();
+            keyframe.set_duration(*duration);
+        }
+        let animation_id = animation.get_id();
+
+        let export_settings = ExportSettings {
+            format: ExportFormat::Template(template.clone()),
+            texture_destination: directory.join("hero.png"),
+            metadata_destination: directory.join("hero.json"),
+            metadata_paths_root: directory.clone(),
+        };
+        let atlas_size = vec2(48, 32);
+        let json = export_sheet(
+            &document.sheet,
+            0,
+            &export_settings,
+            &texture_layout,
+            atlas_size,
+        )
+        .unwrap();
+        let runtime_sheet = tiger_runtime::Sheet::from_json(&json).unwrap();
+        let mut player = tiger_runtime::AnimationPlayer::new(&runtime_sheet, "walk").unwrap();
+
+        document
+            .process_command(&DocumentCommand::EditAnimation(animation_id))
+            .unwrap();
+        document
+            .process_command(&DocumentCommand::TogglePlayback)
+            .unwrap();
+
+        let deltas = [16, 33, 7, 120, 250, 1, 390, 16, 500];
+        for delta in deltas.iter().cycle().take(40) {
+            let delta = Duration::from_millis(*delta);
+            document.tick(delta);
+            player.tick(delta);
+
+            let editor_keyframe = document
+                .sheet
+                .get_animation(animation_id)
+                .unwrap()
+                .get_frame_at(document.view.timeline_clock)
+                .map(|(index, _)| index);
+            let runtime_keyframe = player.get_current_frame().map(|f| f.keyframe_index);
+            assert_eq!(player.get_clock(), document.view.timeline_clock);
+            assert_eq!(runtime_keyframe, editor_keyframe);
+        }
+    }
+}
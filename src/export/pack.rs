@@ -1,11 +1,15 @@
 use failure::Error;
-use image::DynamicImage;
+use image::{DynamicImage, RgbaImage};
+use rayon::prelude::*;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
 use texture_packer::exporter::ImageExporter;
 use texture_packer::importer::ImageImporter;
 use texture_packer::{TexturePacker, TexturePackerConfig};
 
+use crate::export::TextureLayout;
 use crate::sheet::Sheet;
 
 #[derive(Fail, Debug)]
@@ -16,6 +20,7 @@ pub enum PackError {
     PackerExportError,
 }
 
+#[derive(Clone, Debug)]
 pub struct PackedFrame {
     pub position_in_sheet: (u32, u32),
     pub size_in_sheet: (u32, u32),
@@ -23,7 +28,7 @@ pub struct PackedFrame {
 
 pub struct PackedSheet {
     texture: DynamicImage,
-    layout: HashMap<PathBuf, PackedFrame>,
+    layout: TextureLayout,
 }
 
 impl PackedSheet {
@@ -31,12 +36,25 @@ impl PackedSheet {
         &self.texture
     }
 
-    pub fn get_layout(&self) -> &HashMap<PathBuf, PackedFrame> {
+    pub fn get_layout(&self) -> &TextureLayout {
         &self.layout
     }
 }
 
-pub fn pack_sheet(sheet: &Sheet) -> Result<PackedSheet, Error> {
+pub type DecodedFrames = HashMap<PathBuf, (Arc<RgbaImage>, SystemTime)>;
+
+fn load_frame(source: &Path, decoded_frames: &DecodedFrames) -> Result<DynamicImage, Error> {
+    if let Some((pixels, decoded_modified)) = decoded_frames.get(source) {
+        let modified = std::fs::metadata(source).and_then(|m| m.modified()).ok();
+        if modified == Some(*decoded_modified) {
+            return Ok(DynamicImage::ImageRgba8(pixels.as_ref().clone()));
+        }
+    }
+    Ok(ImageImporter::import_from_file(source).map_err(|_| PackError::FrameReadError)?)
+}
+
+// Sheets packed together share a single texture, images used by several of them are only packed once
+pub fn pack_sheets(sheets: &[Sheet], decoded_frames: &DecodedFrames) -> Result<PackedSheet, Error> {
     let config = TexturePackerConfig {
         max_width: 4096, // TODO configurable / dynamic based on widest frame?
         max_height: std::u32::MAX,
@@ -49,17 +67,24 @@ pub fn pack_sheet(sheet: &Sheet) -> Result<PackedSheet, Error> {
 
     let mut packer = TexturePacker::new_skyline(config);
 
-    for frame in sheet.frames_iter() {
-        let source = frame.get_source();
-        let texture =
-            ImageImporter::import_from_file(source).map_err(|_| PackError::FrameReadError)?;
+    let mut sources: Vec<&Path> = Vec::new();
+    for frame in sheets.iter().flat_map(|s| s.frames_iter()) {
+        if !sources.contains(&frame.get_source()) {
+            sources.push(frame.get_source());
+        }
+    }
+    let textures = sources
+        .par_iter()
+        .map(|source| load_frame(source, decoded_frames))
+        .collect::<Result<Vec<DynamicImage>, Error>>()?;
 
+    for (source, texture) in sources.iter().zip(textures.into_iter()) {
         let name = source.to_string_lossy();
         packer.pack_own(name.to_string(), texture);
     }
 
     let texture = ImageExporter::export(&packer).map_err(|_| PackError::PackerExportError)?;
-    let layout = packer
+    let packed_frames: HashMap<PathBuf, PackedFrame> = packer
         .get_frames()
         .iter()
         .map(|(k, v)| {
@@ -73,5 +98,118 @@ pub fn pack_sheet(sheet: &Sheet) -> Result<PackedSheet, Error> {
         })
         .collect();
 
+    let mut layout = TextureLayout::new();
+    for (sheet_index, sheet) in sheets.iter().enumerate() {
+        for frame in sheet.frames_iter() {
+            if let Some(packed_frame) = packed_frames.get(frame.get_source()) {
+                layout.insert(
+                    (sheet_index, frame.get_source().to_owned()),
+                    packed_frame.clone(),
+                );
+            }
+        }
+    }
+
     Ok(PackedSheet { texture, layout })
 }
+
+// Timings depend on the machine, run with `cargo test -- --ignored` to compare them
+#[test]
+#[ignore]
+fn benchmark_pack_synthetic_sheet() {
+    let directory =
+        std::env::temp_dir().join(format!("tiger-pack-benchmark-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let mut sheet = Sheet::default();
+    let mut decoded_frames = DecodedFrames::new();
+    for i in 0..500 {
+        let path = directory.join(format!("frame_{}.png", i));
+        let image =
+            RgbaImage::from_fn(64, 64, |x, y| image::Rgba([x as u8, y as u8, i as u8, 255]));
+        image.save(&path).unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        decoded_frames.insert(path.clone(), (Arc::new(image), modified));
+        sheet.add_frame(&path);
+    }
+    let sheets = std::slice::from_ref(&sheet);
+
+    let start = std::time::Instant::now();
+    let from_disk = pack_sheets(sheets, &DecodedFrames::new()).unwrap();
+    let from_disk_duration = start.elapsed();
+
+    let start = std::time::Instant::now();
+    let from_memory = pack_sheets(sheets, &decoded_frames).unwrap();
+    let from_memory_duration = start.elapsed();
+
+    std::fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(from_disk.get_layout().len(), 500);
+    assert_eq!(from_memory.get_layout().len(), 500);
+    println!(
+        "Packed from disk in {:?}, from memory in {:?}",
+        from_disk_duration, from_memory_duration
+    );
+}
+
+#[test]
+fn test_pack_reuses_decoded_frames() {
+    let directory =
+        std::env::temp_dir().join(format!("tiger-pack-decoded-frames-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("frame.png");
+    RgbaImage::new(8, 8).save(&path).unwrap();
+    let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+    let mut sheet = Sheet::default();
+    sheet.add_frame(&path);
+    let sheets = std::slice::from_ref(&sheet);
+    let packed_size = |decoded_frames: &DecodedFrames| {
+        let packed_sheet = pack_sheets(sheets, decoded_frames).unwrap();
+        packed_sheet.get_layout()[&(0, path.clone())].size_in_sheet
+    };
+
+    // Pixels decoded from the current file are used as they are
+    let mut decoded_frames = DecodedFrames::new();
+    decoded_frames.insert(path.clone(), (Arc::new(RgbaImage::new(16, 16)), modified));
+    assert_eq!(packed_size(&decoded_frames), (16, 16));
+
+    // Pixels decoded from an older version of the file are not
+    let stale = modified - std::time::Duration::from_secs(1);
+    decoded_frames.insert(path.clone(), (Arc::new(RgbaImage::new(16, 16)), stale));
+    assert_eq!(packed_size(&decoded_frames), (8, 8));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn test_pack_shared_atlas() {
+    let directory =
+        std::env::temp_dir().join(format!("tiger-pack-shared-atlas-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let image_path = |name: &str| {
+        let path = directory.join(name);
+        RgbaImage::new(8, 8).save(&path).unwrap();
+        path
+    };
+    let crate_image = image_path("crate.png");
+    let barrel_image = image_path("barrel.png");
+    let spark_image = image_path("spark.png");
+
+    let mut crate_sheet = Sheet::default();
+    crate_sheet.add_frame(&crate_image);
+    crate_sheet.add_frame(&spark_image);
+    let mut barrel_sheet = Sheet::default();
+    barrel_sheet.add_frame(&barrel_image);
+    barrel_sheet.add_frame(&spark_image);
+
+    let packed_sheet = pack_sheets(&[crate_sheet, barrel_sheet], &DecodedFrames::new()).unwrap();
+    let layout = packed_sheet.get_layout();
+    assert_eq!(layout.len(), 4);
+    assert!(layout.contains_key(&(0, crate_image)));
+    assert!(layout.contains_key(&(1, barrel_image)));
+    assert_eq!(
+        layout[&(0, spark_image.clone())].position_in_sheet,
+        layout[&(1, spark_image)].position_in_sheet
+    );
+    std::fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,425 @@
+use euclid::*;
+use failure::Error;
+use gif::SetParameter;
+use image::{Rgba, RgbaImage};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::import::{
+    unused_file_path, ImportBatch, ImportError, ImportedAnimation, ImportedKeyframe,
+};
+use crate::sheet::constants::DEFAULT_FRAME_DURATION;
+
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+struct DecodedAnimation {
+    frames: Vec<(RgbaImage, u32)>, // Pixels and duration in ms
+    is_looping: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Disposal {
+    None,
+    Background,
+    Previous,
+}
+
+// Accumulates partial frames into full images, as described by GIF and APNG
+struct Canvas {
+    image: RgbaImage,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Canvas {
+        Canvas {
+            image: RgbaImage::new(width, height),
+        }
+    }
+
+    fn draw(&mut self, patch: &RgbaImage, position: Vector2D<u32>, blend: bool) -> RgbaImage {
+        let (width, height) = self.image.dimensions();
+        for (x, y, pixel) in patch.enumerate_pixels() {
+            let (canvas_x, canvas_y) = (position.x + x, position.y + y);
+            if canvas_x >= width || canvas_y >= height {
+                continue;
+            }
+            if blend && pixel.data[3] == 0 {
+                continue;
+            }
+            let destination = self.image.get_pixel_mut(canvas_x, canvas_y);
+            *destination = if blend {
+                blend_over(*destination, *pixel)
+            } else {
+                *pixel
+            };
+        }
+        self.image.clone()
+    }
+
+    fn dispose(&mut self, disposal: Disposal, area: Rect<u32>, previous: &RgbaImage) {
+        if disposal == Disposal::None {
+            return;
+        }
+        let (width, height) = self.image.dimensions();
+        for y in area.min_y()..area.max_y().min(height) {
+            for x in area.min_x()..area.max_x().min(width) {
+                let pixel = match disposal {
+                    Disposal::Previous => *previous.get_pixel(x, y),
+                    _ => Rgba([0, 0, 0, 0]),
+                };
+                self.image.put_pixel(x, y, pixel);
+            }
+        }
+    }
+}
+
+fn blend_over(under: Rgba<u8>, over: Rgba<u8>) -> Rgba<u8> {
+    let over_alpha = f32::from(over.data[3]) / 255.0;
+    let under_alpha = f32::from(under.data[3]) / 255.0 * (1.0 - over_alpha);
+    let alpha = over_alpha + under_alpha;
+    if alpha <= 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    let mut blended = [0; 4];
+    for channel in 0..3 {
+        let color = (f32::from(over.data[channel]) * over_alpha
+            + f32::from(under.data[channel]) * under_alpha)
+            / alpha;
+        blended[channel] = color.round() as u8;
+    }
+    blended[3] = (alpha * 255.0).round() as u8;
+    Rgba(blended)
+}
+
+fn duration_or_default(duration: u32) -> u32 {
+    if duration == 0 {
+        DEFAULT_FRAME_DURATION
+    } else {
+        duration
+    }
+}
+
+fn read_gif_sub_blocks<'a>(bytes: &'a [u8], cursor: &mut usize) -> Option<Vec<&'a [u8]>> {
+    let mut sub_blocks = Vec::new();
+    loop {
+        let size = usize::from(*bytes.get(*cursor)?);
+        *cursor += 1;
+        if size == 0 {
+            return Some(sub_blocks);
+        }
+        sub_blocks.push(bytes.get(*cursor..*cursor + size)?);
+        *cursor += size;
+    }
+}
+
+// The looping extension comes before the first image, so parsing stops there
+fn read_gif_loop_count(bytes: &[u8]) -> Option<u16> {
+    if !bytes.starts_with(b"GIF") {
+        return None;
+    }
+    let global_color_table = *bytes.get(10)?;
+    let mut cursor = 13;
+    if global_color_table & 0x80 != 0 {
+        cursor += 3 << ((global_color_table & 0x07) + 1);
+    }
+    while *bytes.get(cursor)? == 0x21 {
+        let label = *bytes.get(cursor + 1)?;
+        cursor += 2;
+        let sub_blocks = read_gif_sub_blocks(bytes, &mut cursor)?;
+        if label == 0xFF && sub_blocks.first() == Some(&&b"NETSCAPE2.0"[..]) {
+            return match sub_blocks.get(1) {
+                Some(data) if data.len() >= 3 && data[0] == 1 => {
+                    Some(u16::from(data[1]) | (u16::from(data[2]) << 8))
+                }
+                _ => None,
+            };
+        }
+    }
+    None
+}
+
+fn decode_gif(path: &Path) -> Result<DecodedAnimation, Error> {
+    let loop_count = read_gif_loop_count(&std::fs::read(path)?);
+
+    let mut decoder = gif::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set(gif::ColorOutput::RGBA);
+    let mut reader = decoder.read_info()?;
+
+    let mut canvas = Canvas::new(u32::from(reader.width()), u32::from(reader.height()));
+    let mut frames = Vec::new();
+    while let Some(frame) = reader.read_next_frame()? {
+        let area = rect(
+            u32::from(frame.left),
+            u32::from(frame.top),
+            u32::from(frame.width),
+            u32::from(frame.height),
+        );
+        let patch = RgbaImage::from_raw(area.size.width, area.size.height, frame.buffer.to_vec())
+            .ok_or(ImportError::InvalidAnimationFrame)?;
+        let previous = canvas.image.clone();
+        let composited = canvas.draw(&patch, area.origin.to_vector(), true);
+        frames.push((composited, duration_or_default(u32::from(frame.delay) * 10)));
+        let disposal = match frame.dispose {
+            gif::DisposalMethod::Background => Disposal::Background,
+            gif::DisposalMethod::Previous => Disposal::Previous,
+            _ => Disposal::None,
+        };
+        canvas.dispose(disposal, area, &previous);
+    }
+
+    Ok(DecodedAnimation {
+        frames,
+        is_looping: loop_count == Some(0),
+    })
+}
+
+fn read_png_chunks(bytes: &[u8]) -> Result<Vec<([u8; 4], &[u8])>, Error> {
+    if bytes.len() < PNG_SIGNATURE.len() || bytes[..PNG_SIGNATURE.len()] != PNG_SIGNATURE {
+        return Err(ImportError::InvalidAnimationFrame.into());
+    }
+    let mut chunks = Vec::new();
+    let mut cursor = PNG_SIGNATURE.len();
+    while cursor + 8 <= bytes.len() {
+        let length = read_u32(&bytes[cursor..]) as usize;
+        let mut chunk_type = [0; 4];
+        chunk_type.copy_from_slice(&bytes[cursor + 4..cursor + 8]);
+        let data = bytes
+            .get(cursor + 8..cursor + 8 + length)
+            .ok_or(ImportError::InvalidAnimationFrame)?;
+        chunks.push((chunk_type, data));
+        cursor += 12 + length;
+    }
+    Ok(chunks)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    (u32::from(bytes[0]) << 24)
+        | (u32::from(bytes[1]) << 16)
+        | (u32::from(bytes[2]) << 8)
+        | u32::from(bytes[3])
+}
+
+fn read_u16(bytes: &[u8]) -> u16 {
+    (u16::from(bytes[0]) << 8) | u16::from(bytes[1])
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn write_png_chunk(output: &mut Vec<u8>, chunk_type: &[u8], data: &[u8]) {
+    output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = output.len();
+    output.extend_from_slice(chunk_type);
+    output.extend_from_slice(data);
+    let crc = crc32(&output[start..]);
+    output.extend_from_slice(&crc.to_be_bytes());
+}
+
+struct ApngFrameControl {
+    area: Rect<u32>,
+    duration: u32,
+    disposal: Disposal,
+    blend: bool,
+}
+
+impl ApngFrameControl {
+    fn read(data: &[u8]) -> Result<ApngFrameControl, Error> {
+        if data.len() < 26 {
+            return Err(ImportError::InvalidAnimationFrame.into());
+        }
+        let delay_numerator = u32::from(read_u16(&data[20..]));
+        let delay_denominator = match read_u16(&data[22..]) {
+            0 => 100,
+            d => u32::from(d),
+        };
+        Ok(ApngFrameControl {
+            area: rect(
+                read_u32(&data[12..]),
+                read_u32(&data[16..]),
+                read_u32(&data[4..]),
+                read_u32(&data[8..]),
+            ),
+            duration: delay_numerator * 1000 / delay_denominator,
+            disposal: match data[24] {
+                1 => Disposal::Background,
+                2 => Disposal::Previous,
+                _ => Disposal::None,
+            },
+            blend: data[25] == 1,
+        })
+    }
+}
+
+// Wraps the image data of one APNG frame into a standalone PNG file
+fn build_apng_frame(
+    header: &[u8],
+    ancillary_chunks: &[([u8; 4], &[u8])],
+    area: Rect<u32>,
+    image_data: &[Vec<u8>],
+) -> Result<RgbaImage, Error> {
+    let mut png = PNG_SIGNATURE.to_vec();
+    let mut frame_header = header.to_vec();
+    frame_header[0..4].copy_from_slice(&area.size.width.to_be_bytes());
+    frame_header[4..8].copy_from_slice(&area.size.height.to_be_bytes());
+    write_png_chunk(&mut png, b"IHDR", &frame_header);
+    for (chunk_type, data) in ancillary_chunks {
+        write_png_chunk(&mut png, chunk_type, data);
+    }
+    for data in image_data {
+        write_png_chunk(&mut png, b"IDAT", data);
+    }
+    write_png_chunk(&mut png, b"IEND", &[]);
+    Ok(image::load_from_memory_with_format(&png, image::PNG)?.to_rgba())
+}
+
+fn decode_apng(bytes: &[u8]) -> Result<Option<DecodedAnimation>, Error> {
+    let chunks = read_png_chunks(bytes)?;
+    let header = match chunks.iter().find(|(t, _)| t == b"IHDR") {
+        Some((_, data)) if data.len() >= 13 => *data,
+        _ => return Err(ImportError::InvalidAnimationFrame.into()),
+    };
+    let num_plays = match chunks.iter().find(|(t, _)| t == b"acTL") {
+        Some((_, data)) if data.len() >= 8 => read_u32(&data[4..]),
+        _ => return Ok(None),
+    };
+
+    let ancillary_chunks: Vec<([u8; 4], &[u8])> = chunks
+        .iter()
+        .take_while(|(t, _)| t != b"IDAT")
+        .filter(|(t, _)| t == b"PLTE" || t == b"tRNS")
+        .cloned()
+        .collect();
+
+    let mut canvas = Canvas::new(read_u32(header), read_u32(&header[4..]));
+    let mut frames = Vec::new();
+    let mut pending: Option<(ApngFrameControl, Vec<Vec<u8>>)> = None;
+    let mut flush = |pending: Option<(ApngFrameControl, Vec<Vec<u8>>)>,
+                     frames: &mut Vec<(RgbaImage, u32)>|
+     -> Result<(), Error> {
+        if let Some((control, image_data)) = pending {
+            let patch = build_apng_frame(header, &ancillary_chunks, control.area, &image_data)?;
+            let previous = canvas.image.clone();
+            let composited = canvas.draw(&patch, control.area.origin.to_vector(), control.blend);
+            frames.push((composited, duration_or_default(control.duration)));
+            canvas.dispose(control.disposal, control.area, &previous);
+        }
+        Ok(())
+    };
+
+    for (chunk_type, data) in &chunks {
+        match chunk_type {
+            b"fcTL" => {
+                flush(pending.take(), &mut frames)?;
+                pending = Some((ApngFrameControl::read(data)?, Vec::new()));
+            }
+            b"IDAT" => {
+                if let Some((_, image_data)) = &mut pending {
+                    image_data.push(data.to_vec());
+                }
+            }
+            b"fdAT" if data.len() >= 4 => {
+                if let Some((_, image_data)) = &mut pending {
+                    image_data.push(data[4..].to_vec());
+                }
+            }
+            _ => (),
+        }
+    }
+    flush(pending.take(), &mut frames)?;
+
+    Ok(Some(DecodedAnimation {
+        frames,
+        is_looping: num_plays == 0,
+    }))
+}
+
+fn decode_animation(path: &Path) -> Result<Option<DecodedAnimation>, Error> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "gif" => Ok(Some(decode_gif(path)?)),
+        // PNG files which cannot be read as APNG are imported as regular images
+        "png" => Ok(decode_apng(&std::fs::read(path)?).unwrap_or(None)),
+        _ => Ok(None),
+    }
+}
+
+// Returns None when the image is not animated and should be imported as a single frame
+pub fn import_animated_image<T: AsRef<Path>>(path: T) -> Result<Option<ImportBatch>, Error> {
+    let animation = match decode_animation(path.as_ref())? {
+        Some(a) => a,
+        None => return Ok(None),
+    };
+
+    let stem = path
+        .as_ref()
+        .file_stem()
+        .ok_or(ImportError::InvalidSourceName)?
+        .to_string_lossy()
+        .into_owned();
+    let directory = path.as_ref().parent().unwrap_or_else(|| Path::new(""));
+    let digits = animation.frames.len().to_string().len();
+
+    let mut batch = ImportBatch::default();
+    let mut keyframes = Vec::new();
+    for (index, (image, duration)) in animation.frames.iter().enumerate() {
+        let file_stem = format!("{}_{:0width$}", stem, index, width = digits);
+        let destination = unused_file_path(directory, &file_stem, "png");
+        image.save(&destination)?;
+        keyframes.push(ImportedKeyframe {
+            frame: destination.clone(),
+            duration: *duration,
+            offset: vec2(0, 0),
+        });
+        batch.frames.push(destination);
+    }
+    batch.animations.push(ImportedAnimation {
+        name: stem,
+        is_looping: animation.is_looping,
+        keyframes,
+    });
+
+    Ok(Some(batch))
+}
+
+#[test]
+fn test_animated_image_helpers() {
+    assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+
+    let header = b"GIF89a\x01\x00\x01\x00\x00\x00\x00";
+    let netscape = b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x05\x00\x00";
+    let comment = b"\x21\xFE\x10NETSCAPE2.0\x03\x01\x00\x00\x00\x00";
+    let image = b"\x2C\x00\x00\x00\x00\x01\x00\x01\x00\x00";
+    let gif = |blocks: &[&[u8]]| {
+        let mut bytes = header.to_vec();
+        for block in blocks {
+            bytes.extend_from_slice(block);
+        }
+        bytes
+    };
+    assert_eq!(read_gif_loop_count(&gif(&[netscape])), Some(5));
+    assert_eq!(read_gif_loop_count(&gif(&[comment, netscape])), Some(5));
+    assert_eq!(read_gif_loop_count(&gif(&[comment])), None);
+    assert_eq!(read_gif_loop_count(&gif(&[image, netscape])), None);
+    assert_eq!(read_gif_loop_count(b"GIF89a"), None);
+
+    let opaque_red = Rgba([255, 0, 0, 255]);
+    let transparent = Rgba([0, 0, 0, 0]);
+    assert_eq!(blend_over(transparent, opaque_red), opaque_red);
+    assert_eq!(blend_over(opaque_red, transparent), opaque_red);
+}
@@ -0,0 +1,287 @@
+use euclid::*;
+use failure::Error;
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use crate::import::{
+    unused_file_path, ImportBatch, ImportError, ImportedAnimation, ImportedHitbox, ImportedKeyframe,
+};
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct AsepriteRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct AsepriteSize {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsepriteFrame {
+    frame: AsepriteRect,
+    #[serde(default)]
+    rotated: bool,
+    sprite_source_size: AsepriteRect,
+    source_size: AsepriteSize,
+    duration: u32,
+}
+
+// Frames in the hash layout are keyed by file name, in the order Aseprite wrote them
+#[derive(Debug)]
+struct AsepriteFrameHash(Vec<AsepriteFrame>);
+
+impl<'de> Deserialize<'de> for AsepriteFrameHash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FrameHashVisitor;
+
+        impl<'de> Visitor<'de> for FrameHashVisitor {
+            type Value = AsepriteFrameHash;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of Aseprite frames")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut access: M) -> Result<Self::Value, M::Error> {
+                let mut frames = Vec::new();
+                while let Some((_, frame)) = access.next_entry::<String, AsepriteFrame>()? {
+                    frames.push(frame);
+                }
+                Ok(AsepriteFrameHash(frames))
+            }
+        }
+
+        deserializer.deserialize_map(FrameHashVisitor)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum AsepriteFrames {
+    Array(Vec<AsepriteFrame>),
+    Hash(AsepriteFrameHash),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum AsepriteDirection {
+    Forward,
+    Reverse,
+    Pingpong,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct AsepriteTag {
+    name: String,
+    from: usize,
+    to: usize,
+    direction: AsepriteDirection,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct AsepriteSliceKey {
+    frame: usize,
+    bounds: AsepriteRect,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct AsepriteSlice {
+    name: String,
+    keys: Vec<AsepriteSliceKey>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsepriteMeta {
+    image: PathBuf,
+    #[serde(default)]
+    frame_tags: Vec<AsepriteTag>,
+    #[serde(default)]
+    slices: Vec<AsepriteSlice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AsepriteSheet {
+    frames: AsepriteFrames,
+    meta: AsepriteMeta,
+}
+
+fn get_tag_frames(tag: &AsepriteTag) -> Vec<usize> {
+    let forward: Vec<usize> = (tag.from..=tag.to).collect();
+    match tag.direction {
+        AsepriteDirection::Forward => forward,
+        AsepriteDirection::Reverse => forward.into_iter().rev().collect(),
+        AsepriteDirection::Pingpong => {
+            let mut frames = forward.clone();
+            if forward.len() > 2 {
+                frames.extend(forward[1..forward.len() - 1].iter().rev());
+            }
+            frames
+        }
+    }
+}
+
+fn get_slice_bounds(slice: &AsepriteSlice, frame_index: usize) -> Option<AsepriteRect> {
+    slice
+        .keys
+        .iter()
+        .filter(|k| k.frame <= frame_index)
+        .max_by_key(|k| k.frame)
+        .map(|k| k.bounds)
+}
+
+pub fn import_aseprite<T: AsRef<Path>, U: AsRef<Path>>(
+    json_path: T,
+    frames_directory: U,
+) -> Result<ImportBatch, Error> {
+    if frames_directory.as_ref().as_os_str().is_empty() {
+        return Err(ImportError::MissingDestination.into());
+    }
+    let file = BufReader::new(File::open(json_path.as_ref())?);
+    let aseprite_sheet: AsepriteSheet = serde_json::from_reader(file)?;
+    let frames: Vec<AsepriteFrame> = match aseprite_sheet.frames {
+        AsepriteFrames::Array(a) => a,
+        AsepriteFrames::Hash(h) => h.0,
+    };
+    let meta = aseprite_sheet.meta;
+
+    let stem = json_path
+        .as_ref()
+        .file_stem()
+        .ok_or(ImportError::InvalidSourceName)?
+        .to_string_lossy()
+        .into_owned();
+    let mut image_path = json_path.as_ref().to_owned();
+    image_path.pop();
+    let mut image = image::open(image_path.join(&meta.image))?.to_rgba();
+
+    std::fs::create_dir_all(frames_directory.as_ref())?;
+    let mut batch = ImportBatch::default();
+    let digits = frames.len().to_string().len();
+    for (index, frame) in frames.iter().enumerate() {
+        if frame.rotated {
+            return Err(ImportError::UnsupportedRotatedFrame.into());
+        }
+
+        // Restore trimmed pixels to their position in the untrimmed frame
+        let mut frame_image = image::RgbaImage::new(frame.source_size.w, frame.source_size.h);
+        let packed_image = image::imageops::crop(
+            &mut image,
+            frame.frame.x,
+            frame.frame.y,
+            frame.frame.w,
+            frame.frame.h,
+        )
+        .to_image();
+        image::imageops::replace(
+            &mut frame_image,
+            &packed_image,
+            frame.sprite_source_size.x,
+            frame.sprite_source_size.y,
+        );
+
+        let file_stem = format!("{}_{:0width$}", stem, index, width = digits);
+        let destination = unused_file_path(frames_directory.as_ref(), &file_stem, "png");
+        frame_image.save(&destination)?;
+
+        let frame_size: Vector2D<i32> = vec2(frame.source_size.w, frame.source_size.h).to_i32();
+        for slice in &meta.slices {
+            if let Some(bounds) = get_slice_bounds(slice, index) {
+                batch.hitboxes.push(ImportedHitbox {
+                    frame: destination.clone(),
+                    name: slice.name.clone(),
+                    position: vec2(bounds.x, bounds.y).to_i32() - frame_size / 2,
+                    size: vec2(bounds.w, bounds.h),
+                });
+            }
+        }
+
+        batch.frames.push(destination);
+    }
+
+    let mut tags = meta.frame_tags.clone();
+    if tags.is_empty() && !frames.is_empty() {
+        tags.push(AsepriteTag {
+            name: stem.clone(),
+            from: 0,
+            to: frames.len() - 1,
+            direction: AsepriteDirection::Forward,
+        });
+    }
+    for tag in &tags {
+        let mut keyframes = Vec::new();
+        for frame_index in get_tag_frames(tag) {
+            let frame = frames
+                .get(frame_index)
+                .ok_or(ImportError::InvalidFrameReference)?;
+            keyframes.push(ImportedKeyframe {
+                frame: batch.frames[frame_index].clone(),
+                duration: frame.duration,
+                offset: vec2(0, 0),
+            });
+        }
+        batch.animations.push(ImportedAnimation {
+            name: tag.name.clone(),
+            is_looping: true,
+            keyframes,
+        });
+    }
+
+    Ok(batch)
+}
+
+#[test]
+fn test_aseprite_tag_directions() {
+    let mut tag = AsepriteTag {
+        name: "walk".to_owned(),
+        from: 2,
+        to: 5,
+        direction: AsepriteDirection::Forward,
+    };
+    assert_eq!(get_tag_frames(&tag), vec![2, 3, 4, 5]);
+    tag.direction = AsepriteDirection::Reverse;
+    assert_eq!(get_tag_frames(&tag), vec![5, 4, 3, 2]);
+    tag.direction = AsepriteDirection::Pingpong;
+    assert_eq!(get_tag_frames(&tag), vec![2, 3, 4, 5, 4, 3]);
+}
+
+#[test]
+fn test_aseprite_layouts() {
+    let frame = r#"{ "frame": { "x": 0, "y": 0, "w": 8, "h": 8 }, "rotated": false, "trimmed": false,
+        "spriteSourceSize": { "x": 0, "y": 0, "w": 8, "h": 8 }, "sourceSize": { "w": 8, "h": 8 },
+        "duration": 120 }"#;
+    let meta = r#"{ "image": "sheet.png", "size": { "w": 16, "h": 8 } }"#;
+
+    let hash = format!(
+        r#"{{ "frames": {{ "b 10": {}, "b 2": {} }}, "meta": {} }}"#,
+        frame, frame, meta
+    );
+    let hash: AsepriteSheet = serde_json::from_str(&hash).unwrap();
+    match hash.frames {
+        AsepriteFrames::Hash(h) => assert_eq!(h.0.len(), 2),
+        _ => panic!("Expected hash layout"),
+    }
+
+    let array = format!(
+        r#"{{ "frames": [ {{ "filename": "a 0", "frame": {{ "x": 8, "y": 0, "w": 8, "h": 8 }},
+            "spriteSourceSize": {{ "x": 0, "y": 0, "w": 8, "h": 8 }}, "sourceSize": {{ "w": 8, "h": 8 }},
+            "duration": 80 }} ], "meta": {} }}"#,
+        meta
+    );
+    let array: AsepriteSheet = serde_json::from_str(&array).unwrap();
+    match array.frames {
+        AsepriteFrames::Array(a) => {
+            assert_eq!(a[0].frame.x, 8);
+            assert_eq!(a[0].duration, 80);
+        }
+        _ => panic!("Expected array layout"),
+    }
+}
@@ -0,0 +1,157 @@
+use euclid::*;
+use failure::Error;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::import::{ImportBatch, ImportError, ImportedAnimation, ImportedKeyframe};
+use crate::sheet::constants::DEFAULT_FRAME_DURATION;
+
+const ANIMATION_TOKEN: &str = "{animation}";
+const FRAME_TOKEN: &str = "{frame}";
+const FOLDER_IMPORT_FILE_EXTENSIONS: [&str; 3] = ["png", "tga", "bmp"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FolderImportSettings {
+    pub folder: PathBuf,
+    pub pattern: String,
+    pub frame_duration: u32,
+}
+
+impl FolderImportSettings {
+    pub fn new() -> FolderImportSettings {
+        FolderImportSettings {
+            folder: PathBuf::new(),
+            pattern: format!("{}_{}", ANIMATION_TOKEN, FRAME_TOKEN),
+            frame_duration: DEFAULT_FRAME_DURATION,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct NamePattern {
+    before: String,
+    separator: String,
+    after: String,
+}
+
+impl NamePattern {
+    fn parse(pattern: &str) -> Result<NamePattern, Error> {
+        let animation = pattern
+            .find(ANIMATION_TOKEN)
+            .ok_or(ImportError::InvalidNamePattern)?;
+        let frame = pattern
+            .find(FRAME_TOKEN)
+            .ok_or(ImportError::InvalidNamePattern)?;
+        let separator_start = animation + ANIMATION_TOKEN.len();
+        if frame < separator_start
+            || pattern.matches(ANIMATION_TOKEN).count() != 1
+            || pattern.matches(FRAME_TOKEN).count() != 1
+        {
+            return Err(ImportError::InvalidNamePattern.into());
+        }
+        Ok(NamePattern {
+            before: pattern[..animation].to_owned(),
+            separator: pattern[separator_start..frame].to_owned(),
+            after: pattern[frame + FRAME_TOKEN.len()..].to_owned(),
+        })
+    }
+
+    // Returns the animation name and frame number encoded in a file stem
+    fn match_stem(&self, stem: &str) -> Option<(String, u64)> {
+        if !stem.starts_with(&self.before) || !stem.ends_with(&self.after) {
+            return None;
+        }
+        if stem.len() < self.before.len() + self.after.len() {
+            return None;
+        }
+        let middle = &stem[self.before.len()..stem.len() - self.after.len()];
+        let digits_start = middle
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_ascii_digit())
+            .last()
+            .map(|(i, _)| i)?;
+        let number = middle[digits_start..].parse().ok()?;
+        let name = &middle[..digits_start];
+        if !name.ends_with(&self.separator) || name.len() == self.separator.len() {
+            return None;
+        }
+        Some((name[..name.len() - self.separator.len()].to_owned(), number))
+    }
+}
+
+pub fn import_folder(settings: &FolderImportSettings) -> Result<ImportBatch, Error> {
+    let pattern = NamePattern::parse(&settings.pattern)?;
+
+    let mut images: Vec<PathBuf> = std::fs::read_dir(&settings.folder)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && is_importable(p))
+        .collect();
+    images.sort();
+
+    let mut batch = ImportBatch::default();
+    let mut groups: BTreeMap<String, Vec<(u64, PathBuf)>> = BTreeMap::new();
+    for image in images {
+        let stem = image.file_stem().map(|s| s.to_string_lossy().into_owned());
+        if let Some((name, number)) = stem.and_then(|s| pattern.match_stem(&s)) {
+            groups
+                .entry(name)
+                .or_default()
+                .push((number, image.clone()));
+        }
+        batch.frames.push(image);
+    }
+
+    for (name, mut frames) in groups {
+        frames.sort();
+        batch.animations.push(ImportedAnimation {
+            name,
+            is_looping: true,
+            keyframes: frames
+                .into_iter()
+                .map(|(_, frame)| ImportedKeyframe {
+                    frame,
+                    duration: settings.frame_duration.max(1),
+                    offset: vec2(0, 0),
+                })
+                .collect(),
+        });
+    }
+
+    Ok(batch)
+}
+
+fn is_importable(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .map_or(false, |e| {
+            FOLDER_IMPORT_FILE_EXTENSIONS.contains(&e.as_str())
+        })
+}
+
+#[test]
+fn test_name_pattern() {
+    let pattern = NamePattern::parse("{animation}_{frame}").unwrap();
+    assert_eq!(pattern.match_stem("walk_1"), Some(("walk".to_owned(), 1)));
+    assert_eq!(
+        pattern.match_stem("walk_010"),
+        Some(("walk".to_owned(), 10))
+    );
+    assert_eq!(
+        pattern.match_stem("attack_up_2"),
+        Some(("attack_up".to_owned(), 2))
+    );
+    assert_eq!(pattern.match_stem("walk"), None);
+    assert_eq!(pattern.match_stem("walk3"), None);
+    assert_eq!(pattern.match_stem("_3"), None);
+
+    let pattern = NamePattern::parse("hero-{animation}{frame}-final").unwrap();
+    assert_eq!(
+        pattern.match_stem("hero-run12-final"),
+        Some(("run".to_owned(), 12))
+    );
+    assert_eq!(pattern.match_stem("villain-run12-final"), None);
+
+    assert!(NamePattern::parse("{frame}_{animation}").is_err());
+    assert!(NamePattern::parse("{animation}").is_err());
+}
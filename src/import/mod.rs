@@ -0,0 +1,130 @@
+use euclid::*;
+use failure::Error;
+use std::path::{Path, PathBuf};
+
+use crate::sheet::{Frame, FrameId, Sheet};
+
+mod animated;
+mod aseprite;
+mod folder;
+mod split;
+
+pub use animated::*;
+pub use aseprite::*;
+pub use folder::*;
+pub use split::*;
+
+#[derive(Fail, Debug)]
+pub enum ImportError {
+    #[fail(display = "Grid cells must be at least one pixel wide and tall")]
+    InvalidCellSize,
+    #[fail(display = "Source image has no file name")]
+    InvalidSourceName,
+    #[fail(display = "Rotated frames are not supported")]
+    UnsupportedRotatedFrame,
+    #[fail(display = "An animation references a frame which does not exist")]
+    InvalidFrameReference,
+    #[fail(display = "Could not decode a frame of the animated image")]
+    InvalidAnimationFrame,
+    #[fail(display = "Name pattern must contain {{animation}} followed by {{frame}}")]
+    InvalidNamePattern,
+    #[fail(display = "No destination folder was chosen for the imported frames")]
+    MissingDestination,
+}
+
+// Imports never replace existing files, a numeric suffix is added to the name instead
+fn unused_file_path<T: AsRef<Path>>(directory: T, stem: &str, extension: &str) -> PathBuf {
+    let mut path = directory.as_ref().join(format!("{}.{}", stem, extension));
+    let mut index = 2;
+    while path.exists() {
+        path = directory
+            .as_ref()
+            .join(format!("{} {}.{}", stem, index, extension));
+        index += 1;
+    }
+    path
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedKeyframe {
+    pub frame: PathBuf,
+    pub duration: u32,
+    pub offset: Vector2D<i32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedAnimation {
+    pub name: String,
+    pub is_looping: bool,
+    pub keyframes: Vec<ImportedKeyframe>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedHitbox {
+    pub frame: PathBuf,
+    pub name: String,
+    pub position: Vector2D<i32>,
+    pub size: Vector2D<u32>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportBatch {
+    pub frames: Vec<PathBuf>,
+    pub animations: Vec<ImportedAnimation>,
+    pub hitboxes: Vec<ImportedHitbox>,
+}
+
+impl ImportBatch {
+    pub fn apply(&self, sheet: &mut Sheet) -> Result<(), Error> {
+        for frame in &self.frames {
+            sheet.add_frame(frame);
+        }
+        for imported_hitbox in &self.hitboxes {
+            let frame_id = sheet
+                .get_frame_by_source(&imported_hitbox.frame)
+                .map(Frame::get_id)
+                .ok_or(ImportError::InvalidFrameReference)?;
+            let frame = sheet
+                .get_frame_mut(frame_id)
+                .ok_or(ImportError::InvalidFrameReference)?;
+            if frame.has_hitbox_name(&imported_hitbox.name) {
+                continue;
+            }
+            let hitbox = frame.add_hitbox();
+            hitbox.set_position(imported_hitbox.position);
+            hitbox.set_size(imported_hitbox.size);
+            let hitbox_id = hitbox.get_id();
+            frame.rename_hitbox(hitbox_id, &imported_hitbox.name)?;
+        }
+        for imported_animation in &self.animations {
+            let frame_ids: Vec<FrameId> = imported_animation
+                .keyframes
+                .iter()
+                .map(|k| sheet.add_frame(&k.frame))
+                .collect();
+            let animation = sheet.add_animation_with_name(&imported_animation.name);
+            animation.set_is_looping(imported_animation.is_looping);
+            for (index, keyframe) in imported_animation.keyframes.iter().enumerate() {
+                animation.insert_frame(frame_ids[index], index)?;
+                if let Some(animation_frame) = animation.get_frame_mut(index) {
+                    animation_frame.set_duration(keyframe.duration);
+                    animation_frame.set_offset(keyframe.offset);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_unused_file_path() {
+    let directory =
+        std::env::temp_dir().join(format!("tiger-unused-file-path-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let first = unused_file_path(&directory, "walk_0", "png");
+    assert_eq!(first, directory.join("walk_0.png"));
+    std::fs::write(&first, b"").unwrap();
+    let second = unused_file_path(&directory, "walk_0", "png");
+    assert_eq!(second, directory.join("walk_0 2.png"));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
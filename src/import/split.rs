@@ -0,0 +1,198 @@
+use euclid::*;
+use failure::Error;
+use image::RgbaImage;
+use std::path::{Path, PathBuf};
+
+use crate::import::{
+    unused_file_path, ImportBatch, ImportError, ImportedAnimation, ImportedKeyframe,
+};
+use crate::sheet::constants::DEFAULT_FRAME_DURATION;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SplitMode {
+    Grid,
+    Islands,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SplitSettings {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub mode: SplitMode,
+    pub cell_size: Vector2D<u32>,
+    pub margin: Vector2D<u32>,
+    pub spacing: Vector2D<u32>,
+    pub animation_per_row: bool,
+}
+
+impl SplitSettings {
+    pub fn new() -> SplitSettings {
+        SplitSettings {
+            source: PathBuf::new(),
+            destination: PathBuf::new(),
+            mode: SplitMode::Grid,
+            cell_size: vec2(32, 32),
+            margin: vec2(0, 0),
+            spacing: vec2(0, 0),
+            animation_per_row: false,
+        }
+    }
+}
+
+fn get_grid_cells(image_size: Vector2D<u32>, settings: &SplitSettings) -> Vec<Vec<Rect<u32>>> {
+    let mut rows = Vec::new();
+    let mut y = settings.margin.y;
+    while y + settings.cell_size.y <= image_size.y {
+        let mut row = Vec::new();
+        let mut x = settings.margin.x;
+        while x + settings.cell_size.x <= image_size.x {
+            row.push(rect(x, y, settings.cell_size.x, settings.cell_size.y));
+            x += settings.cell_size.x + settings.spacing.x;
+        }
+        rows.push(row);
+        y += settings.cell_size.y + settings.spacing.y;
+    }
+    rows
+}
+
+fn is_opaque(image: &RgbaImage, x: u32, y: u32) -> bool {
+    image.get_pixel(x, y).data[3] > 0
+}
+
+fn is_empty(image: &RgbaImage, cell: &Rect<u32>) -> bool {
+    (cell.min_y()..cell.max_y())
+        .all(|y| (cell.min_x()..cell.max_x()).all(|x| !is_opaque(image, x, y)))
+}
+
+fn find_islands(image: &RgbaImage) -> Vec<Rect<u32>> {
+    let (width, height) = image.dimensions();
+    let mut visited = vec![false; (width * height) as usize];
+    let mut islands = Vec::new();
+
+    for start_y in 0..height {
+        for start_x in 0..width {
+            let start_index = (start_y * width + start_x) as usize;
+            if visited[start_index] || !is_opaque(image, start_x, start_y) {
+                continue;
+            }
+
+            let (mut min_x, mut min_y) = (start_x, start_y);
+            let (mut max_x, mut max_y) = (start_x, start_y);
+            let mut stack = vec![(start_x, start_y)];
+            visited[start_index] = true;
+            while let Some((x, y)) = stack.pop() {
+                min_x = min_x.min(x);
+                min_y = min_y.min(y);
+                max_x = max_x.max(x);
+                max_y = max_y.max(y);
+                for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                    for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                        let index = (ny * width + nx) as usize;
+                        if !visited[index] && is_opaque(image, nx, ny) {
+                            visited[index] = true;
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+            }
+
+            islands.push(rect(min_x, min_y, max_x - min_x + 1, max_y - min_y + 1));
+        }
+    }
+
+    islands.sort_by_key(|r| (r.origin.y, r.origin.x));
+    islands
+}
+
+fn write_cell(image: &mut RgbaImage, cell: &Rect<u32>, destination: &Path) -> Result<(), Error> {
+    let cell_image = image::imageops::crop(
+        image,
+        cell.origin.x,
+        cell.origin.y,
+        cell.size.width,
+        cell.size.height,
+    )
+    .to_image();
+    cell_image.save(destination)?;
+    Ok(())
+}
+
+pub fn split_sheet(settings: &SplitSettings) -> Result<ImportBatch, Error> {
+    if settings.destination.as_os_str().is_empty() {
+        return Err(ImportError::MissingDestination.into());
+    }
+    let mut image = image::open(&settings.source)?.to_rgba();
+    let image_size: Vector2D<u32> = image.dimensions().into();
+    let stem = settings
+        .source
+        .file_stem()
+        .ok_or(ImportError::InvalidSourceName)?
+        .to_string_lossy()
+        .into_owned();
+
+    let rows: Vec<Vec<Rect<u32>>> = match settings.mode {
+        SplitMode::Grid => {
+            if settings.cell_size.x == 0 || settings.cell_size.y == 0 {
+                return Err(ImportError::InvalidCellSize.into());
+            }
+            get_grid_cells(image_size, settings)
+                .into_iter()
+                .map(|row| row.into_iter().filter(|c| !is_empty(&image, c)).collect())
+                .collect()
+        }
+        SplitMode::Islands => vec![find_islands(&image)],
+    };
+
+    let num_cells: usize = rows.iter().map(|r| r.len()).sum();
+    let digits = num_cells.to_string().len();
+
+    std::fs::create_dir_all(&settings.destination)?;
+    let mut batch = ImportBatch::default();
+    let mut cell_index = 0;
+    for (row_index, row) in rows.iter().enumerate() {
+        let mut keyframes = Vec::new();
+        for cell in row {
+            let file_stem = format!("{}_{:0width$}", stem, cell_index, width = digits);
+            let destination = unused_file_path(&settings.destination, &file_stem, "png");
+            write_cell(&mut image, cell, &destination)?;
+            keyframes.push(ImportedKeyframe {
+                duration: DEFAULT_FRAME_DURATION,
+                frame: destination.clone(),
+                offset: vec2(0, 0),
+            });
+            batch.frames.push(destination);
+            cell_index += 1;
+        }
+        if settings.mode == SplitMode::Grid && settings.animation_per_row && !keyframes.is_empty() {
+            batch.animations.push(ImportedAnimation {
+                name: format!("{} {}", stem, row_index + 1),
+                is_looping: true,
+                keyframes,
+            });
+        }
+    }
+
+    Ok(batch)
+}
+
+#[test]
+fn test_split_grid_and_islands() {
+    let mut settings = SplitSettings::new();
+    settings.cell_size = vec2(4, 4);
+    settings.margin = vec2(1, 1);
+    settings.spacing = vec2(2, 2);
+    let rows = get_grid_cells(vec2(16, 11), &settings);
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0], vec![rect(1, 1, 4, 4), rect(7, 1, 4, 4)]);
+    assert_eq!(rows[1][1], rect(7, 7, 4, 4));
+
+    let mut image = RgbaImage::new(8, 8);
+    for &(x, y) in &[(1, 1), (2, 2), (2, 3), (6, 0), (6, 6), (7, 7)] {
+        image.put_pixel(x, y, image::Rgba([255, 0, 0, 255]));
+    }
+    let islands = find_islands(&image);
+    assert_eq!(
+        islands,
+        vec![rect(6, 0, 1, 1), rect(1, 1, 2, 3), rect(6, 6, 2, 2)]
+    );
+}
@@ -12,7 +12,11 @@ extern crate serde_derive;
 use gfx::Device;
 use std::sync::*;
 
+mod cli;
 mod export;
+mod import;
+mod project;
+mod recovery;
 mod sheet;
 mod state;
 mod streamer;
@@ -20,6 +24,8 @@ mod ui;
 mod utils;
 
 const WINDOW_TITLE: &str = "Tiger";
+const AUTOSAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+const EXTERNAL_CHANGES_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
 
 #[derive(Fail, Debug)]
 pub enum MainError {
@@ -68,6 +74,11 @@ struct AsyncResults {
 }
 
 fn main() -> Result<(), failure::Error> {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(result) = cli::run(&args) {
+        return result;
+    }
+
     let mut events_loop = glutin::EventsLoop::new();
     let context = glutin::ContextBuilder::new().with_vsync(true);
     let window = glutin::WindowBuilder::new().with_title(WINDOW_TITLE);
@@ -97,6 +108,15 @@ fn main() -> Result<(), failure::Error> {
     let async_commands: Arc<(Mutex<AsyncCommands>, Condvar)> =
         Arc::new((Mutex::new(Default::default()), Condvar::new()));
     let async_results: Arc<Mutex<AsyncResults>> = Arc::new(Mutex::new(Default::default()));
+
+    // Look for unsaved changes left behind by a previous session
+    async_commands
+        .0
+        .lock()
+        .unwrap()
+        .commands
+        .push(state::AsyncCommand::FindRecoveredSheets);
+
     let state_mutex: Arc<Mutex<state::AppState>> = Arc::new(Mutex::new(Default::default()));
     let texture_cache = Arc::new(Mutex::new(streamer::TextureCache::new()));
     let (streamer_from_disk, streamer_to_gpu) = streamer::init();
@@ -105,6 +125,7 @@ fn main() -> Result<(), failure::Error> {
     // Thread processing async commands without blocking the UI
     let async_commands_for_worker = async_commands.clone();
     let async_results_for_worker = async_results.clone();
+    let texture_cache_for_worker = texture_cache.clone();
     std::thread::spawn(move || loop {
         let commands;
 
@@ -118,16 +139,17 @@ fn main() -> Result<(), failure::Error> {
         }
 
         for command in &commands {
-            let process_result = state::process_async_command(&command);
+            let process_result = state::process_async_command(&command, &texture_cache_for_worker);
             {
                 let mut result_mutex = async_results_for_worker.lock().unwrap();
                 result_mutex.results.push(process_result);
             }
         }
 
-        let &(ref commands_mutex, ref _cvar) = &*async_commands_for_worker;
+        let &(ref commands_mutex, ref cvar) = &*async_commands_for_worker;
         let mut async_commands = commands_mutex.lock().unwrap();
         async_commands.commands.drain(..commands.len());
+        cvar.notify_all();
     });
 
     // Streamer thread
@@ -161,6 +183,8 @@ fn main() -> Result<(), failure::Error> {
     // Main thread
     {
         let mut last_frame = std::time::Instant::now();
+        let mut last_autosave = std::time::Instant::now();
+        let mut last_external_changes_check = std::time::Instant::now();
         let mut quit = false;
 
         loop {
@@ -221,9 +245,36 @@ fn main() -> Result<(), failure::Error> {
                 quit = false;
             }
 
+            // Autosave
+            if now - last_autosave >= AUTOSAVE_INTERVAL {
+                new_commands.autosave(&state);
+                last_autosave = now;
+            }
+
+            // Detect sheets modified by other programs
+            if now - last_external_changes_check >= EXTERNAL_CHANGES_CHECK_INTERVAL {
+                new_commands.check_for_external_changes(&state);
+                last_external_changes_check = now;
+            }
+
             state.tick(delta);
 
             if state.get_exit_state() == Some(state::ExitState::Allowed) {
+                // Pending autosaves would write recovery files again after they are discarded
+                {
+                    let &(ref lock, ref cvar) = &*async_commands;
+                    let mut work = lock.lock().unwrap();
+                    while !work.commands.is_empty() {
+                        work = cvar.wait(work).unwrap();
+                    }
+                }
+
+                // Documents left open were either saved or deliberately not saved
+                for document in state.documents_iter() {
+                    if let Err(e) = recovery::discard(&document.source) {
+                        println!("Error: {}", e);
+                    }
+                }
                 break;
             }
 
@@ -236,8 +287,7 @@ fn main() -> Result<(), failure::Error> {
                             new_commands.append(buffer);
                         }
                         Err(e) => {
-                            // TODO surface to user
-                            println!("Error: {}", e);
+                            new_commands.show_error(&e);
                         }
                     }
                 }
@@ -0,0 +1,256 @@
+use failure::Error;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::sheet::{self, ExportFormat, ExportSettings, Sheet};
+
+// Occurrences of this in the default export file names are replaced by the name of each sheet
+const SHEET_NAME_PLACEHOLDER: &str = "{sheet}";
+
+#[derive(Fail, Debug)]
+pub enum ProjectError {
+    #[fail(display = "Sheet is already part of the project")]
+    SheetAlreadyInProject,
+    #[fail(display = "Sheet is not part of the project")]
+    SheetNotInProject,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+enum Version {
+    Project1,
+}
+const CURRENT_VERSION: Version = Version::Project1;
+
+#[derive(Deserialize)]
+struct Versioned {
+    #[allow(dead_code)]
+    version: Version,
+    project: Project,
+}
+
+#[derive(Serialize)]
+struct VersionedProject<'a> {
+    version: Version,
+    project: &'a Project,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Project {
+    #[serde(skip)]
+    pub source: PathBuf,
+    sheets: Vec<PathBuf>,
+    export_settings: Option<ExportSettings>,
+}
+
+fn sheet_name<T: AsRef<Path>>(sheet_path: T) -> String {
+    sheet_path
+        .as_ref()
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn map_file_name<T: AsRef<Path>, F: Fn(&str) -> String>(path: T, f: F) -> PathBuf {
+    let path = path.as_ref();
+    match path.file_name() {
+        Some(file_name) => path.with_file_name(OsString::from(f(&file_name.to_string_lossy()))),
+        None => path.to_owned(),
+    }
+}
+
+// Fields left empty in the sheet's export settings are taken from the defaults
+fn merge_export_settings(defaults: ExportSettings, overrides: &ExportSettings) -> ExportSettings {
+    let pick = |default: PathBuf, path: &PathBuf| {
+        if path.as_os_str().is_empty() {
+            default
+        } else {
+            path.clone()
+        }
+    };
+    ExportSettings {
+        format: match (defaults.format, &overrides.format) {
+            (ExportFormat::Template(d), ExportFormat::Template(p)) => {
+                ExportFormat::Template(pick(d, p))
+            }
+        },
+        texture_destination: pick(defaults.texture_destination, &overrides.texture_destination),
+        metadata_destination: pick(
+            defaults.metadata_destination,
+            &overrides.metadata_destination,
+        ),
+        metadata_paths_root: pick(defaults.metadata_paths_root, &overrides.metadata_paths_root),
+    }
+}
+
+impl Project {
+    pub fn new<T: AsRef<Path>>(source: T) -> Project {
+        Project {
+            source: source.as_ref().to_owned(),
+            ..Default::default()
+        }
+    }
+
+    pub fn open<T: AsRef<Path>>(path: T) -> Result<Project, Error> {
+        let versioned: Versioned =
+            serde_json::from_reader(BufReader::new(File::open(path.as_ref())?))?;
+        let mut directory = path.as_ref().to_owned();
+        directory.pop();
+        let mut project = versioned.project;
+        project.source = path.as_ref().to_owned();
+        project.sheets = project
+            .sheets
+            .iter()
+            .map(|s| sheet::absolute_path(&directory, s))
+            .collect();
+        if let Some(e) = project.export_settings {
+            project.export_settings = Some(e.with_absolute_paths(&directory)?);
+        }
+        Ok(project)
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let mut directory = self.source.clone();
+        directory.pop();
+        let mut project = self.clone();
+        project.sheets = self
+            .sheets
+            .iter()
+            .map(|s| sheet::relative_path(&directory, s))
+            .collect::<Result<_, _>>()?;
+        project.sheets.sort();
+        if let Some(e) = project.export_settings {
+            project.export_settings = Some(e.with_relative_paths(&directory)?);
+        }
+
+        let mut file = BufWriter::new(File::create(&self.source)?);
+        let versioned_project = VersionedProject {
+            version: CURRENT_VERSION,
+            project: &project,
+        };
+        serde_json::to_writer_pretty(&mut file, &versioned_project)?;
+        writeln!(file)?;
+        Ok(())
+    }
+
+    pub fn get_name(&self) -> String {
+        sheet_name(&self.source)
+    }
+
+    pub fn sheets_iter(&self) -> std::slice::Iter<'_, PathBuf> {
+        self.sheets.iter()
+    }
+
+    pub fn has_sheet<T: AsRef<Path>>(&self, path: T) -> bool {
+        self.sheets.iter().any(|s| s == path.as_ref())
+    }
+
+    pub fn add_sheet<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
+        if self.has_sheet(&path) {
+            return Err(ProjectError::SheetAlreadyInProject.into());
+        }
+        self.sheets.push(path.as_ref().to_owned());
+        Ok(())
+    }
+
+    pub fn remove_sheet<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
+        if !self.has_sheet(&path) {
+            return Err(ProjectError::SheetNotInProject.into());
+        }
+        self.sheets.retain(|s| s != path.as_ref());
+        Ok(())
+    }
+
+    pub fn relocate_sheet<T: AsRef<Path>, U: AsRef<Path>>(&mut self, from: T, to: U) {
+        for sheet in self.sheets.iter_mut() {
+            if sheet == from.as_ref() {
+                *sheet = to.as_ref().to_owned();
+            }
+        }
+    }
+
+    pub fn get_export_settings(&self) -> &Option<ExportSettings> {
+        &self.export_settings
+    }
+
+    pub fn set_export_settings(&mut self, export_settings: ExportSettings) {
+        self.export_settings = Some(export_settings);
+    }
+
+    pub fn get_export_settings_for<T: AsRef<Path>>(&self, sheet_path: T) -> Option<ExportSettings> {
+        let name = sheet_name(sheet_path);
+        let specialize = |file_name: &str| file_name.replace(SHEET_NAME_PLACEHOLDER, &name);
+        self.export_settings.clone().map(|mut e| {
+            e.texture_destination = map_file_name(&e.texture_destination, specialize);
+            e.metadata_destination = map_file_name(&e.metadata_destination, specialize);
+            e
+        })
+    }
+
+    // Sheets which have export settings of their own override the defaults field by field
+    pub fn with_default_export_settings<T: AsRef<Path>>(
+        &self,
+        sheet_path: T,
+        sheet: &Sheet,
+    ) -> Sheet {
+        let mut sheet = sheet.clone();
+        if let Some(defaults) = self.get_export_settings_for(sheet_path) {
+            let export_settings = match sheet.get_export_settings() {
+                Some(overrides) => merge_export_settings(defaults, overrides),
+                None => defaults,
+            };
+            sheet.set_export_settings(export_settings);
+        }
+        sheet
+    }
+}
+
+#[test]
+fn test_default_export_settings() {
+    let mut default_settings = ExportSettings::new();
+    default_settings.format = ExportFormat::Template("/game/sheet.liquid".into());
+    default_settings.texture_destination = "/game/sprites/{sheet}.png".into();
+    default_settings.metadata_destination = "/game/sprites/{sheet}.json".into();
+
+    let mut project = Project::new("/art/characters.tigerproj");
+    project.add_sheet("/art/hero.tiger").unwrap();
+    project.add_sheet("/art/villain.tiger").unwrap();
+    project.set_export_settings(default_settings);
+
+    let villain = project
+        .with_default_export_settings("/art/villain.tiger", &Sheet::default())
+        .get_export_settings()
+        .clone()
+        .unwrap();
+    assert_eq!(
+        villain.texture_destination,
+        PathBuf::from("/game/sprites/villain.png")
+    );
+    assert_eq!(
+        villain.metadata_destination,
+        PathBuf::from("/game/sprites/villain.json")
+    );
+
+    let mut overrides = ExportSettings::new();
+    overrides.texture_destination = "/game/sprites/characters.png".into();
+    let mut own_settings = Sheet::default();
+    own_settings.set_export_settings(overrides);
+    let hero = project
+        .with_default_export_settings("/art/hero.tiger", &own_settings)
+        .get_export_settings()
+        .clone()
+        .unwrap();
+    assert_eq!(
+        hero.texture_destination,
+        PathBuf::from("/game/sprites/characters.png")
+    );
+    assert_eq!(
+        hero.metadata_destination,
+        PathBuf::from("/game/sprites/hero.json")
+    );
+    assert_eq!(
+        hero.format,
+        ExportFormat::Template("/game/sheet.liquid".into())
+    );
+}
@@ -0,0 +1,117 @@
+use failure::Error;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use crate::sheet::{compat, Sheet};
+use crate::utils::StableHasher;
+
+const RECOVERY_FILE_EXTENSION: &str = "tigerrecovery";
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+enum Version {
+    Recovery1,
+}
+const CURRENT_VERSION: Version = Version::Recovery1;
+
+#[derive(Serialize, Deserialize)]
+struct RecoveryFile {
+    version: Version,
+    source: PathBuf,
+    sheet: serde_json::Value,
+}
+
+// Unsaved changes to a sheet, written by a previous session which did not exit properly
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecoveredSheet {
+    pub source: PathBuf,
+    pub sheet: Sheet,
+}
+
+fn recovery_directory() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("tiger")
+        .join("recovery")
+}
+
+// File names must not change between releases, or sheets would no longer find their recovery file
+fn recovery_path<T: AsRef<Path>>(source: T) -> PathBuf {
+    let mut hasher = StableHasher::default();
+    hasher.write(source.as_ref().to_string_lossy().as_bytes());
+    recovery_directory().join(format!(
+        "{:016x}.{}",
+        hasher.finish(),
+        RECOVERY_FILE_EXTENSION
+    ))
+}
+
+pub fn write<T: AsRef<Path>>(source: T, sheet: &Sheet) -> Result<(), Error> {
+    let path = recovery_path(&source);
+    std::fs::create_dir_all(recovery_directory())?;
+
+    // Writing to a temporary file first means a crash mid-write never corrupts the last recovery
+    let temporary_path = path.with_extension("tmp");
+    {
+        let file = BufWriter::new(File::create(&temporary_path)?);
+        let recovery_file = RecoveryFile {
+            version: CURRENT_VERSION,
+            source: source.as_ref().to_owned(),
+            sheet: compat::sheet_to_value(sheet)?,
+        };
+        serde_json::to_writer(file, &recovery_file)?;
+    }
+    std::fs::rename(&temporary_path, &path)?;
+    Ok(())
+}
+
+pub fn discard<T: AsRef<Path>>(source: T) -> Result<(), Error> {
+    let path = recovery_path(source);
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn read<T: AsRef<Path>>(path: T) -> Result<RecoveredSheet, Error> {
+    let recovery_file: RecoveryFile =
+        serde_json::from_reader(BufReader::new(File::open(path.as_ref())?))?;
+    Ok(RecoveredSheet {
+        source: recovery_file.source,
+        sheet: compat::sheet_from_value(recovery_file.sheet)?,
+    })
+}
+
+// Recovery files older than the sheet they were made from are outdated and get deleted
+pub fn find() -> Result<Vec<RecoveredSheet>, Error> {
+    let directory = recovery_directory();
+    if !directory.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut recovered_sheets = Vec::new();
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(RECOVERY_FILE_EXTENSION) {
+            continue;
+        }
+        // Files which cannot be read are left alone, they may come from a newer version of Tiger
+        let recovered_sheet = match read(&path) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+        let recovery_modified = std::fs::metadata(&path)?.modified()?;
+        let is_outdated = match std::fs::metadata(&recovered_sheet.source) {
+            Ok(m) => m.modified()? >= recovery_modified,
+            Err(_) => false,
+        };
+        if is_outdated {
+            std::fs::remove_file(&path)?;
+        } else {
+            recovered_sheets.push(recovered_sheet);
+        }
+    }
+    recovered_sheets.sort_by(|a, b| a.source.cmp(&b.source));
+    Ok(recovered_sheets)
+}
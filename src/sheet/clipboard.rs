@@ -0,0 +1,248 @@
+use failure::Error;
+use std::path::PathBuf;
+
+use crate::sheet::*;
+
+#[derive(Fail, Debug)]
+pub enum ClipboardError {
+    #[fail(display = "Clipboard does not contain Tiger data")]
+    NotTigerData,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+enum Version {
+    TigerClipboard1,
+}
+const CURRENT_VERSION: Version = Version::TigerClipboard1;
+
+#[derive(Deserialize)]
+struct Versioned {
+    #[allow(dead_code)]
+    version: Version,
+    content: ClipboardContent,
+}
+
+#[derive(Serialize)]
+struct VersionedContent<'a> {
+    version: Version,
+    content: &'a ClipboardContent,
+}
+
+// Keyframes refer to their frame by source so they can be pasted into any sheet
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CopiedKeyframe {
+    source: PathBuf,
+    duration: u32,
+    offset: (i32, i32),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CopiedAnimation {
+    name: String,
+    is_looping: bool,
+    keyframes: Vec<CopiedKeyframe>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ClipboardContent {
+    Hitboxes(Vec<Hitbox>),
+    Keyframes(Vec<CopiedKeyframe>),
+    Animations(Vec<CopiedAnimation>),
+}
+
+fn copy_keyframe(sheet: &Sheet, keyframe: &AnimationFrame) -> Result<CopiedKeyframe, Error> {
+    let frame = sheet
+        .get_frame(keyframe.frame)
+        .ok_or(SheetError::FrameNotFound)?;
+    Ok(CopiedKeyframe {
+        source: frame.source.clone(),
+        duration: keyframe.duration,
+        offset: keyframe.offset,
+    })
+}
+
+fn paste_keyframe(sheet: &mut Sheet, keyframe: &CopiedKeyframe) -> AnimationFrame {
+    AnimationFrame {
+        frame: sheet.add_frame(&keyframe.source),
+        duration: keyframe.duration,
+        offset: keyframe.offset,
+    }
+}
+
+impl ClipboardContent {
+    pub fn from_hitboxes(frame: &Frame, ids: &[HitboxId]) -> Result<ClipboardContent, Error> {
+        let hitboxes = ids
+            .iter()
+            .map(|id| {
+                frame
+                    .get_hitbox(*id)
+                    .cloned()
+                    .ok_or_else(|| SheetError::HitboxNotFound.into())
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(ClipboardContent::Hitboxes(hitboxes))
+    }
+
+    pub fn from_keyframes(
+        sheet: &Sheet,
+        animation: &Animation,
+        indices: &[usize],
+    ) -> Result<ClipboardContent, Error> {
+        let mut indices = indices.to_vec();
+        indices.sort();
+        let keyframes = indices
+            .iter()
+            .map(|index| {
+                let keyframe = animation
+                    .get_frame(*index)
+                    .ok_or(SheetError::InvalidFrameIndex)?;
+                copy_keyframe(sheet, keyframe)
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(ClipboardContent::Keyframes(keyframes))
+    }
+
+    pub fn from_animations(sheet: &Sheet, ids: &[AnimationId]) -> Result<ClipboardContent, Error> {
+        let animations = ids
+            .iter()
+            .map(|id| {
+                let animation = sheet
+                    .get_animation(*id)
+                    .ok_or(SheetError::AnimationNotFound)?;
+                Ok(CopiedAnimation {
+                    name: animation.name.clone(),
+                    is_looping: animation.is_looping,
+                    keyframes: animation
+                        .frames_iter()
+                        .map(|k| copy_keyframe(sheet, k))
+                        .collect::<Result<_, Error>>()?,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(ClipboardContent::Animations(animations))
+    }
+
+    pub fn to_text(&self) -> Result<String, Error> {
+        let versioned_content = VersionedContent {
+            version: CURRENT_VERSION,
+            content: self,
+        };
+        Ok(serde_json::to_string(&versioned_content)?)
+    }
+
+    pub fn from_text<T: AsRef<str>>(text: T) -> Result<ClipboardContent, Error> {
+        let versioned: Versioned =
+            serde_json::from_str(text.as_ref()).map_err(|_| ClipboardError::NotTigerData)?;
+        Ok(versioned.content)
+    }
+}
+
+// Pasted items get new IDs and are renamed when their name is already taken
+impl Sheet {
+    pub fn paste_hitboxes(
+        &mut self,
+        frame_id: FrameId,
+        hitboxes: &[Hitbox],
+    ) -> Result<Vec<HitboxId>, Error> {
+        let frame = self
+            .get_frame_mut(frame_id)
+            .ok_or(SheetError::FrameNotFound)?;
+        Ok(hitboxes
+            .iter()
+            .map(|h| {
+                let hitbox = frame.add_hitbox_with_name(&h.name);
+                hitbox.geometry = h.geometry.clone();
+                hitbox.id
+            })
+            .collect())
+    }
+
+    pub fn paste_keyframes(
+        &mut self,
+        animation_id: AnimationId,
+        index: usize,
+        keyframes: &[CopiedKeyframe],
+    ) -> Result<(), Error> {
+        let keyframes: Vec<AnimationFrame> =
+            keyframes.iter().map(|k| paste_keyframe(self, k)).collect();
+        let animation = self
+            .get_animation_mut(animation_id)
+            .ok_or(SheetError::AnimationNotFound)?;
+        if index > animation.timeline.len() {
+            return Err(SheetError::InvalidFrameIndex.into());
+        }
+        animation.timeline.splice(index..index, keyframes);
+        Ok(())
+    }
+
+    pub fn paste_animations(&mut self, animations: &[CopiedAnimation]) -> Vec<AnimationId> {
+        animations
+            .iter()
+            .map(|a| {
+                let timeline: Vec<AnimationFrame> = a
+                    .keyframes
+                    .iter()
+                    .map(|k| paste_keyframe(self, k))
+                    .collect();
+                let animation = self.add_animation_with_name(&a.name);
+                animation.timeline = timeline;
+                animation.is_looping = a.is_looping;
+                animation.id
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_copy_paste() {
+    let mut sheet = Sheet::default();
+    let walk_0 = sheet.add_frame("walk_0.png");
+    let walk_1 = sheet.add_frame("walk_1.png");
+    let hitbox = sheet.get_frame_mut(walk_0).unwrap().add_hitbox().get_id();
+    let animation = sheet.add_animation_with_name("walk");
+    animation.insert_frame(walk_0, 0).unwrap();
+    animation.insert_frame(walk_1, 1).unwrap();
+    let animation_id = animation.get_id();
+
+    let text = ClipboardContent::from_hitboxes(sheet.get_frame(walk_0).unwrap(), &[hitbox])
+        .unwrap()
+        .to_text()
+        .unwrap();
+    match ClipboardContent::from_text(text).unwrap() {
+        ClipboardContent::Hitboxes(h) => {
+            sheet.paste_hitboxes(walk_0, &h).unwrap();
+            sheet.paste_hitboxes(walk_1, &h).unwrap();
+        }
+        _ => panic!("Unexpected clipboard content"),
+    };
+    assert!(sheet
+        .get_frame(walk_0)
+        .unwrap()
+        .has_hitbox_name("New Hitbox 2"));
+    assert!(sheet
+        .get_frame(walk_1)
+        .unwrap()
+        .has_hitbox_name("New Hitbox"));
+
+    let animations = ClipboardContent::from_animations(&sheet, &[animation_id]).unwrap();
+    let mut other_sheet = Sheet::default();
+    other_sheet.add_animation_with_name("walk");
+    if let ClipboardContent::Animations(a) = animations {
+        other_sheet.paste_animations(&a);
+    }
+    let pasted = other_sheet.get_animation_by_name("walk 2").unwrap();
+    assert_eq!(pasted.get_num_frames(), 2);
+    assert!(other_sheet.has_frame_source("walk_1.png"));
+
+    let keyframes =
+        ClipboardContent::from_keyframes(&sheet, sheet.get_animation(animation_id).unwrap(), &[1])
+            .unwrap();
+    if let ClipboardContent::Keyframes(k) = keyframes {
+        sheet.paste_keyframes(animation_id, 0, &k).unwrap();
+    }
+    let animation = sheet.get_animation(animation_id).unwrap();
+    let timeline: Vec<FrameId> = animation.frames_iter().map(|k| k.get_frame()).collect();
+    assert_eq!(timeline, vec![walk_1, walk_0, walk_1]);
+
+    assert!(ClipboardContent::from_text("walk_0.png").is_err());
+}
@@ -1,42 +1,207 @@
 use failure::Error;
+use std::fmt;
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Write};
 use std::path::Path;
 
-use crate::sheet::{self, Sheet};
+use crate::sheet::Sheet;
 
 pub mod version1;
 pub mod version2;
+pub mod version3;
 
-#[derive(Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Version {
     Tiger1,
     Tiger2,
+    Tiger3,
+}
+const CURRENT_VERSION: Version = Version::Tiger3;
+
+// Format names are the ones stored in the `version` field of sheet files
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Version::Tiger1 => write!(f, "Tiger1"),
+            Version::Tiger2 => write!(f, "Tiger2"),
+            Version::Tiger3 => write!(f, "Tiger3"),
+        }
+    }
+}
+
+type Migration = fn(serde_json::Value) -> Result<serde_json::Value, Error>;
+
+// Each migration upgrades sheet data by exactly one version
+const MIGRATIONS: &[(Version, Version, Migration)] = &[
+    (Version::Tiger1, Version::Tiger2, version2::migrate),
+    (Version::Tiger2, Version::Tiger3, version3::migrate),
+];
+
+#[derive(Fail, Debug)]
+pub enum CompatError {
+    #[fail(
+        display = "This sheet was saved with Tiger {} which uses a newer file format ({}). Please update Tiger to open it.",
+        _0, _1
+    )]
+    NewerTigerVersion(String, String),
+    #[fail(
+        display = "This sheet uses a newer file format ({}). Please update Tiger to open it.",
+        _0
+    )]
+    NewerFormat(String),
+    #[fail(display = "Unknown sheet format: {}", _0)]
+    UnknownFormat(String),
+    #[fail(display = "No migration available from sheet format {}", _0)]
+    MissingMigration(Version),
 }
-const CURRENT_VERSION: Version = Version::Tiger2;
 
 #[derive(Deserialize)]
 struct Versioned {
-    version: Version,
+    version: String,
+    #[serde(default)]
+    tiger_version: Option<String>,
+    sheet: serde_json::Value,
 }
 
 #[derive(Serialize)]
-struct VersionedSheet<'a> {
+struct VersionedSheet {
     version: Version,
-    sheet: &'a Sheet,
+    tiger_version: &'static str,
+    sheet: Sheet,
+}
+
+impl VersionedSheet {
+    fn new(sheet: &Sheet) -> VersionedSheet {
+        VersionedSheet {
+            version: CURRENT_VERSION,
+            tiger_version: env!("CARGO_PKG_VERSION"),
+            sheet: sheet.with_canonical_order(),
+        }
+    }
+}
+
+fn parse_version(versioned: &Versioned) -> Result<Version, CompatError> {
+    let name = &versioned.version;
+    if let Ok(version) = serde_json::from_value(serde_json::Value::String(name.clone())) {
+        return Ok(version);
+    }
+    let is_tiger_format = name.starts_with("Tiger") && name["Tiger".len()..].parse::<u32>().is_ok();
+    match (is_tiger_format, &versioned.tiger_version) {
+        (true, Some(tiger_version)) => Err(CompatError::NewerTigerVersion(
+            tiger_version.clone(),
+            name.clone(),
+        )),
+        (true, None) => Err(CompatError::NewerFormat(name.clone())),
+        (false, _) => Err(CompatError::UnknownFormat(name.clone())),
+    }
+}
+
+fn migrate(version: Version, sheet: serde_json::Value) -> Result<Sheet, Error> {
+    let mut version = version;
+    let mut sheet = sheet;
+    while version != CURRENT_VERSION {
+        let (_, next_version, migration) = MIGRATIONS
+            .iter()
+            .find(|(from, _, _)| *from == version)
+            .ok_or(CompatError::MissingMigration(version))?;
+        sheet = migration(sheet)?;
+        version = *next_version;
+    }
+    Ok(serde_json::from_value(sheet)?)
 }
 
 pub fn read_sheet<T: AsRef<Path>>(path: T) -> Result<Sheet, Error> {
-    let versioned: Versioned = serde_json::from_reader(BufReader::new(File::open(path.as_ref())?))?;
-    sheet::read_file(versioned.version, path)
+    sheet_from_value(serde_json::from_reader(BufReader::new(File::open(
+        path.as_ref(),
+    )?))?)
+}
+
+pub fn sheet_from_value(value: serde_json::Value) -> Result<Sheet, Error> {
+    let versioned: Versioned = serde_json::from_value(value)?;
+    let version = parse_version(&versioned)?;
+    migrate(version, versioned.sheet)
 }
 
+pub fn sheet_to_value(sheet: &Sheet) -> Result<serde_json::Value, Error> {
+    Ok(serde_json::to_value(VersionedSheet::new(sheet))?)
+}
+
+// Serializing the struct directly keeps fields in declaration order, which makes diffs easier to read
 pub fn write_sheet<T: AsRef<Path>>(path: T, sheet: &Sheet) -> Result<(), Error> {
-    let file = BufWriter::new(File::create(path.as_ref())?);
-    let versioned_sheet = VersionedSheet {
-        version: CURRENT_VERSION,
-        sheet: &sheet,
-    };
-    serde_json::to_writer_pretty(file, &versioned_sheet)?;
+    let mut file = BufWriter::new(File::create(path.as_ref())?);
+    serde_json::to_writer_pretty(&mut file, &VersionedSheet::new(sheet))?;
+    writeln!(file)?;
     Ok(())
 }
+
+#[test]
+fn test_read_every_version() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("test-data/sheets");
+    for fixture in &["tiger1.tiger", "tiger2.tiger", "tiger3.tiger"] {
+        let sheet = read_sheet(fixtures.join(fixture)).unwrap();
+
+        let sources: Vec<&Path> = sheet.frames_iter().map(|f| f.get_source()).collect();
+        assert_eq!(
+            sources,
+            vec![Path::new("walk_0.png"), Path::new("walk_1.png")]
+        );
+
+        let walk_0 = sheet.get_frame_by_source("walk_0.png").unwrap();
+        assert!(walk_0.get_hitbox_by_name("hurt").is_some());
+
+        let walk = sheet.get_animation_by_name("walk").unwrap();
+        assert!(walk.is_looping());
+        let timeline: Vec<&Path> = walk
+            .frames_iter()
+            .map(|k| sheet.get_frame(k.get_frame()).unwrap().get_source())
+            .collect();
+        assert_eq!(
+            timeline,
+            vec![Path::new("walk_0.png"), Path::new("walk_1.png")]
+        );
+        assert_eq!(walk.get_frame(1).unwrap().get_duration(), 150);
+
+        let export_settings = sheet.get_export_settings().as_ref().unwrap();
+        assert_eq!(export_settings.texture_destination, Path::new("walk.png"));
+    }
+}
+
+#[test]
+fn test_read_newer_version() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("test-data/sheets");
+    let error = read_sheet(fixtures.join("tiger99.tiger")).unwrap_err();
+    match error.downcast_ref::<CompatError>() {
+        Some(CompatError::NewerTigerVersion(tiger_version, _)) => {
+            assert_eq!(tiger_version, "9.0.0")
+        }
+        _ => panic!("Unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn test_write_keeps_field_order() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("test-data/sheets");
+    let sheet = read_sheet(fixtures.join("tiger3.tiger")).unwrap();
+
+    let path = std::env::temp_dir().join(format!("tiger-write-field-order-{}", std::process::id()));
+    write_sheet(&path, &sheet).unwrap();
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let position = |field: &str| written.find(&format!("\"{}\"", field)).unwrap();
+    assert!(position("version") < position("tiger_version"));
+    assert!(position("tiger_version") < position("sheet"));
+    assert!(position("frames") < position("animations"));
+    let read_back = sheet_from_value(serde_json::from_str(&written).unwrap()).unwrap();
+    assert_eq!(read_back, sheet.with_canonical_order());
+}
+
+#[test]
+fn test_version_names() {
+    for version in &[Version::Tiger1, Version::Tiger2, Version::Tiger3] {
+        assert_eq!(
+            serde_json::to_value(version).unwrap(),
+            serde_json::Value::String(version.to_string())
+        );
+    }
+}
@@ -1,28 +1,4 @@
-use failure::Error;
-use std::fs::File;
-use std::io::BufReader;
-use std::path::{Path, PathBuf};
-
-use crate::sheet::compat::Version;
-
-const THIS_VERSION: Version = Version::Tiger1;
-
-#[derive(Serialize, Deserialize)]
-pub struct VersionedSheet {
-    pub sheet: Sheet,
-}
-
-pub fn read_file<T: AsRef<Path>>(version: Version, path: T) -> Result<Sheet, Error> {
-    assert!(version == THIS_VERSION);
-    match version {
-        THIS_VERSION => {
-            let deserialized: VersionedSheet =
-                serde_json::from_reader(BufReader::new(File::open(path.as_ref())?))?;
-            Ok(deserialized.sheet)
-        }
-        _ => bail!("Unexpected version"),
-    }
-}
+use std::path::PathBuf;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Sheet {
@@ -1,27 +1,11 @@
 use failure::Error;
-use std::fs::File;
-use std::io::BufReader;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 use crate::sheet::compat::version1 as previous_version;
-use crate::sheet::compat::Version;
 
-const THIS_VERSION: Version = Version::Tiger2;
-
-#[derive(Serialize, Deserialize)]
-pub struct VersionedSheet {
-    pub sheet: Sheet,
-}
-
-pub fn read_file<T: AsRef<Path>>(version: Version, path: T) -> Result<Sheet, Error> {
-    match version {
-        THIS_VERSION => {
-            let deserialized: VersionedSheet =
-                serde_json::from_reader(BufReader::new(File::open(path.as_ref())?))?;
-            Ok(deserialized.sheet)
-        }
-        _ => Ok(previous_version::read_file(version, path)?.into()),
-    }
+pub fn migrate(old: serde_json::Value) -> Result<serde_json::Value, Error> {
+    let old: previous_version::Sheet = serde_json::from_value(old)?;
+    Ok(serde_json::to_value(Sheet::from(old))?)
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
@@ -0,0 +1,238 @@
+use failure::Error;
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+
+use crate::sheet::compat::version2 as previous_version;
+use crate::utils::StableHasher;
+
+pub fn migrate(old: serde_json::Value) -> Result<serde_json::Value, Error> {
+    let old: previous_version::Sheet = serde_json::from_value(old)?;
+    Ok(serde_json::to_value(Sheet::from(old))?)
+}
+
+// Migrated IDs are derived from names so that copies of a sheet migrated separately agree
+fn stable_id(parts: &[&[u8]]) -> u64 {
+    let mut hasher = StableHasher::default();
+    for part in parts {
+        hasher.write(part);
+        hasher.write_u8(0xff);
+    }
+    hasher.finish()
+}
+
+fn path_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().replace('\\', "/").into_bytes()
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct FrameId(pub u64);
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct AnimationId(pub u64);
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct HitboxId(pub u64);
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Sheet {
+    pub frames: Vec<Frame>,
+    pub animations: Vec<Animation>,
+    pub export_settings: Option<ExportSettings>,
+}
+
+impl From<previous_version::Sheet> for Sheet {
+    fn from(old: previous_version::Sheet) -> Sheet {
+        let mut frames: Vec<Frame> = old.frames.into_iter().map(|o| o.into()).collect();
+        let mut frame_ids: HashMap<PathBuf, FrameId> =
+            frames.iter().map(|f| (f.source.clone(), f.id)).collect();
+
+        // Animations used to reference frames by path, which may not have been part of the sheet
+        let mut animations = Vec::new();
+        for old_animation in old.animations {
+            let mut timeline = Vec::new();
+            for old_animation_frame in old_animation.timeline {
+                let frame_id = *frame_ids
+                    .entry(old_animation_frame.frame.clone())
+                    .or_insert_with(|| {
+                        let frame = Frame::from(previous_version::Frame {
+                            source: old_animation_frame.frame.clone(),
+                            hitboxes: vec![],
+                        });
+                        let id = frame.id;
+                        frames.push(frame);
+                        id
+                    });
+                timeline.push(AnimationFrame {
+                    frame: frame_id,
+                    duration: old_animation_frame.duration,
+                    offset: old_animation_frame.offset,
+                });
+            }
+            animations.push(Animation {
+                id: AnimationId(stable_id(&[b"animation", old_animation.name.as_bytes()])),
+                name: old_animation.name,
+                timeline,
+                is_looping: old_animation.is_looping,
+            });
+        }
+
+        Sheet {
+            frames,
+            animations,
+            export_settings: old.export_settings.map(|o| o.into()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Animation {
+    pub id: AnimationId,
+    pub name: String,
+    pub timeline: Vec<AnimationFrame>,
+    pub is_looping: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    pub id: FrameId,
+    pub source: PathBuf,
+    pub hitboxes: Vec<Hitbox>,
+}
+
+impl From<previous_version::Frame> for Frame {
+    fn from(old: previous_version::Frame) -> Frame {
+        let source = path_bytes(&old.source);
+        let hitboxes = old
+            .hitboxes
+            .into_iter()
+            .map(|o| Hitbox {
+                id: HitboxId(stable_id(&[b"hitbox", &source, o.name.as_bytes()])),
+                name: o.name,
+                geometry: o.geometry.into(),
+            })
+            .collect();
+        Frame {
+            id: FrameId(stable_id(&[b"frame", &source])),
+            source: old.source,
+            hitboxes,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AnimationFrame {
+    pub frame: FrameId,
+    pub duration: u32, // in ms
+    pub offset: (i32, i32),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Hitbox {
+    pub id: HitboxId,
+    pub name: String,
+    pub geometry: Shape,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Shape {
+    Rectangle(Rectangle),
+}
+
+impl From<previous_version::Shape> for Shape {
+    fn from(old: previous_version::Shape) -> Shape {
+        match old {
+            previous_version::Shape::Rectangle(r) => Shape::Rectangle(r.into()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Rectangle {
+    pub top_left: (i32, i32),
+    pub size: (u32, u32),
+}
+
+impl From<previous_version::Rectangle> for Rectangle {
+    fn from(old: previous_version::Rectangle) -> Rectangle {
+        Rectangle {
+            top_left: old.top_left,
+            size: old.size,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum ExportFormat {
+    Template(PathBuf),
+}
+
+impl From<previous_version::ExportFormat> for ExportFormat {
+    fn from(old: previous_version::ExportFormat) -> ExportFormat {
+        match old {
+            previous_version::ExportFormat::Template(p) => ExportFormat::Template(p),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ExportSettings {
+    pub format: ExportFormat,
+    pub texture_destination: PathBuf,
+    pub metadata_destination: PathBuf,
+    pub metadata_paths_root: PathBuf,
+}
+
+impl From<previous_version::ExportSettings> for ExportSettings {
+    fn from(old: previous_version::ExportSettings) -> ExportSettings {
+        ExportSettings {
+            format: old.format.into(),
+            texture_destination: old.texture_destination,
+            metadata_destination: old.metadata_destination,
+            metadata_paths_root: old.metadata_paths_root,
+        }
+    }
+}
+
+#[test]
+fn test_migrate_from_version2() {
+    let hitbox = previous_version::Hitbox {
+        name: "hurt".to_owned(),
+        geometry: previous_version::Shape::Rectangle(previous_version::Rectangle {
+            top_left: (0, 0),
+            size: (4, 4),
+        }),
+    };
+    let old = previous_version::Sheet {
+        frames: vec![previous_version::Frame {
+            source: "walk_0.png".into(),
+            hitboxes: vec![hitbox],
+        }],
+        animations: vec![previous_version::Animation {
+            name: "walk".to_owned(),
+            timeline: ["walk_0.png", "walk_1.png", "walk_0.png"]
+                .iter()
+                .map(|f| previous_version::AnimationFrame {
+                    frame: f.into(),
+                    duration: 100,
+                    offset: (0, 0),
+                })
+                .collect(),
+            is_looping: true,
+        }],
+        export_settings: None,
+    };
+
+    let sheet: Sheet = old.clone().into();
+    assert_eq!(sheet.frames.len(), 2);
+    assert_ne!(sheet.frames[0].id, sheet.frames[1].id);
+    assert_eq!(sheet.frames[1].source, PathBuf::from("walk_1.png"));
+    assert_eq!(sheet.frames[0].hitboxes[0].name, "hurt");
+    let timeline = &sheet.animations[0].timeline;
+    assert_eq!(timeline[0].frame, sheet.frames[0].id);
+    assert_eq!(timeline[1].frame, sheet.frames[1].id);
+    assert_eq!(timeline[2].frame, sheet.frames[0].id);
+
+    let again: Sheet = old.into();
+    assert_eq!(again, sheet);
+}
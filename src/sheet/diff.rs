@@ -0,0 +1,362 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::sheet::matching::*;
+use crate::sheet::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SheetChange {
+    FrameAdded(PathBuf),
+    FrameRemoved(PathBuf),
+    FrameMoved(PathBuf, PathBuf),
+    HitboxAdded(PathBuf, String),
+    HitboxRemoved(PathBuf, String),
+    HitboxRenamed(PathBuf, String, String),
+    HitboxMoved(PathBuf, String, (i32, i32), (i32, i32)),
+    HitboxResized(PathBuf, String, (u32, u32), (u32, u32)),
+    AnimationAdded(String),
+    AnimationRemoved(String),
+    AnimationRenamed(String, String),
+    AnimationLoopingChanged(String, bool),
+    KeyframeAdded(String, usize, PathBuf),
+    KeyframeRemoved(String, usize, PathBuf),
+    KeyframeDurationChanged(String, usize, u32, u32),
+    KeyframeOffsetChanged(String, usize, (i32, i32), (i32, i32)),
+    ExportSettingsChanged,
+}
+
+impl fmt::Display for SheetChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use SheetChange::*;
+        match self {
+            FrameAdded(p) => write!(f, "Added frame {}", p.display()),
+            FrameRemoved(p) => write!(f, "Removed frame {}", p.display()),
+            FrameMoved(o, n) => write!(f, "Moved frame {} to {}", o.display(), n.display()),
+            HitboxAdded(p, h) => write!(f, "Added hitbox \"{}\" to frame {}", h, p.display()),
+            HitboxRemoved(p, h) => {
+                write!(f, "Removed hitbox \"{}\" from frame {}", h, p.display())
+            }
+            HitboxRenamed(p, o, n) => write!(
+                f,
+                "Renamed hitbox \"{}\" of frame {} to \"{}\"",
+                o,
+                p.display(),
+                n
+            ),
+            HitboxMoved(p, h, o, n) => write!(
+                f,
+                "Moved hitbox \"{}\" of frame {} from {:?} to {:?}",
+                h,
+                p.display(),
+                o,
+                n
+            ),
+            HitboxResized(p, h, o, n) => write!(
+                f,
+                "Resized hitbox \"{}\" of frame {} from {}x{} to {}x{}",
+                h,
+                p.display(),
+                o.0,
+                o.1,
+                n.0,
+                n.1
+            ),
+            AnimationAdded(a) => write!(f, "Added animation \"{}\"", a),
+            AnimationRemoved(a) => write!(f, "Removed animation \"{}\"", a),
+            AnimationRenamed(o, n) => write!(f, "Renamed animation \"{}\" to \"{}\"", o, n),
+            AnimationLoopingChanged(a, true) => write!(f, "Animation \"{}\" now loops", a),
+            AnimationLoopingChanged(a, false) => {
+                write!(f, "Animation \"{}\" no longer loops", a)
+            }
+            KeyframeAdded(a, i, p) => write!(
+                f,
+                "Inserted frame {} as frame #{} of animation \"{}\"",
+                p.display(),
+                i + 1,
+                a
+            ),
+            KeyframeRemoved(a, i, p) => write!(
+                f,
+                "Removed frame {} which was frame #{} of animation \"{}\"",
+                p.display(),
+                i + 1,
+                a
+            ),
+            KeyframeDurationChanged(a, i, o, n) => write!(
+                f,
+                "Changed duration of frame #{} of animation \"{}\" from {}ms to {}ms",
+                i + 1,
+                a,
+                o,
+                n
+            ),
+            KeyframeOffsetChanged(a, i, o, n) => write!(
+                f,
+                "Changed offset of frame #{} of animation \"{}\" from {:?} to {:?}",
+                i + 1,
+                a,
+                o,
+                n
+            ),
+            ExportSettingsChanged => write!(f, "Changed export settings"),
+        }
+    }
+}
+
+struct Matches<'a, T> {
+    pairs: Vec<(&'a T, &'a T)>,
+    removed: Vec<&'a T>,
+    added: Vec<&'a T>,
+}
+
+fn pair_items<'a, T: Keyed>(old: &'a [T], new: &'a [T]) -> Matches<'a, T> {
+    let reference: Vec<&T> = old.iter().collect();
+    let new_matches = match_items(&reference, new);
+
+    let mut old_matched = vec![false; old.len()];
+    let mut matches = Matches {
+        pairs: vec![],
+        removed: vec![],
+        added: vec![],
+    };
+    for (n, m) in new.iter().zip(new_matches) {
+        match m {
+            Some(o) => {
+                old_matched[o] = true;
+                matches.pairs.push((&old[o], n));
+            }
+            None => matches.added.push(n),
+        }
+    }
+    for (o, matched) in old.iter().zip(old_matched) {
+        if !matched {
+            matches.removed.push(o);
+        }
+    }
+    matches
+}
+
+// Keyframes are aligned on the images they show (longest common subsequence), so inserting a
+// keyframe does not register as an edit of every keyframe after it
+fn align_keyframes<T: PartialEq>(old: &[T], new: &[T]) -> Vec<(Option<usize>, Option<usize>)> {
+    let mut lengths = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for o in (0..old.len()).rev() {
+        for n in (0..new.len()).rev() {
+            lengths[o][n] = if old[o] == new[n] {
+                lengths[o + 1][n + 1] + 1
+            } else {
+                lengths[o + 1][n].max(lengths[o][n + 1])
+            };
+        }
+    }
+
+    let mut alignment = Vec::new();
+    let (mut o, mut n) = (0, 0);
+    while o < old.len() || n < new.len() {
+        if o < old.len() && n < new.len() && old[o] == new[n] {
+            alignment.push((Some(o), Some(n)));
+            o += 1;
+            n += 1;
+        } else if n == new.len() || (o < old.len() && lengths[o + 1][n] >= lengths[o][n + 1]) {
+            alignment.push((Some(o), None));
+            o += 1;
+        } else {
+            alignment.push((None, Some(n)));
+            n += 1;
+        }
+    }
+    alignment
+}
+
+fn diff_hitboxes(old: &Frame, new: &Frame, changes: &mut Vec<SheetChange>) {
+    let source = new.get_source().to_owned();
+    let matches = pair_items(&old.hitboxes, &new.hitboxes);
+    for hitbox in matches.removed {
+        changes.push(SheetChange::HitboxRemoved(
+            source.clone(),
+            hitbox.get_name().to_owned(),
+        ));
+    }
+    for hitbox in matches.added {
+        changes.push(SheetChange::HitboxAdded(
+            source.clone(),
+            hitbox.get_name().to_owned(),
+        ));
+    }
+    for (o, n) in matches.pairs {
+        let name = n.get_name().to_owned();
+        if o.get_name() != n.get_name() {
+            changes.push(SheetChange::HitboxRenamed(
+                source.clone(),
+                o.get_name().to_owned(),
+                name.clone(),
+            ));
+        }
+        if o.get_position() != n.get_position() {
+            changes.push(SheetChange::HitboxMoved(
+                source.clone(),
+                name.clone(),
+                o.get_position().to_tuple(),
+                n.get_position().to_tuple(),
+            ));
+        }
+        if o.get_size() != n.get_size() {
+            changes.push(SheetChange::HitboxResized(
+                source.clone(),
+                name.clone(),
+                o.get_size().to_tuple(),
+                n.get_size().to_tuple(),
+            ));
+        }
+    }
+}
+
+fn keyframe_sources<'a>(sheet: &'a Sheet, animation: &Animation) -> Vec<Option<&'a Path>> {
+    animation
+        .frames_iter()
+        .map(|k| sheet.get_frame(k.get_frame()).map(Frame::get_source))
+        .collect()
+}
+
+fn diff_timelines(
+    old_sheet: &Sheet,
+    new_sheet: &Sheet,
+    old: &Animation,
+    new: &Animation,
+    changes: &mut Vec<SheetChange>,
+) {
+    let name = new.get_name().to_owned();
+    let old_sources = keyframe_sources(old_sheet, old);
+    let new_sources = keyframe_sources(new_sheet, new);
+    let source_or_empty = |s: Option<&Path>| s.map(Path::to_owned).unwrap_or_default();
+
+    for alignment in align_keyframes(&old_sources, &new_sources) {
+        match alignment {
+            (Some(o), None) => changes.push(SheetChange::KeyframeRemoved(
+                name.clone(),
+                o,
+                source_or_empty(old_sources[o]),
+            )),
+            (None, Some(n)) => changes.push(SheetChange::KeyframeAdded(
+                name.clone(),
+                n,
+                source_or_empty(new_sources[n]),
+            )),
+            (Some(o), Some(n)) => {
+                let (old_keyframe, new_keyframe) = match (old.get_frame(o), new.get_frame(n)) {
+                    (Some(o), Some(n)) => (o, n),
+                    _ => continue,
+                };
+                if old_keyframe.get_duration() != new_keyframe.get_duration() {
+                    changes.push(SheetChange::KeyframeDurationChanged(
+                        name.clone(),
+                        n,
+                        old_keyframe.get_duration(),
+                        new_keyframe.get_duration(),
+                    ));
+                }
+                if old_keyframe.get_offset() != new_keyframe.get_offset() {
+                    changes.push(SheetChange::KeyframeOffsetChanged(
+                        name.clone(),
+                        n,
+                        old_keyframe.get_offset().to_tuple(),
+                        new_keyframe.get_offset().to_tuple(),
+                    ));
+                }
+            }
+            (None, None) => (),
+        }
+    }
+}
+
+// Both sheets are expected to use the same kind of paths, see Sheet::with_absolute_paths
+pub fn diff_sheets(old: &Sheet, new: &Sheet) -> Vec<SheetChange> {
+    let mut changes = Vec::new();
+
+    let frames = pair_items(&old.frames, &new.frames);
+    for frame in frames.removed {
+        changes.push(SheetChange::FrameRemoved(frame.get_source().to_owned()));
+    }
+    for frame in frames.added {
+        changes.push(SheetChange::FrameAdded(frame.get_source().to_owned()));
+    }
+    for (o, n) in frames.pairs {
+        if o.get_source() != n.get_source() {
+            changes.push(SheetChange::FrameMoved(
+                o.get_source().to_owned(),
+                n.get_source().to_owned(),
+            ));
+        }
+        diff_hitboxes(o, n, &mut changes);
+    }
+
+    let animations = pair_items(&old.animations, &new.animations);
+    for animation in animations.removed {
+        changes.push(SheetChange::AnimationRemoved(
+            animation.get_name().to_owned(),
+        ));
+    }
+    for animation in animations.added {
+        changes.push(SheetChange::AnimationAdded(animation.get_name().to_owned()));
+    }
+    for (o, n) in animations.pairs {
+        if o.get_name() != n.get_name() {
+            changes.push(SheetChange::AnimationRenamed(
+                o.get_name().to_owned(),
+                n.get_name().to_owned(),
+            ));
+        }
+        if o.is_looping() != n.is_looping() {
+            changes.push(SheetChange::AnimationLoopingChanged(
+                n.get_name().to_owned(),
+                n.is_looping(),
+            ));
+        }
+        diff_timelines(old, new, o, n, &mut changes);
+    }
+
+    if old.get_export_settings() != new.get_export_settings() {
+        changes.push(SheetChange::ExportSettingsChanged);
+    }
+
+    changes
+}
+
+#[test]
+fn test_diff_sheets() {
+    let mut old = Sheet::default();
+    let idle = old.add_frame("idle.png");
+    let walk_0 = old.add_frame("walk_0.png");
+    let walk_1 = old.add_frame("walk_1.png");
+    let hitbox = old.get_frame_mut(walk_0).unwrap().add_hitbox().get_id();
+    let walk = old.add_animation_with_name("walk");
+    walk.insert_frame(walk_0, 0).unwrap();
+    walk.insert_frame(walk_1, 1).unwrap();
+    let walk = walk.get_id();
+
+    let mut new = old.clone();
+    new.delete_frame(idle);
+    new.rename_animation(walk, "walk_left").unwrap();
+    let hitbox = new
+        .get_frame_mut(walk_0)
+        .unwrap()
+        .get_hitbox_mut(hitbox)
+        .unwrap();
+    hitbox.set_position((2, 3).into());
+    let hitbox_name = hitbox.get_name().to_owned();
+    let animation = new.get_animation_mut(walk).unwrap();
+    animation.insert_frame(walk_1, 0).unwrap();
+    animation.get_frame_mut(2).unwrap().set_duration(150);
+
+    let changes = diff_sheets(&old, &new);
+    assert_eq!(
+        changes,
+        vec![
+            SheetChange::FrameRemoved("idle.png".into()),
+            SheetChange::HitboxMoved("walk_0.png".into(), hitbox_name, (0, 0), (2, 3)),
+            SheetChange::AnimationRenamed("walk".to_owned(), "walk_left".to_owned()),
+            SheetChange::KeyframeAdded("walk_left".to_owned(), 0, "walk_1.png".into()),
+            SheetChange::KeyframeDurationChanged("walk_left".to_owned(), 2, 100, 150),
+        ]
+    );
+}
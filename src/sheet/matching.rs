@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use crate::sheet::*;
+
+pub trait Keyed {
+    type Key: Copy + Eq + Hash;
+    fn key(&self) -> Self::Key;
+    fn set_key(&mut self, key: Self::Key);
+    fn same_name(&self, other: &Self) -> bool;
+}
+
+impl Keyed for Frame {
+    type Key = FrameId;
+    fn key(&self) -> FrameId {
+        self.id
+    }
+    fn set_key(&mut self, key: FrameId) {
+        self.id = key;
+    }
+    fn same_name(&self, other: &Frame) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Keyed for Animation {
+    type Key = AnimationId;
+    fn key(&self) -> AnimationId {
+        self.id
+    }
+    fn set_key(&mut self, key: AnimationId) {
+        self.id = key;
+    }
+    fn same_name(&self, other: &Animation) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Keyed for Hitbox {
+    type Key = HitboxId;
+    fn key(&self) -> HitboxId {
+        self.id
+    }
+    fn set_key(&mut self, key: HitboxId) {
+        self.id = key;
+    }
+    fn same_name(&self, other: &Hitbox) -> bool {
+        self.name == other.name
+    }
+}
+
+// Items are matched by ID first, then by name so sheets migrated separately still line up.
+// Returns the index of the reference item matching each item, reference items with the same ID
+// count as one.
+pub fn match_items<T: Keyed>(reference: &[&T], items: &[T]) -> Vec<Option<usize>> {
+    let mut taken: HashSet<T::Key> = items
+        .iter()
+        .map(Keyed::key)
+        .filter(|k| reference.iter().any(|r| r.key() == *k))
+        .collect();
+    items
+        .iter()
+        .map(|item| {
+            reference
+                .iter()
+                .position(|r| r.key() == item.key())
+                .or_else(|| {
+                    let index = reference
+                        .iter()
+                        .position(|r| !taken.contains(&r.key()) && r.same_name(item))?;
+                    taken.insert(reference[index].key());
+                    Some(index)
+                })
+        })
+        .collect()
+}
+
+#[test]
+fn test_match_items() {
+    let mut old = Sheet::default();
+    let idle = old.add_frame("idle.png");
+    old.add_frame("walk.png");
+    old.add_frame("run.png");
+
+    let mut new = old.clone();
+    new.frames[1].id = FrameId::generate();
+    new.frames[2].id = FrameId::generate();
+    new.frames[2].source = "sprint.png".into();
+    new.add_frame("jump.png");
+
+    let reference: Vec<&Frame> = old.frames.iter().collect();
+    assert_eq!(
+        match_items(&reference, &new.frames),
+        vec![Some(0), Some(1), None, None]
+    );
+    assert_eq!(new.frames[0].id, idle);
+}
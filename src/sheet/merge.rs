@@ -0,0 +1,450 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::sheet::matching::*;
+use crate::sheet::*;
+
+// Conflicting edits are resolved in favor of our side, conflicts describe what was overridden
+#[derive(Clone, Debug, PartialEq)]
+pub enum MergeConflict {
+    FrameSource(FrameId, PathBuf, PathBuf),
+    FrameDeleted(FrameId, PathBuf),
+    HitboxName(FrameId, HitboxId, PathBuf, String, String),
+    HitboxGeometry(FrameId, HitboxId, PathBuf, String),
+    HitboxDeleted(FrameId, HitboxId, PathBuf, String),
+    AnimationName(AnimationId, String, String),
+    AnimationLooping(AnimationId, String),
+    AnimationTimeline(AnimationId, String),
+    AnimationDeleted(AnimationId, String),
+    ExportSettings,
+    DuplicateFrame(PathBuf),
+    DuplicateHitboxName(PathBuf, String),
+    DuplicateAnimationName(String),
+    FrameNotInSheet(AnimationId, String, FrameId),
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use MergeConflict::*;
+        match self {
+            FrameSource(_, o, t) => write!(
+                f,
+                "Frame was moved to both {} and {}",
+                o.display(),
+                t.display()
+            ),
+            FrameDeleted(_, p) => write!(
+                f,
+                "Frame {} was deleted on one side and edited on the other",
+                p.display()
+            ),
+            HitboxName(_, _, p, o, t) => write!(
+                f,
+                "Hitbox of frame {} was renamed to both \"{}\" and \"{}\"",
+                p.display(),
+                o,
+                t
+            ),
+            HitboxGeometry(_, _, p, h) => write!(
+                f,
+                "Hitbox \"{}\" of frame {} was resized or moved on both sides",
+                h,
+                p.display()
+            ),
+            HitboxDeleted(_, _, p, h) => write!(
+                f,
+                "Hitbox \"{}\" of frame {} was deleted on one side and edited on the other",
+                h,
+                p.display()
+            ),
+            AnimationName(_, o, t) => {
+                write!(f, "Animation was renamed to both \"{}\" and \"{}\"", o, t)
+            }
+            AnimationLooping(_, a) => write!(
+                f,
+                "Looping of animation \"{}\" was changed on both sides",
+                a
+            ),
+            AnimationTimeline(_, a) => {
+                write!(f, "Frames of animation \"{}\" were edited on both sides", a)
+            }
+            AnimationDeleted(_, a) => write!(
+                f,
+                "Animation \"{}\" was deleted on one side and edited on the other",
+                a
+            ),
+            ExportSettings => write!(f, "Export settings were changed on both sides"),
+            DuplicateFrame(p) => write!(f, "Frame {} was added on both sides", p.display()),
+            DuplicateHitboxName(p, h) => write!(
+                f,
+                "Frame {} has more than one hitbox named \"{}\"",
+                p.display(),
+                h
+            ),
+            DuplicateAnimationName(a) => {
+                write!(f, "More than one animation is named \"{}\"", a)
+            }
+            FrameNotInSheet(_, a, id) => {
+                write!(f, "Animation \"{}\" uses frame {} which was deleted", a, id)
+            }
+        }
+    }
+}
+
+// Items matched by name take the ID of their reference item, the previous IDs are returned
+fn realign_items<T: Keyed>(reference: &[&[T]], items: &mut [T]) -> HashMap<T::Key, T::Key> {
+    let reference: Vec<&T> = reference.iter().flat_map(|r| r.iter()).collect();
+    let keys: Vec<Option<T::Key>> = match_items(&reference, items)
+        .into_iter()
+        .map(|m| m.map(|r| reference[r].key()))
+        .collect();
+
+    let mut renamed = HashMap::new();
+    for (item, key) in items.iter_mut().zip(keys) {
+        match key {
+            Some(key) if key != item.key() => {
+                renamed.insert(item.key(), key);
+                item.set_key(key);
+            }
+            _ => (),
+        }
+    }
+    renamed
+}
+
+fn realign_sheet(reference: &[&Sheet], sheet: &Sheet) -> Sheet {
+    let mut sheet = sheet.clone();
+
+    let frames: Vec<&[Frame]> = reference.iter().map(|s| &s.frames[..]).collect();
+    let renamed_frames = realign_items(&frames, &mut sheet.frames);
+    for frame in sheet.frames.iter_mut() {
+        let hitboxes: Vec<&[Hitbox]> = reference
+            .iter()
+            .filter_map(|s| s.frames.iter().find(|f| f.id == frame.id))
+            .map(|f| &f.hitboxes[..])
+            .collect();
+        realign_items(&hitboxes, &mut frame.hitboxes);
+    }
+
+    let animations: Vec<&[Animation]> = reference.iter().map(|s| &s.animations[..]).collect();
+    realign_items(&animations, &mut sheet.animations);
+    for animation in sheet.animations.iter_mut() {
+        for animation_frame in animation.timeline.iter_mut() {
+            if let Some(id) = renamed_frames.get(&animation_frame.frame) {
+                animation_frame.frame = *id;
+            }
+        }
+    }
+
+    sheet
+}
+
+// Returns None when both sides changed the value differently
+fn merge_value<T: Clone + PartialEq>(base: &T, ours: &T, theirs: &T) -> Option<T> {
+    if ours == theirs || base == theirs {
+        Some(ours.clone())
+    } else if base == ours {
+        Some(theirs.clone())
+    } else {
+        None
+    }
+}
+
+// Items are matched by ID. Our items keep their order, items only added by them come last.
+fn merge_items<T, M, D>(
+    base: &[T],
+    ours: &[T],
+    theirs: &[T],
+    conflicts: &mut Vec<MergeConflict>,
+    merge_item: M,
+    deletion_conflict: D,
+) -> Vec<T>
+where
+    T: Clone + PartialEq + Keyed,
+    M: Fn(&T, &T, &T, &mut Vec<MergeConflict>) -> T,
+    D: Fn(&T) -> MergeConflict,
+{
+    let find = |items: &[T], key: T::Key| items.iter().find(|i| i.key() == key).cloned();
+    let mut keys: Vec<T::Key> = ours.iter().map(Keyed::key).collect();
+    keys.extend(
+        theirs
+            .iter()
+            .map(Keyed::key)
+            .filter(|k| !ours.iter().any(|o| o.key() == *k)),
+    );
+
+    let mut merged = Vec::new();
+    for key in keys {
+        match (find(base, key), find(ours, key), find(theirs, key)) {
+            (Some(b), Some(o), Some(t)) => merged.push(merge_item(&b, &o, &t, conflicts)),
+            (None, Some(i), _) | (None, None, Some(i)) => merged.push(i),
+            (Some(b), Some(i), None) | (Some(b), None, Some(i)) => {
+                if i != b {
+                    conflicts.push(deletion_conflict(&i));
+                    merged.push(i);
+                }
+            }
+            (_, None, None) => (),
+        }
+    }
+    merged
+}
+
+fn merge_hitbox(
+    frame_id: FrameId,
+    source: &Path,
+    base: &Hitbox,
+    ours: &Hitbox,
+    theirs: &Hitbox,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Hitbox {
+    let name = merge_value(&base.name, &ours.name, &theirs.name).unwrap_or_else(|| {
+        conflicts.push(MergeConflict::HitboxName(
+            frame_id,
+            ours.id,
+            source.to_owned(),
+            ours.name.clone(),
+            theirs.name.clone(),
+        ));
+        ours.name.clone()
+    });
+    let geometry =
+        merge_value(&base.geometry, &ours.geometry, &theirs.geometry).unwrap_or_else(|| {
+            conflicts.push(MergeConflict::HitboxGeometry(
+                frame_id,
+                ours.id,
+                source.to_owned(),
+                name.clone(),
+            ));
+            ours.geometry.clone()
+        });
+    Hitbox {
+        id: ours.id,
+        name,
+        geometry,
+    }
+}
+
+fn merge_frame(
+    base: &Frame,
+    ours: &Frame,
+    theirs: &Frame,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Frame {
+    let source = merge_value(&base.source, &ours.source, &theirs.source).unwrap_or_else(|| {
+        conflicts.push(MergeConflict::FrameSource(
+            ours.id,
+            ours.source.clone(),
+            theirs.source.clone(),
+        ));
+        ours.source.clone()
+    });
+    let hitboxes = merge_items(
+        &base.hitboxes,
+        &ours.hitboxes,
+        &theirs.hitboxes,
+        conflicts,
+        |b, o, t, c| merge_hitbox(ours.id, &source, b, o, t, c),
+        |h| MergeConflict::HitboxDeleted(ours.id, h.id, source.clone(), h.name.clone()),
+    );
+    Frame {
+        id: ours.id,
+        source,
+        hitboxes,
+    }
+}
+
+fn merge_animation(
+    base: &Animation,
+    ours: &Animation,
+    theirs: &Animation,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Animation {
+    let name = merge_value(&base.name, &ours.name, &theirs.name).unwrap_or_else(|| {
+        conflicts.push(MergeConflict::AnimationName(
+            ours.id,
+            ours.name.clone(),
+            theirs.name.clone(),
+        ));
+        ours.name.clone()
+    });
+    let is_looping = merge_value(&base.is_looping, &ours.is_looping, &theirs.is_looping)
+        .unwrap_or_else(|| {
+            conflicts.push(MergeConflict::AnimationLooping(ours.id, name.clone()));
+            ours.is_looping
+        });
+    let timeline =
+        merge_value(&base.timeline, &ours.timeline, &theirs.timeline).unwrap_or_else(|| {
+            conflicts.push(MergeConflict::AnimationTimeline(ours.id, name.clone()));
+            ours.timeline.clone()
+        });
+    Animation {
+        id: ours.id,
+        name,
+        timeline,
+        is_looping,
+    }
+}
+
+// Edits which merged cleanly can still add up to an invalid sheet
+fn check_merged_sheet(sheet: &Sheet, conflicts: &mut Vec<MergeConflict>) {
+    let mut sources = HashSet::new();
+    for frame in sheet.frames_iter() {
+        if !sources.insert(frame.get_source()) {
+            conflicts.push(MergeConflict::DuplicateFrame(frame.source.clone()));
+        }
+        let mut names = HashSet::new();
+        for hitbox in frame.hitboxes_iter() {
+            if !names.insert(hitbox.get_name()) {
+                conflicts.push(MergeConflict::DuplicateHitboxName(
+                    frame.source.clone(),
+                    hitbox.name.clone(),
+                ));
+            }
+        }
+    }
+
+    let mut names = HashSet::new();
+    for animation in sheet.animations_iter() {
+        if !names.insert(animation.get_name()) {
+            conflicts.push(MergeConflict::DuplicateAnimationName(
+                animation.name.clone(),
+            ));
+        }
+        for animation_frame in animation.frames_iter() {
+            if !sheet.has_frame(animation_frame.frame) {
+                conflicts.push(MergeConflict::FrameNotInSheet(
+                    animation.id,
+                    animation.name.clone(),
+                    animation_frame.frame,
+                ));
+            }
+        }
+    }
+}
+
+pub fn merge_sheets(base: &Sheet, ours: &Sheet, theirs: &Sheet) -> (Sheet, Vec<MergeConflict>) {
+    let mut conflicts = Vec::new();
+    let base = &realign_sheet(&[ours], base);
+    let theirs = &realign_sheet(&[ours, base], theirs);
+    let base = &realign_sheet(&[ours, theirs], base);
+
+    let frames = merge_items(
+        &base.frames,
+        &ours.frames,
+        &theirs.frames,
+        &mut conflicts,
+        merge_frame,
+        |f| MergeConflict::FrameDeleted(f.id, f.source.clone()),
+    );
+
+    let animations = merge_items(
+        &base.animations,
+        &ours.animations,
+        &theirs.animations,
+        &mut conflicts,
+        merge_animation,
+        |a| MergeConflict::AnimationDeleted(a.id, a.name.clone()),
+    );
+
+    let export_settings = merge_value(
+        &base.export_settings,
+        &ours.export_settings,
+        &theirs.export_settings,
+    )
+    .unwrap_or_else(|| {
+        conflicts.push(MergeConflict::ExportSettings);
+        ours.export_settings.clone()
+    });
+
+    let sheet = Sheet {
+        frames,
+        animations,
+        export_settings,
+    };
+    check_merged_sheet(&sheet, &mut conflicts);
+    (sheet, conflicts)
+}
+
+#[test]
+fn test_merge_sheets() {
+    let mut base = Sheet::default();
+    let idle = base.add_frame("idle.png");
+    let walk = base.add_frame("walk.png");
+    let hitbox = base.get_frame_mut(walk).unwrap().add_hitbox().get_id();
+    let animation = base.add_animation_with_name("walk").get_id();
+
+    let mut ours = base.clone();
+    ours.add_frame("run.png");
+    ours.rename_animation(animation, "walk_left").unwrap();
+    ours.get_frame_mut(walk)
+        .unwrap()
+        .rename_hitbox(hitbox, "hurt")
+        .unwrap();
+
+    let mut theirs = base.clone();
+    theirs.delete_frame(idle);
+    theirs
+        .get_animation_mut(animation)
+        .unwrap()
+        .insert_frame(walk, 0)
+        .unwrap();
+    theirs
+        .get_frame_mut(walk)
+        .unwrap()
+        .rename_hitbox(hitbox, "weak_spot")
+        .unwrap();
+
+    let (merged, conflicts) = merge_sheets(&base, &ours, &theirs);
+    assert!(!merged.has_frame(idle));
+    assert!(merged.has_frame_source("run.png"));
+    let merged_animation = merged.get_animation(animation).unwrap();
+    assert_eq!(merged_animation.get_name(), "walk_left");
+    assert_eq!(merged_animation.get_num_frames(), 1);
+    assert_eq!(
+        conflicts,
+        vec![MergeConflict::HitboxName(
+            walk,
+            hitbox,
+            "walk.png".into(),
+            "hurt".to_owned(),
+            "weak_spot".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn test_merge_sheets_matches_by_name() {
+    let mut base = Sheet::default();
+    let walk = base.add_frame("walk.png");
+    let hitbox = base.get_frame_mut(walk).unwrap().add_hitbox().get_id();
+    let animation = base.add_animation_with_name("walk").get_id();
+    base.get_animation_mut(animation)
+        .unwrap()
+        .insert_frame(walk, 0)
+        .unwrap();
+
+    let mut ours = base.clone();
+    ours.rename_animation(animation, "walk_left").unwrap();
+
+    // Their copy was migrated separately and assigned different IDs
+    let mut theirs = base.clone();
+    theirs.frames[0].id = FrameId::generate();
+    theirs.frames[0].hitboxes[0].id = HitboxId::generate();
+    theirs.animations[0].id = AnimationId::generate();
+    theirs.animations[0].timeline[0].frame = theirs.frames[0].id;
+    theirs.animations[0].is_looping = !base.animations[0].is_looping;
+
+    let (merged, conflicts) = merge_sheets(&base, &ours, &theirs);
+    assert_eq!(conflicts, vec![]);
+    assert_eq!(merged.frames.len(), 1);
+    assert_eq!(merged.animations.len(), 1);
+    let merged_animation = merged.get_animation(animation).unwrap();
+    assert_eq!(merged_animation.get_name(), "walk_left");
+    assert_ne!(merged_animation.is_looping, base.animations[0].is_looping);
+    assert_eq!(merged_animation.timeline[0].frame, walk);
+    let merged_frame = merged.get_frame(walk).unwrap();
+    assert_eq!(merged_frame.hitboxes.len(), 1);
+    assert_eq!(merged_frame.hitboxes[0].id, hitbox);
+}
@@ -3,23 +3,42 @@ use dunce::canonicalize;
 use euclid::*;
 use failure::Error;
 use pathdiff::diff_paths;
-use std::path::{Path, PathBuf};
-use std::time::Duration;
-
-pub use self::compat::version2::*;
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub use self::clipboard::*;
+pub use self::compat::version3::*;
 use self::constants::*;
+pub use self::diff::*;
+pub use self::merge::*;
+pub use self::validate::*;
 
+mod clipboard;
 pub mod compat;
+mod diff;
+mod matching;
+mod merge;
+mod validate;
 
 pub mod constants {
     pub const MAX_ANIMATION_NAME_LENGTH: usize = 32;
     pub const MAX_HITBOX_NAME_LENGTH: usize = 32;
+    pub const DEFAULT_FRAME_DURATION: u32 = 100; // TODO better default?
 }
 
 #[derive(Fail, Debug)]
 pub enum SheetError {
     #[fail(display = "Animation was not found")]
     AnimationNotFound,
+    #[fail(display = "Frame was not found")]
+    FrameNotFound,
     #[fail(display = "Hitbox was not found")]
     HitboxNotFound,
     #[fail(display = "Animation name too long")]
@@ -30,20 +49,147 @@ pub enum SheetError {
     AbsoluteToRelativePath,
     #[fail(display = "Invalid frame index")]
     InvalidFrameIndex,
+    #[fail(display = "A frame with this source already exists")]
+    FrameAlreadyExists,
+}
+
+// Files which cannot be found on disk keep a normalized version of their expected location
+pub(crate) fn absolute_path<T: AsRef<Path>, U: AsRef<Path>>(relative_to: T, path: U) -> PathBuf {
+    let joined = relative_to.as_ref().join(path);
+    canonicalize(&joined).unwrap_or_else(|_| {
+        let mut normalized = PathBuf::new();
+        for component in joined.components() {
+            match component {
+                Component::CurDir => (),
+                Component::ParentDir => {
+                    normalized.pop();
+                }
+                c => normalized.push(c.as_os_str()),
+            }
+        }
+        normalized
+    })
+}
+
+// Relative paths always use forward slashes so sheets saved on any platform are identical
+pub(crate) fn relative_path<T: AsRef<Path>, U: AsRef<Path>>(
+    relative_to: T,
+    path: U,
+) -> Result<PathBuf, SheetError> {
+    let relative = diff_paths(path.as_ref(), relative_to.as_ref())
+        .ok_or(SheetError::AbsoluteToRelativePath)?;
+    let mut joined = OsString::new();
+    for (index, component) in relative.components().enumerate() {
+        if index > 0 {
+            joined.push("/");
+        }
+        joined.push(component.as_os_str());
+    }
+    Ok(joined.into())
+}
+
+// Empty export paths are left to the project defaults, so they stay empty
+fn absolute_export_path<T: AsRef<Path>, U: AsRef<Path>>(relative_to: T, path: U) -> PathBuf {
+    if path.as_ref().as_os_str().is_empty() {
+        PathBuf::new()
+    } else {
+        absolute_path(relative_to, path)
+    }
+}
+
+fn relative_export_path<T: AsRef<Path>, U: AsRef<Path>>(
+    relative_to: T,
+    path: U,
+) -> Result<PathBuf, SheetError> {
+    if path.as_ref().as_os_str().is_empty() {
+        Ok(PathBuf::new())
+    } else {
+        relative_path(relative_to, path)
+    }
+}
+
+static NEXT_ID_SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+// IDs must not collide across sheets edited separately: the sequence number makes them unique within
+// this process, while the process ID, clock and random hash keys set this process apart from others
+fn generate_id() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_usize(NEXT_ID_SEQUENCE.fetch_add(1, AtomicOrdering::Relaxed));
+    hasher.write_u32(process::id());
+    if let Ok(time) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        hasher.write_u128(time.as_nanos());
+    }
+    hasher.finish()
+}
+
+fn truncate_name(name: &str, max_length: usize) -> String {
+    let mut name = name.to_owned();
+    while name.len() > max_length {
+        name.pop();
+    }
+    name
+}
+
+// Names already taken get a numeric suffix after the given one. Names are shortened
+// when the suffixes do not fit, the suffixes are always kept.
+fn unique_name<F: Fn(&str) -> bool>(
+    name: &str,
+    suffix: &str,
+    max_length: usize,
+    is_taken: F,
+) -> String {
+    let mut unique_name = truncate_name(name, max_length - suffix.len()) + suffix;
+    let mut index = 2;
+    while is_taken(&unique_name) {
+        let number = format!(" {}", index);
+        let length = max_length - suffix.len() - number.len();
+        unique_name = truncate_name(name, length) + suffix + &number;
+        index += 1;
+    }
+    unique_name
+}
+
+impl FrameId {
+    pub fn generate() -> FrameId {
+        FrameId(generate_id())
+    }
+}
+
+impl fmt::Display for FrameId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl AnimationId {
+    pub fn generate() -> AnimationId {
+        AnimationId(generate_id())
+    }
+}
+
+impl fmt::Display for AnimationId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl HitboxId {
+    pub fn generate() -> HitboxId {
+        HitboxId(generate_id())
+    }
+}
+
+impl fmt::Display for HitboxId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
 impl Sheet {
     pub fn with_relative_paths<T: AsRef<Path>>(&self, relative_to: T) -> Result<Sheet, Error> {
         let mut sheet = self.clone();
         for frame in sheet.frames_iter_mut() {
-            frame.source = diff_paths(&frame.source, relative_to.as_ref())
-                .ok_or(SheetError::AbsoluteToRelativePath)?;
-        }
-        for animation in sheet.animations.iter_mut() {
-            for animation_frame in animation.frames_iter_mut() {
-                animation_frame.frame = diff_paths(&animation_frame.frame, relative_to.as_ref())
-                    .ok_or(SheetError::AbsoluteToRelativePath)?;
-            }
+            frame.source = relative_path(&relative_to, &frame.source)?;
         }
         if let Some(e) = sheet.export_settings {
             sheet.export_settings = e.with_relative_paths(relative_to).ok();
@@ -54,13 +200,7 @@ impl Sheet {
     pub fn with_absolute_paths<T: AsRef<Path>>(&self, relative_to: T) -> Result<Sheet, Error> {
         let mut sheet = self.clone();
         for frame in sheet.frames_iter_mut() {
-            frame.source = canonicalize(relative_to.as_ref().join(&frame.source))?;
-        }
-        for animation in sheet.animations.iter_mut() {
-            for animation_frame in animation.frames_iter_mut() {
-                animation_frame.frame =
-                    canonicalize(relative_to.as_ref().join(&&animation_frame.frame))?;
-            }
+            frame.source = absolute_path(&relative_to, &frame.source);
         }
         if let Some(e) = sheet.export_settings {
             sheet.export_settings = Some(e.with_absolute_paths(relative_to)?);
@@ -68,6 +208,17 @@ impl Sheet {
         Ok(sheet)
     }
 
+    // Sorting keeps saved files stable no matter the order in which content was added
+    pub fn with_canonical_order(&self) -> Sheet {
+        let mut sheet = self.clone();
+        sheet.frames.sort();
+        for frame in sheet.frames_iter_mut() {
+            frame.hitboxes.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        sheet.animations.sort();
+        sheet
+    }
+
     pub fn frames_iter(&self) -> std::slice::Iter<'_, Frame> {
         self.frames.iter()
     }
@@ -80,48 +231,67 @@ impl Sheet {
         self.animations.iter()
     }
 
-    pub fn has_frame<T: AsRef<Path>>(&self, path: T) -> bool {
+    pub fn has_frame(&self, id: FrameId) -> bool {
+        self.frames.iter().any(|f| f.id == id)
+    }
+
+    pub fn has_frame_source<T: AsRef<Path>>(&self, path: T) -> bool {
         self.frames.iter().any(|f| f.source == path.as_ref())
     }
 
-    pub fn has_animation<T: AsRef<str>>(&self, name: T) -> bool {
+    pub fn has_animation(&self, id: AnimationId) -> bool {
+        self.animations.iter().any(|a| a.id == id)
+    }
+
+    pub fn has_animation_name<T: AsRef<str>>(&self, name: T) -> bool {
         self.animations.iter().any(|a| a.name == name.as_ref())
     }
 
-    pub fn add_frame<T: AsRef<Path>>(&mut self, path: T) {
-        if self.has_frame(&path) {
-            return;
+    pub fn add_frame<T: AsRef<Path>>(&mut self, path: T) -> FrameId {
+        if let Some(frame) = self.get_frame_by_source(&path) {
+            return frame.id;
         }
         let frame = Frame::new(path);
+        let id = frame.id;
         self.frames.push(frame);
+        id
     }
 
     pub fn add_animation(&mut self) -> &mut Animation {
-        let mut name = "New Animation".to_owned();
-        let mut index = 2;
-        while self.has_animation(&name) {
-            name = format!("New Animation {}", index);
-            index += 1;
-        }
+        self.add_animation_with_name("New Animation")
+    }
+
+    pub fn add_animation_with_name<T: AsRef<str>>(&mut self, name: T) -> &mut Animation {
+        let name = unique_name(name.as_ref(), "", MAX_ANIMATION_NAME_LENGTH, |n| {
+            self.has_animation_name(n)
+        });
         let animation = Animation::new(&name);
         self.animations.push(animation);
         self.animations.last_mut().unwrap()
     }
 
-    pub fn get_frame<T: AsRef<Path>>(&self, path: T) -> Option<&Frame> {
+    pub fn get_frame(&self, id: FrameId) -> Option<&Frame> {
+        self.frames.iter().find(|f| f.id == id)
+    }
+
+    pub fn get_frame_mut(&mut self, id: FrameId) -> Option<&mut Frame> {
+        self.frames.iter_mut().find(|f| f.id == id)
+    }
+
+    pub fn get_frame_by_source<T: AsRef<Path>>(&self, path: T) -> Option<&Frame> {
         self.frames.iter().find(|f| f.source == path.as_ref())
     }
 
-    pub fn get_frame_mut<T: AsRef<Path>>(&mut self, path: T) -> Option<&mut Frame> {
-        self.frames.iter_mut().find(|f| f.source == path.as_ref())
+    pub fn get_animation(&self, id: AnimationId) -> Option<&Animation> {
+        self.animations.iter().find(|a| a.id == id)
     }
 
-    pub fn get_animation<T: AsRef<str>>(&self, name: T) -> Option<&Animation> {
-        self.animations.iter().find(|a| a.name == name.as_ref())
+    pub fn get_animation_mut(&mut self, id: AnimationId) -> Option<&mut Animation> {
+        self.animations.iter_mut().find(|a| a.id == id)
     }
 
-    pub fn get_animation_mut<T: AsRef<str>>(&mut self, name: T) -> Option<&mut Animation> {
-        self.animations.iter_mut().find(|a| a.name == name.as_ref())
+    pub fn get_animation_by_name<T: AsRef<str>>(&self, name: T) -> Option<&Animation> {
+        self.animations.iter().find(|a| a.name == name.as_ref())
     }
 
     pub fn get_export_settings(&self) -> &Option<ExportSettings> {
@@ -132,40 +302,100 @@ impl Sheet {
         self.export_settings = Some(export_settings);
     }
 
-    pub fn rename_animation<T: AsRef<str>, U: AsRef<str>>(
+    pub fn rename_animation<T: AsRef<str>>(
         &mut self,
-        old_name: T,
-        new_name: U,
+        id: AnimationId,
+        new_name: T,
     ) -> Result<(), Error> {
         if new_name.as_ref().len() > MAX_ANIMATION_NAME_LENGTH {
             return Err(SheetError::AnimationNameTooLong.into());
         }
         let animation = self
-            .get_animation_mut(old_name)
+            .get_animation_mut(id)
             .ok_or(SheetError::AnimationNotFound)?;
         animation.name = new_name.as_ref().to_owned();
         Ok(())
     }
 
-    pub fn delete_frame<T: AsRef<Path>>(&mut self, path: T) {
-        self.frames.retain(|f| f.source != path.as_ref());
+    pub fn relocate_frames(&mut self, moves: &[(PathBuf, PathBuf)]) -> Result<(), Error> {
+        let new_source = |path: &Path| {
+            moves
+                .iter()
+                .find(|(from, _)| from == path)
+                .map(|(_, to)| to.to_owned())
+        };
+        for (from, to) in moves {
+            if from != to && self.has_frame_source(to) && new_source(to).is_none() {
+                return Err(SheetError::FrameAlreadyExists.into());
+            }
+        }
+        let mut destinations = HashSet::new();
+        for (_, to) in moves.iter().filter(|(from, _)| self.has_frame_source(from)) {
+            if !destinations.insert(to) {
+                return Err(SheetError::FrameAlreadyExists.into());
+            }
+        }
+        for frame in self.frames.iter_mut() {
+            if let Some(to) = new_source(&frame.source) {
+                frame.source = to;
+            }
+        }
+        Ok(())
+    }
+
+    // The duplicate uses a copy of the original image, hitboxes are carried over under new IDs
+    pub fn duplicate_frame<T: AsRef<Path>>(
+        &mut self,
+        id: FrameId,
+        new_source: T,
+    ) -> Result<FrameId, Error> {
+        if self.has_frame_source(&new_source) {
+            return Err(SheetError::FrameAlreadyExists.into());
+        }
+        let mut frame = self.get_frame(id).ok_or(SheetError::FrameNotFound)?.clone();
+        frame.id = FrameId::generate();
+        frame.source = new_source.as_ref().to_owned();
+        for hitbox in frame.hitboxes.iter_mut() {
+            hitbox.id = HitboxId::generate();
+        }
+        let new_id = frame.id;
+        self.frames.push(frame);
+        Ok(new_id)
+    }
+
+    pub fn duplicate_animation(&mut self, id: AnimationId) -> Result<AnimationId, Error> {
+        let animation = self
+            .get_animation(id)
+            .ok_or(SheetError::AnimationNotFound)?
+            .clone();
+        let name = unique_name(&animation.name, " copy", MAX_ANIMATION_NAME_LENGTH, |n| {
+            self.has_animation_name(n)
+        });
+        let duplicate = self.add_animation_with_name(name);
+        duplicate.timeline = animation.timeline;
+        duplicate.is_looping = animation.is_looping;
+        Ok(duplicate.id)
+    }
+
+    pub fn delete_frame(&mut self, id: FrameId) {
+        self.frames.retain(|f| f.id != id);
         for animation in self.animations.iter_mut() {
-            animation.timeline.retain(|af| af.frame != path.as_ref())
+            animation.timeline.retain(|af| af.frame != id)
         }
     }
 
-    pub fn delete_hitbox<T: AsRef<Path>, U: AsRef<str>>(&mut self, path: T, name: U) {
-        if let Some(frame) = self.get_frame_mut(path.as_ref()) {
-            frame.hitboxes.retain(|h| h.name != name.as_ref());
+    pub fn delete_hitbox(&mut self, frame_id: FrameId, hitbox_id: HitboxId) {
+        if let Some(frame) = self.get_frame_mut(frame_id) {
+            frame.hitboxes.retain(|h| h.id != hitbox_id);
         }
     }
 
-    pub fn delete_animation<T: AsRef<str>>(&mut self, name: T) {
-        self.animations.retain(|a| a.name != name.as_ref());
+    pub fn delete_animation(&mut self, id: AnimationId) {
+        self.animations.retain(|a| a.id != id);
     }
 
-    pub fn delete_animation_frame<T: AsRef<str>>(&mut self, animation_name: T, frame_index: usize) {
-        if let Some(animation) = self.get_animation_mut(animation_name) {
+    pub fn delete_animation_frame(&mut self, animation_id: AnimationId, frame_index: usize) {
+        if let Some(animation) = self.get_animation_mut(animation_id) {
             if frame_index < animation.timeline.len() {
                 animation.timeline.remove(frame_index);
             }
@@ -176,12 +406,17 @@ impl Sheet {
 impl Animation {
     pub fn new<T: AsRef<str>>(name: T) -> Animation {
         Animation {
+            id: AnimationId::generate(),
             name: name.as_ref().to_owned(),
             timeline: vec![],
             is_looping: true,
         }
     }
 
+    pub fn get_id(&self) -> AnimationId {
+        self.id
+    }
+
     pub fn get_name(&self) -> &str {
         &self.name
     }
@@ -254,8 +489,9 @@ impl Animation {
             .collect()
     }
 
-    pub fn insert_frame<T: AsRef<Path>>(&mut self, frame: T, index: usize) -> Result<(), Error> {
-        // TODO validate that frame exists in sheet!
+    pub fn insert_frame(&mut self, frame: FrameId, index: usize) -> Result<(), Error> {
+        // Frames are checked against the sheet by callers, sheets referencing
+        // missing frames must still load so the problem can be reported
         if index > self.timeline.len() {
             return Err(SheetError::InvalidFrameIndex.into());
         }
@@ -264,6 +500,16 @@ impl Animation {
         Ok(())
     }
 
+    pub fn duplicate_frame(&mut self, index: usize) -> Result<(), Error> {
+        let animation_frame = self
+            .timeline
+            .get(index)
+            .ok_or(SheetError::InvalidFrameIndex)?
+            .clone();
+        self.timeline.insert(index + 1, animation_frame);
+        Ok(())
+    }
+
     pub fn reorder_frame(&mut self, old_index: usize, new_index: usize) -> Result<(), Error> {
         if old_index >= self.timeline.len() || new_index > self.timeline.len() {
             return Err(SheetError::InvalidFrameIndex.into());
@@ -302,11 +548,16 @@ impl PartialOrd for Animation {
 impl Frame {
     pub fn new<T: AsRef<Path>>(path: T) -> Frame {
         Frame {
+            id: FrameId::generate(),
             source: path.as_ref().to_owned(),
             hitboxes: vec![],
         }
     }
 
+    pub fn get_id(&self) -> FrameId {
+        self.id
+    }
+
     pub fn get_source(&self) -> &Path {
         &self.source
     }
@@ -315,27 +566,37 @@ impl Frame {
         self.hitboxes.iter()
     }
 
-    pub fn get_hitbox<T: AsRef<str>>(&self, name: T) -> Option<&Hitbox> {
+    pub fn get_hitbox(&self, id: HitboxId) -> Option<&Hitbox> {
+        self.hitboxes.iter().find(|a| a.id == id)
+    }
+
+    pub fn get_hitbox_mut(&mut self, id: HitboxId) -> Option<&mut Hitbox> {
+        self.hitboxes.iter_mut().find(|a| a.id == id)
+    }
+
+    pub fn get_hitbox_by_name<T: AsRef<str>>(&self, name: T) -> Option<&Hitbox> {
         self.hitboxes.iter().find(|a| a.name == name.as_ref())
     }
 
-    pub fn get_hitbox_mut<T: AsRef<str>>(&mut self, name: T) -> Option<&mut Hitbox> {
-        self.hitboxes.iter_mut().find(|a| a.name == name.as_ref())
+    pub fn has_hitbox(&self, id: HitboxId) -> bool {
+        self.hitboxes.iter().any(|a| a.id == id)
     }
 
-    pub fn has_hitbox<T: AsRef<str>>(&self, name: T) -> bool {
+    pub fn has_hitbox_name<T: AsRef<str>>(&self, name: T) -> bool {
         self.hitboxes.iter().any(|a| a.name == name.as_ref())
     }
 
     pub fn add_hitbox(&mut self) -> &mut Hitbox {
-        let mut name = "New Hitbox".to_owned();
-        let mut index = 2;
-        while self.has_hitbox(&name) {
-            name = format!("New Hitbox {}", index);
-            index += 1;
-        }
+        self.add_hitbox_with_name("New Hitbox")
+    }
+
+    pub fn add_hitbox_with_name<T: AsRef<str>>(&mut self, name: T) -> &mut Hitbox {
+        let name = unique_name(name.as_ref(), "", MAX_HITBOX_NAME_LENGTH, |n| {
+            self.has_hitbox_name(n)
+        });
 
         self.hitboxes.push(Hitbox {
+            id: HitboxId::generate(),
             name,
             geometry: Shape::Rectangle(Rectangle {
                 top_left: (0, 0),
@@ -345,17 +606,32 @@ impl Frame {
         self.hitboxes.last_mut().unwrap() // TODO no unwrap?
     }
 
-    pub fn rename_hitbox<T: AsRef<str>, U: AsRef<str>>(
-        &mut self,
-        old_name: T,
-        new_name: U,
-    ) -> Result<(), Error> {
+    // Keeps hitboxes in place relative to the artwork when the frame image is resized
+    pub fn scale_hitboxes(&mut self, from_size: Vector2D<u32>, to_size: Vector2D<u32>) {
+        if from_size.x == 0 || from_size.y == 0 {
+            return;
+        }
+        let scale: Vector2D<f32> = vec2(
+            to_size.x as f32 / from_size.x as f32,
+            to_size.y as f32 / from_size.y as f32,
+        );
+        for hitbox in self.hitboxes.iter_mut() {
+            let position = hitbox.get_position().to_f32();
+            let size = hitbox.get_size().to_f32();
+            hitbox.set_position(
+                vec2(position.x * scale.x, position.y * scale.y)
+                    .round()
+                    .to_i32(),
+            );
+            hitbox.set_size(vec2(size.x * scale.x, size.y * scale.y).round().to_u32());
+        }
+    }
+
+    pub fn rename_hitbox<T: AsRef<str>>(&mut self, id: HitboxId, new_name: T) -> Result<(), Error> {
         if new_name.as_ref().len() > MAX_HITBOX_NAME_LENGTH {
             return Err(SheetError::HitboxNameTooLong.into());
         }
-        let hitbox = self
-            .get_hitbox_mut(old_name)
-            .ok_or(SheetError::HitboxNotFound)?;
+        let hitbox = self.get_hitbox_mut(id).ok_or(SheetError::HitboxNotFound)?;
         hitbox.name = new_name.as_ref().to_owned();
         Ok(())
     }
@@ -376,6 +652,10 @@ impl PartialOrd for Frame {
 }
 
 impl Hitbox {
+    pub fn get_id(&self) -> HitboxId {
+        self.id
+    }
+
     pub fn get_name(&self) -> &str {
         &self.name
     }
@@ -430,16 +710,16 @@ impl PartialOrd for Hitbox {
 }
 
 impl AnimationFrame {
-    pub fn new<T: AsRef<Path>>(frame: T) -> AnimationFrame {
+    pub fn new(frame: FrameId) -> AnimationFrame {
         AnimationFrame {
-            frame: frame.as_ref().to_owned(),
-            duration: 100, // TODO better default?
+            frame,
+            duration: DEFAULT_FRAME_DURATION,
             offset: (0, 0),
         }
     }
 
-    pub fn get_frame(&self) -> &Path {
-        &self.frame
+    pub fn get_frame(&self) -> FrameId {
+        self.frame
     }
 
     pub fn get_duration(&self) -> u32 {
@@ -465,9 +745,10 @@ impl ExportFormat {
         relative_to: T,
     ) -> Result<ExportFormat, Error> {
         match self {
-            ExportFormat::Template(p) => Ok(ExportFormat::Template(
-                diff_paths(&p, relative_to.as_ref()).ok_or(SheetError::AbsoluteToRelativePath)?,
-            )),
+            ExportFormat::Template(p) => Ok(ExportFormat::Template(relative_export_path(
+                relative_to,
+                p,
+            )?)),
         }
     }
 
@@ -476,9 +757,9 @@ impl ExportFormat {
         relative_to: T,
     ) -> Result<ExportFormat, Error> {
         match self {
-            ExportFormat::Template(p) => Ok(ExportFormat::Template(canonicalize(
-                relative_to.as_ref().join(&p),
-            )?)),
+            ExportFormat::Template(p) => {
+                Ok(ExportFormat::Template(absolute_export_path(relative_to, p)))
+            }
         }
     }
 }
@@ -499,12 +780,9 @@ impl ExportSettings {
     ) -> Result<ExportSettings, Error> {
         Ok(ExportSettings {
             format: self.format.with_relative_paths(&relative_to)?,
-            texture_destination: diff_paths(&self.texture_destination, relative_to.as_ref())
-                .ok_or(SheetError::AbsoluteToRelativePath)?,
-            metadata_destination: diff_paths(&self.metadata_destination, relative_to.as_ref())
-                .ok_or(SheetError::AbsoluteToRelativePath)?,
-            metadata_paths_root: diff_paths(&self.metadata_paths_root, relative_to.as_ref())
-                .ok_or(SheetError::AbsoluteToRelativePath)?,
+            texture_destination: relative_export_path(&relative_to, &self.texture_destination)?,
+            metadata_destination: relative_export_path(&relative_to, &self.metadata_destination)?,
+            metadata_paths_root: relative_export_path(&relative_to, &self.metadata_paths_root)?,
         })
     }
 
@@ -514,15 +792,127 @@ impl ExportSettings {
     ) -> Result<ExportSettings, Error> {
         Ok(ExportSettings {
             format: self.format.with_absolute_paths(&relative_to)?,
-            texture_destination: canonicalize(
-                relative_to.as_ref().join(&self.texture_destination),
-            )?,
-            metadata_destination: canonicalize(
-                relative_to.as_ref().join(&self.metadata_destination),
-            )?,
-            metadata_paths_root: canonicalize(
-                relative_to.as_ref().join(&self.metadata_paths_root),
-            )?,
+            texture_destination: absolute_export_path(&relative_to, &self.texture_destination),
+            metadata_destination: absolute_export_path(&relative_to, &self.metadata_destination),
+            metadata_paths_root: absolute_export_path(&relative_to, &self.metadata_paths_root),
         })
     }
 }
+
+#[test]
+fn test_missing_frames_and_relocation() {
+    let mut sheet = Sheet::default();
+    let frame_id = sheet.add_frame("art/../missing/walk_0.png");
+    sheet.add_frame("missing/walk_1.png");
+    let walk = sheet.add_animation_with_name("walk");
+    walk.insert_frame(frame_id, 0).unwrap();
+    let walk_id = walk.get_id();
+
+    let root = Path::new("/does/not/exist");
+    let mut sheet = sheet.with_absolute_paths(root).unwrap();
+    let old = root.join("missing/walk_0.png");
+    assert!(sheet.has_frame_source(&old));
+
+    let new = PathBuf::from("/found/walk_0.png");
+    let taken = root.join("missing/walk_1.png");
+    assert!(sheet
+        .relocate_frames(&[(old.clone(), taken.clone())])
+        .is_err());
+    let merged = PathBuf::from("/found/merged.png");
+    assert!(sheet
+        .relocate_frames(&[(old.clone(), merged.clone()), (taken, merged)])
+        .is_err());
+    sheet
+        .relocate_frames(&[(old.clone(), new.clone())])
+        .unwrap();
+    assert!(!sheet.has_frame_source(&old));
+    assert_eq!(
+        sheet.get_frame(frame_id).unwrap().get_source(),
+        new.as_path()
+    );
+    let walk = sheet.get_animation(walk_id).unwrap();
+    assert_eq!(walk.get_frame(0).unwrap().get_frame(), frame_id);
+}
+
+#[test]
+fn test_empty_export_paths() {
+    let mut export_settings = ExportSettings::new();
+    export_settings.texture_destination = "/game/sprites/hero.png".into();
+
+    let relative = export_settings.with_relative_paths("/game").unwrap();
+    assert_eq!(
+        relative.texture_destination,
+        PathBuf::from("sprites/hero.png")
+    );
+    assert_eq!(relative.metadata_destination, PathBuf::new());
+    assert_eq!(relative.format, ExportFormat::Template(PathBuf::new()));
+
+    let absolute = relative.with_absolute_paths("/game").unwrap();
+    assert_eq!(absolute, export_settings);
+}
+
+#[test]
+fn test_scale_hitboxes() {
+    let mut frame = Frame::new("frame.png");
+    {
+        let hitbox = frame.add_hitbox();
+        hitbox.set_position(vec2(-8, -4));
+        hitbox.set_size(vec2(16, 6));
+    }
+    frame.scale_hitboxes(vec2(32, 16), vec2(64, 8));
+    let hitbox = frame.hitboxes_iter().next().unwrap();
+    assert_eq!(hitbox.get_position(), vec2(-16, -2));
+    assert_eq!(hitbox.get_size(), vec2(32, 3));
+}
+
+#[test]
+fn test_duplicate() {
+    let mut sheet = Sheet::default();
+    let frame = sheet.add_frame("walk_0.png");
+    let hitbox = sheet.get_frame_mut(frame).unwrap().add_hitbox().get_id();
+    let duplicate_frame = sheet.duplicate_frame(frame, "walk_0 copy.png").unwrap();
+    let duplicate_hitbox = sheet
+        .get_frame(duplicate_frame)
+        .unwrap()
+        .get_hitbox_by_name("New Hitbox")
+        .unwrap();
+    assert_ne!(duplicate_hitbox.get_id(), hitbox);
+    assert!(sheet.duplicate_frame(frame, "walk_0 copy.png").is_err());
+
+    let name = "a".repeat(MAX_ANIMATION_NAME_LENGTH);
+    let animation = sheet.add_animation_with_name(&name);
+    animation.insert_frame(frame, 0).unwrap();
+    let animation_id = animation.get_id();
+    for _ in 0..2 {
+        let duplicate = sheet.duplicate_animation(animation_id).unwrap();
+        let duplicate = sheet.get_animation(duplicate).unwrap();
+        assert!(duplicate.get_name().len() <= MAX_ANIMATION_NAME_LENGTH);
+        assert_eq!(duplicate.get_num_frames(), 1);
+    }
+    assert!(sheet.has_animation_name(format!("{} copy", &name[..27])));
+    assert!(sheet.has_animation_name(format!("{} copy 2", &name[..25])));
+}
+
+#[test]
+fn test_unique_names() {
+    let mut sheet = Sheet::default();
+    let name = "a".repeat(MAX_ANIMATION_NAME_LENGTH);
+    assert_eq!(sheet.add_animation_with_name(&name).get_name(), name);
+    assert_eq!(
+        sheet.add_animation_with_name(&name).get_name(),
+        format!("{} 2", &name[..30])
+    );
+    let too_long = "b".repeat(MAX_ANIMATION_NAME_LENGTH + 1);
+    assert_eq!(
+        sheet.add_animation_with_name(&too_long).get_name().len(),
+        MAX_ANIMATION_NAME_LENGTH
+    );
+
+    let mut frame = Frame::new("walk_0.png");
+    let name = "c".repeat(MAX_HITBOX_NAME_LENGTH);
+    assert_eq!(frame.add_hitbox_with_name(&name).get_name(), name);
+    assert_eq!(
+        frame.add_hitbox_with_name(&name).get_name(),
+        format!("{} 2", &name[..30])
+    );
+}
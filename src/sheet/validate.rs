@@ -0,0 +1,295 @@
+use euclid::*;
+use failure::Error;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::sheet::*;
+
+// Problems carry IDs to locate the offending item, and names to describe it
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    MissingImage(FrameId, PathBuf),
+    UnreadableImage(FrameId, PathBuf),
+    DuplicateFrame(FrameId, PathBuf),
+    HitboxOutOfBounds(FrameId, HitboxId, PathBuf, String),
+    DuplicateHitboxName(FrameId, HitboxId, PathBuf, String),
+    EmptyAnimation(AnimationId, String),
+    DuplicateAnimationName(AnimationId, String),
+    FrameNotInSheet(AnimationId, String, usize, FrameId),
+    ZeroDurationKeyframe(AnimationId, String, usize),
+    InvalidTextureDestination(PathBuf),
+    InvalidMetadataDestination(PathBuf),
+    InvalidMetadataPathsRoot(PathBuf),
+    MissingTemplate(PathBuf),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Problem::*;
+        match self {
+            MissingImage(_, p) => write!(f, "Image file {} does not exist", p.display()),
+            UnreadableImage(_, p) => write!(f, "Image file {} could not be read", p.display()),
+            DuplicateFrame(_, p) => write!(f, "Frame {} is listed more than once", p.display()),
+            HitboxOutOfBounds(_, _, p, h) => write!(
+                f,
+                "Hitbox \"{}\" extends outside of frame {}",
+                h,
+                p.display()
+            ),
+            DuplicateHitboxName(_, _, p, h) => write!(
+                f,
+                "Frame {} has more than one hitbox named \"{}\"",
+                p.display(),
+                h
+            ),
+            EmptyAnimation(_, a) => write!(f, "Animation \"{}\" has no frames", a),
+            DuplicateAnimationName(_, a) => {
+                write!(f, "More than one animation is named \"{}\"", a)
+            }
+            FrameNotInSheet(_, a, i, id) => write!(
+                f,
+                "Frame #{} of animation \"{}\" uses frame {} which is not part of the sheet",
+                i + 1,
+                a,
+                id
+            ),
+            ZeroDurationKeyframe(_, a, i) => {
+                write!(f, "Frame #{} of animation \"{}\" has no duration", i + 1, a)
+            }
+            InvalidTextureDestination(p) => {
+                write!(f, "Cannot write texture to {}", p.display())
+            }
+            InvalidMetadataDestination(p) => {
+                write!(f, "Cannot write metadata to {}", p.display())
+            }
+            InvalidMetadataPathsRoot(p) => {
+                write!(f, "Metadata paths root {} is not a directory", p.display())
+            }
+            MissingTemplate(p) => write!(f, "Export template {} does not exist", p.display()),
+        }
+    }
+}
+
+fn is_writable_file(path: &Path) -> bool {
+    if path.as_os_str().is_empty() || path.is_dir() {
+        return false;
+    }
+    match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.is_dir(),
+        _ => true,
+    }
+}
+
+fn validate_frame(frame: &Frame, problems: &mut Vec<Problem>) {
+    let source = frame.get_source();
+
+    let mut names = HashSet::new();
+    for hitbox in frame.hitboxes_iter() {
+        if !names.insert(hitbox.get_name()) {
+            problems.push(Problem::DuplicateHitboxName(
+                frame.get_id(),
+                hitbox.get_id(),
+                source.to_owned(),
+                hitbox.get_name().to_owned(),
+            ));
+        }
+    }
+
+    if !source.exists() {
+        problems.push(Problem::MissingImage(frame.get_id(), source.to_owned()));
+        return;
+    }
+    let size: Vector2D<i32> = match read_image_size(source) {
+        Ok(s) => s.to_i32(),
+        Err(_) => {
+            problems.push(Problem::UnreadableImage(frame.get_id(), source.to_owned()));
+            return;
+        }
+    };
+
+    // Hitbox positions are relative to the center of the frame
+    let bounds = rect(-size.x / 2, -size.y / 2, size.x, size.y);
+    for hitbox in frame.hitboxes_iter() {
+        if !bounds.contains_rect(&hitbox.get_rectangle()) {
+            problems.push(Problem::HitboxOutOfBounds(
+                frame.get_id(),
+                hitbox.get_id(),
+                source.to_owned(),
+                hitbox.get_name().to_owned(),
+            ));
+        }
+    }
+}
+
+// Size as written in the PNG, GIF or BMP header
+fn read_header_size(header: &[u8]) -> Option<Vector2D<u32>> {
+    let u16_le = |i: usize| u32::from(u16::from_le_bytes([header[i], header[i + 1]]));
+    let i32_le =
+        |i: usize| i32::from_le_bytes([header[i], header[i + 1], header[i + 2], header[i + 3]]);
+    let u32_be =
+        |i: usize| u32::from_be_bytes([header[i], header[i + 1], header[i + 2], header[i + 3]]);
+    if header.starts_with(&[137, 80, 78, 71, 13, 10, 26, 10]) && header.len() >= 24 {
+        Some(vec2(u32_be(16), u32_be(20)))
+    } else if header.starts_with(b"GIF") && header.len() >= 10 {
+        Some(vec2(u16_le(6), u16_le(8)))
+    } else if header.starts_with(b"BM") && header.len() >= 26 {
+        // Old BMP headers use 16 bits dimensions, newer ones use signed 32 bits dimensions
+        if i32_le(14) == 12 {
+            Some(vec2(u16_le(18), u16_le(20)))
+        } else {
+            Some(vec2(i32_le(18), i32_le(22)).abs().to_u32())
+        }
+    } else {
+        None
+    }
+}
+
+// Only formats without a recognizable header are fully decoded
+pub(crate) fn read_image_size(source: &Path) -> Result<Vector2D<u32>, Error> {
+    let mut header = Vec::new();
+    File::open(source)?.take(26).read_to_end(&mut header)?;
+    match read_header_size(&header) {
+        Some(size) => Ok(size),
+        None => Ok(image::open(source)?.to_rgba().dimensions().into()),
+    }
+}
+
+fn validate_export_settings(settings: &ExportSettings, problems: &mut Vec<Problem>) {
+    if !is_writable_file(&settings.texture_destination) {
+        problems.push(Problem::InvalidTextureDestination(
+            settings.texture_destination.clone(),
+        ));
+    }
+    if !is_writable_file(&settings.metadata_destination) {
+        problems.push(Problem::InvalidMetadataDestination(
+            settings.metadata_destination.clone(),
+        ));
+    }
+    if !settings.metadata_paths_root.is_dir() {
+        problems.push(Problem::InvalidMetadataPathsRoot(
+            settings.metadata_paths_root.clone(),
+        ));
+    }
+    match &settings.format {
+        ExportFormat::Template(p) => {
+            if !p.is_file() {
+                problems.push(Problem::MissingTemplate(p.clone()));
+            }
+        }
+    }
+}
+
+impl Sheet {
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+
+        let mut sources = HashSet::new();
+        for frame in self.frames_iter() {
+            if !sources.insert(frame.get_source()) {
+                problems.push(Problem::DuplicateFrame(
+                    frame.get_id(),
+                    frame.get_source().to_owned(),
+                ));
+                continue;
+            }
+            validate_frame(frame, &mut problems);
+        }
+
+        let mut names = HashSet::new();
+        for animation in self.animations_iter() {
+            let id = animation.get_id();
+            let name = animation.get_name();
+            if !names.insert(name) {
+                problems.push(Problem::DuplicateAnimationName(id, name.to_owned()));
+            }
+            if animation.get_num_frames() == 0 {
+                problems.push(Problem::EmptyAnimation(id, name.to_owned()));
+            }
+            for (index, animation_frame) in animation.frames_iter().enumerate() {
+                if !self.has_frame(animation_frame.get_frame()) {
+                    problems.push(Problem::FrameNotInSheet(
+                        id,
+                        name.to_owned(),
+                        index,
+                        animation_frame.get_frame(),
+                    ));
+                }
+                if animation_frame.get_duration() == 0 {
+                    problems.push(Problem::ZeroDurationKeyframe(id, name.to_owned(), index));
+                }
+            }
+        }
+
+        if let Some(settings) = self.get_export_settings() {
+            validate_export_settings(settings, &mut problems);
+        }
+
+        problems
+    }
+}
+
+#[test]
+fn test_validate_sheet() {
+    let mut sheet = Sheet::default();
+    let frame_id = sheet.add_frame("missing.png");
+    let duplicate_frame = Frame::new("missing.png");
+    let duplicate_frame_id = duplicate_frame.get_id();
+    sheet.frames.push(duplicate_frame);
+    sheet.frames[0].add_hitbox();
+    sheet.frames[0].add_hitbox();
+    sheet.frames[0].hitboxes[1].name = sheet.frames[0].hitboxes[0].name.clone();
+
+    let idle_id = sheet.add_animation_with_name("idle").get_id();
+    let walk_id = sheet.add_animation_with_name("walk").get_id();
+    sheet.animations[1].name = "idle".to_owned();
+    let unknown_frame_id = FrameId::generate();
+    {
+        let walk = &mut sheet.animations[1];
+        walk.insert_frame(frame_id, 0).unwrap();
+        walk.insert_frame(unknown_frame_id, 1).unwrap();
+        walk.get_frame_mut(0).unwrap().set_duration(0);
+    }
+
+    let problems = sheet.validate();
+    let hitbox = &sheet.frames[0].hitboxes[1];
+    for expected in &[
+        Problem::MissingImage(frame_id, "missing.png".into()),
+        Problem::DuplicateFrame(duplicate_frame_id, "missing.png".into()),
+        Problem::DuplicateHitboxName(
+            frame_id,
+            hitbox.get_id(),
+            "missing.png".into(),
+            hitbox.get_name().to_owned(),
+        ),
+        Problem::EmptyAnimation(idle_id, "idle".to_owned()),
+        Problem::DuplicateAnimationName(walk_id, "idle".to_owned()),
+        Problem::FrameNotInSheet(walk_id, "idle".to_owned(), 1, unknown_frame_id),
+        Problem::ZeroDurationKeyframe(walk_id, "idle".to_owned(), 0),
+    ] {
+        assert!(problems.contains(expected), "{}", expected);
+    }
+    assert_eq!(problems.len(), 7);
+}
+
+#[test]
+fn test_read_header_size() {
+    let mut png = vec![137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13];
+    png.extend_from_slice(b"IHDR");
+    png.extend_from_slice(&[0, 0, 1, 0, 0, 0, 0, 48]);
+    assert_eq!(read_header_size(&png), Some(vec2(256, 48)));
+
+    let gif = b"GIF89a\x20\x00\x10\x00";
+    assert_eq!(read_header_size(gif), Some(vec2(32, 16)));
+
+    let mut bmp = b"BM".to_vec();
+    bmp.extend_from_slice(&[0; 12]);
+    bmp.extend_from_slice(&40i32.to_le_bytes());
+    bmp.extend_from_slice(&64i32.to_le_bytes());
+    bmp.extend_from_slice(&(-24i32).to_le_bytes());
+    assert_eq!(read_header_size(&bmp), Some(vec2(64, 24)));
+
+    assert_eq!(read_header_size(b"not an image"), None);
+}
@@ -1,16 +1,26 @@
+use clipboard::{ClipboardContext, ClipboardProvider};
+use euclid::*;
 use failure::Error;
+use image::GenericImageView;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
 
 use crate::export::*;
+use crate::import::*;
+use crate::project::Project;
+use crate::recovery::{self, RecoveredSheet};
 use crate::sheet::*;
 use crate::state::*;
+use crate::streamer::TextureCache;
 
 const SHEET_FILE_EXTENSION: &str = "tiger";
+const PROJECT_FILE_EXTENSION: &str = "tigerproj";
+const ASEPRITE_FILE_EXTENSION: &str = "json";
 const TEMPLATE_FILE_EXTENSION: &str = "liquid";
-const IMAGE_IMPORT_FILE_EXTENSIONS: &str = "png;tga;bmp";
+const IMAGE_IMPORT_FILE_EXTENSIONS: &str = "png;tga;bmp;gif";
 const IMAGE_EXPORT_FILE_EXTENSIONS: &str = "png";
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -24,8 +34,12 @@ pub enum ExitState {
 pub struct AppState {
     documents: Vec<Document>,
     current_document: Option<PathBuf>,
+    project: Option<Project>,
+    recovered_sheets: Vec<RecoveredSheet>,
     clock: Duration,
     exit_state: Option<ExitState>,
+    history_limit: Option<usize>,
+    errors: Vec<String>,
 }
 
 impl AppState {
@@ -45,6 +59,18 @@ impl AppState {
         self.clock
     }
 
+    pub fn get_history_limit(&self) -> usize {
+        self.history_limit.unwrap_or(DEFAULT_HISTORY_LIMIT)
+    }
+
+    // Applies to open documents and documents opened later
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history_limit = Some(limit);
+        for document in &mut self.documents {
+            document.set_history_limit(limit);
+        }
+    }
+
     pub fn get_exit_state(&self) -> Option<ExitState> {
         self.exit_state
     }
@@ -83,9 +109,23 @@ impl AppState {
         self.documents.iter()
     }
 
+    pub fn get_project(&self) -> Option<&Project> {
+        self.project.as_ref()
+    }
+
+    fn get_project_mut(&mut self) -> Result<&mut Project, Error> {
+        Ok(self.project.as_mut().ok_or(StateError::NoProjectOpen)?)
+    }
+
     fn end_new_document<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
+        let history_limit = self.history_limit;
         match self.get_document_mut(&path) {
-            Some(d) => *d = Document::new(path.as_ref()),
+            Some(d) => {
+                *d = Document::new(path.as_ref());
+                if let Some(limit) = history_limit {
+                    d.set_history_limit(limit);
+                }
+            }
             None => {
                 let document = Document::new(path.as_ref());
                 self.add_document(document);
@@ -123,6 +163,13 @@ impl AppState {
 
         self.documents.retain(|d| d.source != to.as_ref());
 
+        if let Some(project) = &mut self.project {
+            if project.has_sheet(&from) {
+                project.relocate_sheet(&from, &to);
+                project.save()?;
+            }
+        }
+
         for document in &mut self.documents {
             if document.source == from.as_ref() {
                 document.source = to.as_ref().to_owned();
@@ -144,8 +191,11 @@ impl AppState {
         Ok(())
     }
 
-    fn add_document(&mut self, added_document: Document) {
+    fn add_document(&mut self, mut added_document: Document) {
         assert!(!self.is_opened(&added_document.source));
+        if let Some(limit) = self.history_limit {
+            added_document.set_history_limit(limit);
+        }
         self.documents.push(added_document);
     }
 
@@ -176,6 +226,101 @@ impl AppState {
         self.current_document = None;
     }
 
+    fn end_new_project<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
+        let mut project = Project::new(path);
+        for document in &self.documents {
+            project.add_sheet(&document.source)?;
+        }
+        project.save()?;
+        self.project = Some(project);
+        Ok(())
+    }
+
+    fn end_open_project<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
+        let project = Project::open(path)?;
+
+        // Sheets which fail to open do not prevent opening the others
+        let mut result = Ok(());
+        for sheet in project.sheets_iter() {
+            if let Err(e) = self.end_open_document(sheet) {
+                result = Err(e);
+            }
+        }
+        if let Some(sheet) = project.sheets_iter().find(|s| self.is_opened(s)) {
+            self.focus_document(sheet)?;
+        }
+
+        self.project = Some(project);
+        result
+    }
+
+    fn close_project(&mut self) {
+        self.project = None;
+    }
+
+    // Projects are not part of undo history, changes are saved right away
+    fn add_sheet_to_project<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
+        let project = self.get_project_mut()?;
+        project.add_sheet(path)?;
+        project.save()
+    }
+
+    fn remove_sheet_from_project<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
+        let project = self.get_project_mut()?;
+        project.remove_sheet(path)?;
+        project.save()
+    }
+
+    fn set_project_export_settings<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
+        let export_settings = self
+            .get_document(&path)
+            .ok_or(StateError::DocumentNotFound)?
+            .sheet
+            .get_export_settings()
+            .clone()
+            .ok_or(StateError::NoExistingExportSettings)?;
+        let project = self.get_project_mut()?;
+        project.set_export_settings(export_settings);
+        project.save()
+    }
+
+    pub fn recovered_sheets_iter(&self) -> std::slice::Iter<'_, RecoveredSheet> {
+        self.recovered_sheets.iter()
+    }
+
+    fn offer_recovered_sheets(&mut self, recovered_sheets: &[RecoveredSheet]) {
+        self.recovered_sheets = recovered_sheets.to_vec();
+    }
+
+    // Recovered changes are applied on top of the sheet on disk, so that undoing them is possible
+    fn restore_recovered_sheet<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
+        let index = self
+            .recovered_sheets
+            .iter()
+            .position(|r| r.source == path.as_ref())
+            .ok_or(StateError::RecoveredSheetNotFound)?;
+        if self.get_document(&path).is_none() {
+            let document = if path.as_ref().exists() {
+                Document::open(&path)?
+            } else {
+                Document::new(&path)
+            };
+            self.add_document(document);
+        }
+        let recovered_sheet = self.recovered_sheets.remove(index);
+        self.get_document_mut(&path)
+            .ok_or(StateError::DocumentNotFound)?
+            .process_command(&DocumentCommand::RestoreSheet(
+                path.as_ref().to_owned(),
+                recovered_sheet.sheet,
+            ))?;
+        self.focus_document(path)
+    }
+
+    fn discard_recovered_sheet<T: AsRef<Path>>(&mut self, path: T) {
+        self.recovered_sheets.retain(|r| r.source != path.as_ref());
+    }
+
     fn exit(&mut self) {
         if self.exit_state.is_none() {
             self.exit_state = Some(ExitState::Requested);
@@ -196,6 +341,21 @@ impl AppState {
         self.exit_state = None;
     }
 
+    // Errors are shown one at a time, oldest first
+    pub fn get_error(&self) -> Option<&String> {
+        self.errors.first()
+    }
+
+    fn show_error(&mut self, message: &str) {
+        self.errors.push(message.to_owned());
+    }
+
+    fn dismiss_error(&mut self) {
+        if !self.errors.is_empty() {
+            self.errors.remove(0);
+        }
+    }
+
     fn process_app_command(&mut self, command: &AppCommand) -> Result<(), Error> {
         use AppCommand::*;
 
@@ -206,6 +366,15 @@ impl AppState {
             FocusDocument(p) => self.focus_document(p)?,
             CloseCurrentDocument => self.close_current_document()?,
             CloseAllDocuments => self.close_all_documents(),
+            EndNewProject(p) => self.end_new_project(p)?,
+            EndOpenProject(p) => self.end_open_project(p)?,
+            CloseProject => self.close_project(),
+            AddSheetToProject(p) => self.add_sheet_to_project(p)?,
+            RemoveSheetFromProject(p) => self.remove_sheet_from_project(p)?,
+            SetProjectExportSettings(p) => self.set_project_export_settings(p)?,
+            OfferRecoveredSheets(r) => self.offer_recovered_sheets(r),
+            RestoreRecoveredSheet(p) => self.restore_recovered_sheet(p)?,
+            DiscardRecoveredSheet(p) => self.discard_recovered_sheet(p),
             Undo => self
                 .get_current_document_mut()
                 .ok_or(StateError::NoDocumentOpen)?
@@ -214,10 +383,17 @@ impl AppState {
                 .get_current_document_mut()
                 .ok_or(StateError::NoDocumentOpen)?
                 .redo()?,
+            JumpToHistory(i) => self
+                .get_current_document_mut()
+                .ok_or(StateError::NoDocumentOpen)?
+                .jump_to_history(*i)?,
+            SetHistoryLimit(l) => self.set_history_limit(*l),
             Exit => self.exit(),
             ExitAfterSaving => self.exit_after_saving(),
             ExitWithoutSaving => self.exit_without_saving(),
             CancelExit => self.cancel_exit(),
+            ShowError(m) => self.show_error(m),
+            DismissError => self.dismiss_error(),
         }
 
         Ok(())
@@ -227,11 +403,28 @@ impl AppState {
         use DocumentCommand::*;
         let document = match command {
             EndImport(p, _)
-            | MarkAsSaved(p, _)
+            | EndImportBatch(p, _)
+            | EndRelocateFrame(p, _, _)
+            | EndRelocateDirectory(p, _, _)
+            | BeginRescaleHitboxes(p, _)
+            | EndReplaceFrameImage(p, _, _)
+            | EndSetSplitSource(p, _)
+            | EndSetSplitDestination(p, _)
+            | EndSetImportFolder(p, _)
+            | EndValidate(p, _)
+            | MarkAsSaved(p, _, _)
+            | OfferReload(p, _, _)
+            | ReloadFromDisk(p)
+            | KeepLocalChanges(p)
+            | MarkAsAutosaved(p, _)
+            | RestoreSheet(p, _)
             | EndSetExportTextureDestination(p, _)
             | EndSetExportMetadataDestination(p, _)
             | EndSetExportMetadataPathsRoot(p, _)
-            | EndSetExportFormat(p, _) => {
+            | EndSetExportFormat(p, _)
+            | EndCut(p, _)
+            | EndPaste(p, _)
+            | EndDuplicateFrames(p, _) => {
                 self.get_document_mut(p).ok_or(StateError::DocumentNotFound)
             }
             _ => self
@@ -279,10 +472,60 @@ fn begin_open_document() -> Result<CommandBuffer, Error> {
     Ok(buffer)
 }
 
+fn begin_new_project() -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    if let nfd::Response::Okay(path_string) =
+        nfd::open_save_dialog(Some(PROJECT_FILE_EXTENSION), None)?
+    {
+        let mut path = std::path::PathBuf::from(path_string);
+        path.set_extension(PROJECT_FILE_EXTENSION);
+        buffer.end_new_project(path);
+    };
+    Ok(buffer)
+}
+
+fn begin_open_project() -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    if let nfd::Response::Okay(path_string) =
+        nfd::open_file_dialog(Some(PROJECT_FILE_EXTENSION), None)?
+    {
+        buffer.end_open_project(std::path::PathBuf::from(path_string));
+    };
+    Ok(buffer)
+}
+
+fn begin_import_aseprite() -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    let aseprite_path = match nfd::open_file_dialog(Some(ASEPRITE_FILE_EXTENSION), None)? {
+        nfd::Response::Okay(path_string) => std::path::PathBuf::from(path_string),
+        _ => return Ok(buffer),
+    };
+    if let nfd::Response::Okay(path_string) =
+        nfd::open_save_dialog(Some(SHEET_FILE_EXTENSION), None)?
+    {
+        let mut sheet_path = std::path::PathBuf::from(path_string);
+        sheet_path.set_extension(SHEET_FILE_EXTENSION);
+        let sheet_name = sheet_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let frames_directory = sheet_path.with_file_name(format!("{}_frames", sheet_name));
+
+        let batch = import_aseprite(&aseprite_path, &frames_directory)?;
+        let mut sheet = Sheet::default();
+        batch.apply(&mut sheet)?;
+        Document::save(&sheet, &sheet_path)?;
+        buffer.end_open_document(sheet_path);
+    };
+    Ok(buffer)
+}
+
 fn save<T: AsRef<Path>>(sheet: &Sheet, source: T, version: i32) -> Result<CommandBuffer, Error> {
     let mut buffer = CommandBuffer::new();
     Document::save(sheet, source.as_ref())?;
-    buffer.mark_as_saved(source, version);
+    recovery::discard(source.as_ref())?;
+    let modified = std::fs::metadata(source.as_ref())?.modified()?;
+    buffer.mark_as_saved(source, version, modified);
     Ok(buffer)
 }
 
@@ -293,6 +536,7 @@ fn save_as<T: AsRef<Path>>(sheet: &Sheet, source: T, version: i32) -> Result<Com
     {
         let mut new_path = std::path::PathBuf::from(path_string);
         new_path.set_extension(SHEET_FILE_EXTENSION);
+        recovery::discard(source.as_ref())?;
         buffer.relocate_document(source, &new_path);
         buffer.save(&new_path, sheet, version);
     };
@@ -301,22 +545,135 @@ fn save_as<T: AsRef<Path>>(sheet: &Sheet, source: T, version: i32) -> Result<Com
 
 fn begin_import<T: AsRef<Path>>(into: T) -> Result<CommandBuffer, Error> {
     let mut buffer = CommandBuffer::new();
-    match nfd::open_file_multiple_dialog(Some(IMAGE_IMPORT_FILE_EXTENSIONS), None)? {
-        nfd::Response::Okay(path_string) => {
-            let path = std::path::PathBuf::from(path_string);
-            buffer.end_import(into, path);
+    let path_strings =
+        match nfd::open_file_multiple_dialog(Some(IMAGE_IMPORT_FILE_EXTENSIONS), None)? {
+            nfd::Response::Okay(path_string) => vec![path_string],
+            nfd::Response::OkayMultiple(path_strings) => path_strings,
+            _ => vec![],
+        };
+    for path_string in &path_strings {
+        let path = std::path::PathBuf::from(path_string);
+        match import_animated_image(&path)? {
+            Some(batch) => buffer.end_import_batch(&into, batch),
+            None => buffer.end_import(&into, path),
         }
-        nfd::Response::OkayMultiple(path_strings) => {
-            for path_string in &path_strings {
-                let path = std::path::PathBuf::from(path_string);
-                buffer.end_import(&into, path);
+    }
+    Ok(buffer)
+}
+
+fn begin_relocate_frame<T: AsRef<Path>>(
+    document_path: T,
+    frame: FrameId,
+) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    if let nfd::Response::Okay(path_string) =
+        nfd::open_file_dialog(Some(IMAGE_IMPORT_FILE_EXTENSIONS), None)?
+    {
+        buffer.end_relocate_frame(document_path, frame, std::path::PathBuf::from(path_string));
+    };
+    Ok(buffer)
+}
+
+fn begin_relocate_directory<T: AsRef<Path>, U: AsRef<Path>>(
+    document_path: T,
+    directory: U,
+) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    if let nfd::Response::Okay(path_string) = nfd::open_pick_folder(None)? {
+        buffer.end_relocate_directory(
+            document_path,
+            directory,
+            std::path::PathBuf::from(path_string),
+        );
+    };
+    Ok(buffer)
+}
+
+fn begin_replace_frame_image<T: AsRef<Path>, U: AsRef<Path>>(
+    document_path: T,
+    frame: FrameId,
+    old_source: U,
+) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    if let nfd::Response::Okay(path_string) =
+        nfd::open_file_dialog(Some(IMAGE_IMPORT_FILE_EXTENSIONS), None)?
+    {
+        let image_size = |path: &Path| -> Option<Vector2D<u32>> {
+            image::open(path)
+                .ok()
+                .map(|i| i.to_rgba().dimensions().into())
+        };
+        let new_source = std::path::PathBuf::from(path_string);
+        let mut replacement = ImageReplacement {
+            frame,
+            new_source: new_source.clone(),
+            old_size: vec2(0, 0),
+            new_size: vec2(0, 0),
+        };
+        match (image_size(old_source.as_ref()), image_size(&new_source)) {
+            (Some(old_size), Some(new_size)) if old_size != new_size => {
+                replacement.old_size = old_size;
+                replacement.new_size = new_size;
+                buffer.begin_rescale_hitboxes(document_path, replacement);
             }
+            _ => buffer.end_replace_frame_image(document_path, replacement, false),
         }
-        _ => (),
     };
     Ok(buffer)
 }
 
+fn begin_set_split_source<T: AsRef<Path>>(document_path: T) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    if let nfd::Response::Okay(path_string) =
+        nfd::open_file_dialog(Some(IMAGE_IMPORT_FILE_EXTENSIONS), None)?
+    {
+        buffer.end_set_split_source(document_path, std::path::PathBuf::from(path_string));
+    };
+    Ok(buffer)
+}
+
+fn begin_set_split_destination<T: AsRef<Path>>(document_path: T) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    if let nfd::Response::Okay(path_string) = nfd::open_pick_folder(None)? {
+        buffer.end_set_split_destination(document_path, std::path::PathBuf::from(path_string));
+    };
+    Ok(buffer)
+}
+
+fn split<T: AsRef<Path>>(
+    document_path: T,
+    settings: &SplitSettings,
+) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    let batch = split_sheet(settings)?;
+    buffer.end_import_batch(document_path, batch);
+    Ok(buffer)
+}
+
+fn begin_set_import_folder<T: AsRef<Path>>(document_path: T) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    if let nfd::Response::Okay(path_string) = nfd::open_pick_folder(None)? {
+        buffer.end_set_import_folder(document_path, std::path::PathBuf::from(path_string));
+    };
+    Ok(buffer)
+}
+
+fn import_folder_into<T: AsRef<Path>>(
+    document_path: T,
+    settings: &FolderImportSettings,
+) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    let batch = import_folder(settings)?;
+    buffer.end_import_batch(document_path, batch);
+    Ok(buffer)
+}
+
+fn validate<T: AsRef<Path>>(document_path: T, sheet: &Sheet) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    buffer.end_validate(document_path, sheet.validate());
+    Ok(buffer)
+}
+
 fn begin_set_export_texture_destination<T: AsRef<Path>>(
     document_path: T,
 ) -> Result<CommandBuffer, Error> {
@@ -363,33 +720,237 @@ fn begin_set_export_format<T: AsRef<Path>>(document_path: T) -> Result<CommandBu
     Ok(buffer)
 }
 
-fn export(sheet: &Sheet) -> Result<(), Error> {
-    let export_settings = sheet
+fn get_export_settings(sheet: &Sheet) -> Result<&ExportSettings, Error> {
+    Ok(sheet
         .get_export_settings()
         .as_ref()
-        .ok_or(StateError::NoExistingExportSettings)?;
+        .ok_or(StateError::NoExistingExportSettings)?)
+}
 
-    // TODO texture export performance is awful
-    let packed_sheet = pack_sheet(&sheet)?;
-    let exported_data = export_sheet(&sheet, &export_settings, &packed_sheet.get_layout())?;
+// All sheets must have the same texture destination, they are packed into a single atlas
+fn export_sheets(sheets: &[Sheet], texture_cache: &Mutex<TextureCache>) -> Result<(), Error> {
+    let texture_destination = match sheets.first() {
+        Some(sheet) => get_export_settings(sheet)?.texture_destination.clone(),
+        None => return Ok(()),
+    };
 
-    {
-        let mut file = File::create(&export_settings.metadata_destination)?;
-        file.write_all(&exported_data.into_bytes())?;
+    let images_hash = hash_images(sheets)?;
+    let mut metadata_hashes = Vec::new();
+    for sheet in sheets {
+        let export_settings = get_export_settings(sheet)?;
+        metadata_hashes.push(hash_metadata_inputs(sheet, export_settings, images_hash)?);
     }
-    {
-        let mut file = File::create(&export_settings.texture_destination)?;
-        packed_sheet.get_texture().write_to(&mut file, image::PNG)?;
+    let is_sheet_up_to_date = |sheet_index: usize| {
+        get_export_settings(&sheets[sheet_index])
+            .map(|e| is_metadata_up_to_date(&e.metadata_destination, metadata_hashes[sheet_index]))
+            .unwrap_or(false)
+    };
+    let cached_layout = read_texture_layout(&texture_destination, images_hash);
+    if cached_layout.is_some() && (0..sheets.len()).all(is_sheet_up_to_date) {
+        return Ok(());
+    }
+
+    let (texture_layout, atlas_size) = match cached_layout {
+        Some(layout) => (layout, read_image_size(&texture_destination)?),
+        None => {
+            let mut decoded_frames = DecodedFrames::new();
+            {
+                let texture_cache = texture_cache.lock().unwrap();
+                for frame in sheets.iter().flat_map(|s| s.frames_iter()) {
+                    if let Some(pixels) = texture_cache.get_pixels(frame.get_source()) {
+                        decoded_frames.insert(frame.get_source().to_owned(), pixels);
+                    }
+                }
+            }
+            let packed_sheet = pack_sheets(sheets, &decoded_frames)?;
+            {
+                let mut file = File::create(&texture_destination)?;
+                packed_sheet.get_texture().write_to(&mut file, image::PNG)?;
+            }
+            write_texture_layout(&texture_destination, images_hash, packed_sheet.get_layout())?;
+            let atlas_size = packed_sheet.get_texture().dimensions().into();
+            (packed_sheet.get_layout().clone(), atlas_size)
+        }
+    };
+
+    for (sheet_index, sheet) in sheets.iter().enumerate() {
+        if !is_sheet_up_to_date(sheet_index) {
+            let export_settings = get_export_settings(sheet)?;
+            let exported_data = export_sheet(
+                sheet,
+                sheet_index,
+                export_settings,
+                &texture_layout,
+                atlas_size,
+            )?;
+            let mut file = File::create(&export_settings.metadata_destination)?;
+            file.write_all(&exported_data.into_bytes())?;
+            write_metadata_hash(
+                &export_settings.metadata_destination,
+                metadata_hashes[sheet_index],
+            )?;
+        }
     }
 
     Ok(())
 }
 
-pub fn process_async_command(command: &AsyncCommand) -> Result<CommandBuffer, Error> {
+// Sheets which are not open are exported as they are on disk. Sheets exporting
+// to the same texture share an atlas. Sheets without export settings are skipped,
+// and so are sheets not exporting to the given texture destination if there is one.
+fn export_project(
+    project: &Project,
+    open_sheets: &[(PathBuf, Sheet)],
+    only_texture_destination: Option<&PathBuf>,
+    texture_cache: &Mutex<TextureCache>,
+) -> Result<(), Error> {
+    let mut atlases: Vec<(PathBuf, Vec<Sheet>)> = Vec::new();
+    for sheet_path in project.sheets_iter() {
+        let sheet = match open_sheets.iter().find(|(p, _)| p == sheet_path) {
+            Some((_, sheet)) => sheet.clone(),
+            None => Document::open(sheet_path)?.sheet,
+        };
+        let sheet = project.with_default_export_settings(sheet_path, &sheet);
+        let texture_destination = match sheet.get_export_settings() {
+            Some(e) => e.texture_destination.clone(),
+            None => continue,
+        };
+        match only_texture_destination {
+            Some(t) if *t != texture_destination => continue,
+            _ => (),
+        }
+        match atlases.iter_mut().find(|(t, _)| *t == texture_destination) {
+            Some((_, sheets)) => sheets.push(sheet),
+            None => atlases.push((texture_destination, vec![sheet])),
+        }
+    }
+    for (_, sheets) in &atlases {
+        export_sheets(sheets, texture_cache)?;
+    }
+    Ok(())
+}
+
+fn copy(content: &ClipboardContent) -> Result<(), Error> {
+    let mut clipboard: ClipboardContext =
+        ClipboardProvider::new().map_err(|_| StateError::ClipboardUnavailable)?;
+    clipboard
+        .set_contents(content.to_text()?)
+        .map_err(|_| StateError::ClipboardUnavailable)?;
+    Ok(())
+}
+
+fn cut<T: AsRef<Path>>(
+    document_path: T,
+    content: &ClipboardContent,
+    items: &[SelectedItem],
+) -> Result<CommandBuffer, Error> {
+    copy(content)?;
+    let mut buffer = CommandBuffer::new();
+    buffer.end_cut(document_path, items.to_vec());
+    Ok(buffer)
+}
+
+fn begin_paste<T: AsRef<Path>>(document_path: T) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    let mut clipboard: ClipboardContext =
+        ClipboardProvider::new().map_err(|_| StateError::ClipboardUnavailable)?;
+    let text = clipboard
+        .get_contents()
+        .map_err(|_| StateError::ClipboardUnavailable)?;
+    buffer.end_paste(document_path, ClipboardContent::from_text(text)?);
+    Ok(buffer)
+}
+
+// Picks an unused file name next to the original, like `walk_0 copy.png` or `walk_0 copy 2.png`
+fn copy_frame_image<T: AsRef<Path>>(source: T) -> Result<PathBuf, Error> {
+    let source = source.as_ref();
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = source.extension().map(|e| e.to_string_lossy().into_owned());
+    let mut index = 1;
+    let destination = loop {
+        let mut file_name = if index == 1 {
+            format!("{} copy", stem)
+        } else {
+            format!("{} copy {}", stem, index)
+        };
+        if let Some(extension) = &extension {
+            file_name = format!("{}.{}", file_name, extension);
+        }
+        let destination = source.with_file_name(file_name);
+        if !destination.exists() {
+            break destination;
+        }
+        index += 1;
+    };
+    std::fs::copy(source, &destination)?;
+    Ok(destination)
+}
+
+fn begin_duplicate_frames<T: AsRef<Path>>(
+    document_path: T,
+    frames: &[(FrameId, PathBuf)],
+) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    let duplicates = frames
+        .iter()
+        .map(|(frame, source)| Ok((*frame, copy_frame_image(source)?)))
+        .collect::<Result<_, Error>>()?;
+    buffer.end_duplicate_frames(document_path, duplicates);
+    Ok(buffer)
+}
+
+fn find_recovered_sheets() -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    let recovered_sheets = recovery::find()?;
+    if !recovered_sheets.is_empty() {
+        buffer.offer_recovered_sheets(recovered_sheets);
+    }
+    Ok(buffer)
+}
+
+fn autosave<T: AsRef<Path>>(
+    sheet: &Sheet,
+    source: T,
+    version: i32,
+) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    recovery::write(source.as_ref(), sheet)?;
+    buffer.mark_as_autosaved(source, version);
+    Ok(buffer)
+}
+
+// Files which cannot be read yet, like ones still being written, are checked again later
+fn check_for_external_changes<T: AsRef<Path>>(
+    source: T,
+    disk_modified: SystemTime,
+) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    let modified = match std::fs::metadata(source.as_ref()).and_then(|m| m.modified()) {
+        Ok(m) => m,
+        Err(_) => return Ok(buffer),
+    };
+    if modified != disk_modified {
+        if let Ok(document) = Document::open(source.as_ref()) {
+            buffer.offer_reload(source, document.sheet, modified);
+        }
+    }
+    Ok(buffer)
+}
+
+pub fn process_async_command(
+    command: &AsyncCommand,
+    texture_cache: &Mutex<TextureCache>,
+) -> Result<CommandBuffer, Error> {
     let no_commands = CommandBuffer::new();
     match command {
         AsyncCommand::BeginNewDocument => begin_new_document(),
         AsyncCommand::BeginOpenDocument => begin_open_document(),
+        AsyncCommand::BeginImportAseprite => begin_import_aseprite(),
+        AsyncCommand::BeginNewProject => begin_new_project(),
+        AsyncCommand::BeginOpenProject => begin_open_project(),
         AsyncCommand::Save(p, sheet, version) => save(sheet, p, *version),
         AsyncCommand::SaveAs(p, sheet, version) => save_as(sheet, p, *version),
         AsyncCommand::BeginSetExportTextureDestination(p) => {
@@ -401,6 +962,32 @@ pub fn process_async_command(command: &AsyncCommand) -> Result<CommandBuffer, Er
         AsyncCommand::BeginSetExportMetadataPathsRoot(p) => begin_set_export_metadata_paths_root(p),
         AsyncCommand::BeginSetExportFormat(p) => begin_set_export_format(p),
         AsyncCommand::BeginImport(p) => begin_import(p),
-        AsyncCommand::Export(sheet) => export(sheet).and(Ok(no_commands)),
+        AsyncCommand::BeginRelocateFrame(p, f) => begin_relocate_frame(p, *f),
+        AsyncCommand::BeginRelocateDirectory(p, d) => begin_relocate_directory(p, d),
+        AsyncCommand::BeginReplaceFrameImage(p, f, s) => begin_replace_frame_image(p, *f, s),
+        AsyncCommand::BeginSetSplitSource(p) => begin_set_split_source(p),
+        AsyncCommand::BeginSetSplitDestination(p) => begin_set_split_destination(p),
+        AsyncCommand::SplitSheet(p, s) => split(p, s),
+        AsyncCommand::BeginSetImportFolder(p) => begin_set_import_folder(p),
+        AsyncCommand::ImportFolder(p, s) => import_folder_into(p, s),
+        AsyncCommand::Validate(p, s) => validate(p, s),
+        AsyncCommand::Export(sheet) => {
+            export_sheets(std::slice::from_ref(sheet), texture_cache).and(Ok(no_commands))
+        }
+        AsyncCommand::ExportProject(project, open_sheets, texture_destination) => export_project(
+            project,
+            open_sheets,
+            texture_destination.as_ref(),
+            texture_cache,
+        )
+        .and(Ok(no_commands)),
+        AsyncCommand::Copy(content) => copy(content).and(Ok(no_commands)),
+        AsyncCommand::Cut(p, content, items) => cut(p, content, items),
+        AsyncCommand::BeginPaste(p) => begin_paste(p),
+        AsyncCommand::BeginDuplicateFrames(p, f) => begin_duplicate_frames(p, f),
+        AsyncCommand::FindRecoveredSheets => find_recovered_sheets(),
+        AsyncCommand::Autosave(p, sheet, version) => autosave(sheet, p, *version),
+        AsyncCommand::DiscardRecovery(p) => recovery::discard(p).and(Ok(no_commands)),
+        AsyncCommand::CheckForExternalChanges(p, m) => check_for_external_changes(p, *m),
     }
 }
@@ -1,8 +1,11 @@
 use euclid::*;
 use std::fmt;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
+use crate::import::{FolderImportSettings, ImportBatch, SplitSettings};
+use crate::project::Project;
+use crate::recovery::RecoveredSheet;
 use crate::sheet::*;
 use crate::state::*;
 
@@ -10,6 +13,9 @@ use crate::state::*;
 pub enum AsyncCommand {
     BeginNewDocument,
     BeginOpenDocument,
+    BeginImportAseprite,
+    BeginNewProject,
+    BeginOpenProject,
     Save(PathBuf, Sheet, i32),
     SaveAs(PathBuf, Sheet, i32),
     BeginSetExportTextureDestination(PathBuf),
@@ -17,7 +23,25 @@ pub enum AsyncCommand {
     BeginSetExportMetadataPathsRoot(PathBuf),
     BeginSetExportFormat(PathBuf),
     BeginImport(PathBuf),
+    BeginRelocateFrame(PathBuf, FrameId),
+    BeginRelocateDirectory(PathBuf, PathBuf),
+    BeginReplaceFrameImage(PathBuf, FrameId, PathBuf),
+    BeginSetSplitSource(PathBuf),
+    BeginSetSplitDestination(PathBuf),
+    SplitSheet(PathBuf, SplitSettings),
+    BeginSetImportFolder(PathBuf),
+    ImportFolder(PathBuf, FolderImportSettings),
+    Validate(PathBuf, Sheet),
     Export(Sheet),
+    ExportProject(Project, Vec<(PathBuf, Sheet)>, Option<PathBuf>),
+    Copy(ClipboardContent),
+    Cut(PathBuf, ClipboardContent, Vec<SelectedItem>),
+    BeginPaste(PathBuf),
+    BeginDuplicateFrames(PathBuf, Vec<(FrameId, PathBuf)>),
+    FindRecoveredSheets,
+    Autosave(PathBuf, Sheet, i32),
+    DiscardRecovery(PathBuf),
+    CheckForExternalChanges(PathBuf, SystemTime),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -28,12 +52,25 @@ pub enum AppCommand {
     CloseAllDocuments,
     FocusDocument(PathBuf),
     RelocateDocument(PathBuf, PathBuf),
+    EndNewProject(PathBuf),
+    EndOpenProject(PathBuf),
+    CloseProject,
+    AddSheetToProject(PathBuf),
+    RemoveSheetFromProject(PathBuf),
+    SetProjectExportSettings(PathBuf),
+    OfferRecoveredSheets(Vec<RecoveredSheet>),
+    RestoreRecoveredSheet(PathBuf),
+    DiscardRecoveredSheet(PathBuf),
     Undo,
     Redo,
+    JumpToHistory(usize),
+    SetHistoryLimit(usize),
     Exit,
     ExitAfterSaving,
     ExitWithoutSaving,
     CancelExit,
+    ShowError(String),
+    DismissError,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -45,22 +82,49 @@ pub enum DocumentCommand {
     EndSetExportFormat(PathBuf, ExportFormat),
     CancelExportAs,
     EndExportAs,
-    MarkAsSaved(PathBuf, i32),
+    MarkAsSaved(PathBuf, i32, SystemTime),
+    MarkAsAutosaved(PathBuf, i32),
+    RestoreSheet(PathBuf, Sheet),
+    OfferReload(PathBuf, Sheet, SystemTime),
+    ReloadFromDisk(PathBuf),
+    KeepLocalChanges(PathBuf),
     EndImport(PathBuf, PathBuf),
+    EndImportBatch(PathBuf, ImportBatch),
+    EndRelocateFrame(PathBuf, FrameId, PathBuf),
+    EndRelocateDirectory(PathBuf, PathBuf, PathBuf),
+    BeginRescaleHitboxes(PathBuf, ImageReplacement),
+    CancelReplaceFrameImage,
+    EndReplaceFrameImage(PathBuf, ImageReplacement, bool),
+    BeginSplitSheet,
+    EndSetSplitSource(PathBuf, PathBuf),
+    EndSetSplitDestination(PathBuf, PathBuf),
+    UpdateSplitSettings(SplitSettings),
+    CancelSplitSheet,
+    EndSplitSheet,
+    BeginImportFolder,
+    EndSetImportFolder(PathBuf, PathBuf),
+    UpdateFolderImportSettings(FolderImportSettings),
+    CancelImportFolder,
+    EndImportFolder,
+    EndValidate(PathBuf, Vec<Problem>),
+    CloseProblems,
     SwitchToContentTab(ContentTab),
     ClearSelection,
-    SelectFrame(PathBuf),
-    SelectAnimation(String),
-    SelectHitbox(String),
+    SelectFrame(FrameId),
+    SelectAnimation(AnimationId),
+    SelectHitbox(HitboxId),
     SelectAnimationFrame(usize),
+    ToggleSelection(SelectedItem),
+    SelectRange(SelectedItem),
+    SelectHitboxesInRectangle(Vector2D<f32>, Vector2D<f32>),
     SelectPrevious,
     SelectNext,
-    EditFrame(PathBuf),
-    EditAnimation(String),
+    EditFrame(FrameId),
+    EditAnimation(AnimationId),
     CreateAnimation,
-    BeginFrameDrag(PathBuf),
+    BeginFrameDrag(FrameId),
     EndFrameDrag,
-    InsertAnimationFrameBefore(PathBuf, usize),
+    InsertAnimationFrameBefore(FrameId, usize),
     ReorderAnimationFrame(usize, usize),
     BeginAnimationFrameDurationDrag(usize),
     UpdateAnimationFrameDurationDrag(u32),
@@ -76,10 +140,10 @@ pub enum DocumentCommand {
     WorkbenchCenter,
     Pan(Vector2D<f32>),
     CreateHitbox(Vector2D<f32>),
-    BeginHitboxScale(String, ResizeAxis),
+    BeginHitboxScale(HitboxId, ResizeAxis),
     UpdateHitboxScale(Vector2D<f32>, bool),
     EndHitboxScale,
-    BeginHitboxDrag(String),
+    BeginHitboxDrag(HitboxId),
     UpdateHitboxDrag(Vector2D<f32>, bool),
     EndHitboxDrag,
     TogglePlayback,
@@ -97,6 +161,10 @@ pub enum DocumentCommand {
     BeginRenameSelection,
     UpdateRenameSelection(String),
     EndRenameSelection,
+    EndCut(PathBuf, Vec<SelectedItem>),
+    EndPaste(PathBuf, ClipboardContent),
+    DuplicateSelection,
+    EndDuplicateFrames(PathBuf, Vec<(FrameId, PathBuf)>),
 }
 
 impl fmt::Display for DocumentCommand {
@@ -104,6 +172,33 @@ impl fmt::Display for DocumentCommand {
         use DocumentCommand::*;
         match self {
             EndImport(_, _) => write!(f, "Import Image"),
+            EndImportBatch(_, _) => write!(f, "Import Frames"),
+            EndRelocateFrame(_, _, _) | EndRelocateDirectory(_, _, _) => {
+                write!(f, "Relocate Frames")
+            }
+            BeginRescaleHitboxes(_, _)
+            | CancelReplaceFrameImage
+            | EndReplaceFrameImage(_, _, _) => {
+                write!(f, "Replace Frame Image")
+            }
+
+            // Split
+            BeginSplitSheet
+            | EndSetSplitSource(_, _)
+            | EndSetSplitDestination(_, _)
+            | UpdateSplitSettings(_)
+            | CancelSplitSheet
+            | EndSplitSheet => write!(f, "Change Split Options"),
+
+            // Import folder
+            BeginImportFolder
+            | EndSetImportFolder(_, _)
+            | UpdateFolderImportSettings(_)
+            | CancelImportFolder
+            | EndImportFolder => write!(f, "Change Folder Import Options"),
+
+            // Problems
+            EndValidate(_, _) | CloseProblems => write!(f, "Check for Problems"),
 
             // Export
             BeginExportAs
@@ -121,6 +216,9 @@ impl fmt::Display for DocumentCommand {
             | SelectAnimation(_)
             | SelectHitbox(_)
             | SelectAnimationFrame(_)
+            | ToggleSelection(_)
+            | SelectRange(_)
+            | SelectHitboxesInRectangle(_, _)
             | SelectPrevious
             | SelectNext
             | EditFrame(_)
@@ -140,7 +238,12 @@ impl fmt::Display for DocumentCommand {
             | EndScrub
             | Pan(_) => write!(f, "Navigation"),
 
-            MarkAsSaved(_, _) => write!(f, "Mark As Saved"),
+            MarkAsSaved(_, _, _) => write!(f, "Mark As Saved"),
+            MarkAsAutosaved(_, _) => write!(f, "Autosave"),
+            RestoreSheet(_, _) => write!(f, "Recover Unsaved Changes"),
+            OfferReload(_, _, _) => write!(f, "Offer Reload"),
+            ReloadFromDisk(_) => write!(f, "Reload From Disk"),
+            KeepLocalChanges(_) => write!(f, "Keep Local Changes"),
 
             // Animation
             CreateAnimation => write!(f, "Create Animation"),
@@ -167,6 +270,9 @@ impl fmt::Display for DocumentCommand {
 
             NudgeSelection(_, _) => write!(f, "Nudge"),
             DeleteSelection => write!(f, "Delete"),
+            EndCut(_, _) => write!(f, "Cut"),
+            EndPaste(_, _) => write!(f, "Paste"),
+            DuplicateSelection | EndDuplicateFrames(_, _) => write!(f, "Duplicate"),
             BeginRenameSelection | UpdateRenameSelection(_) | EndRenameSelection => {
                 write!(f, "Rename")
             }
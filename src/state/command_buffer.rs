@@ -1,7 +1,11 @@
 use euclid::*;
-use std::path::Path;
-use std::time::Duration;
+use failure::Error;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
+use crate::import::{FolderImportSettings, ImportBatch, SplitSettings};
+use crate::project::Project;
+use crate::recovery::RecoveredSheet;
 use crate::sheet::*;
 use crate::state::*;
 
@@ -47,6 +51,10 @@ impl CommandBuffer {
             .push(Sync(App(EndOpenDocument(path.as_ref().to_owned()))));
     }
 
+    pub fn begin_import_aseprite(&mut self) {
+        self.queue.push(Async(BeginImportAseprite));
+    }
+
     pub fn relocate_document<T: AsRef<Path>, U: AsRef<Path>>(&mut self, from: T, to: U) {
         self.queue.push(Sync(App(RelocateDocument(
             from.as_ref().to_owned(),
@@ -59,14 +67,62 @@ impl CommandBuffer {
             .push(Sync(App(FocusDocument(document.source.to_owned()))));
     }
 
-    pub fn close_current_document(&mut self) {
+    // Closing a document without saving it also discards its unsaved changes
+    pub fn close_current_document(&mut self, app_state: &AppState) {
+        if let Some(document) = app_state.get_current_document() {
+            self.queue
+                .push(Async(DiscardRecovery(document.source.to_owned())));
+        }
         self.queue.push(Sync(App(CloseCurrentDocument)));
     }
 
-    pub fn close_all_documents(&mut self) {
+    pub fn close_all_documents(&mut self, app_state: &AppState) {
+        for document in app_state.documents_iter() {
+            self.queue
+                .push(Async(DiscardRecovery(document.source.to_owned())));
+        }
         self.queue.push(Sync(App(CloseAllDocuments)));
     }
 
+    pub fn begin_new_project(&mut self) {
+        self.queue.push(Async(BeginNewProject));
+    }
+
+    pub fn end_new_project<T: AsRef<Path>>(&mut self, path: T) {
+        self.queue
+            .push(Sync(App(EndNewProject(path.as_ref().to_owned()))));
+    }
+
+    pub fn begin_open_project(&mut self) {
+        self.queue.push(Async(BeginOpenProject));
+    }
+
+    pub fn end_open_project<T: AsRef<Path>>(&mut self, path: T) {
+        self.queue
+            .push(Sync(App(EndOpenProject(path.as_ref().to_owned()))));
+    }
+
+    pub fn close_project(&mut self) {
+        self.queue.push(Sync(App(CloseProject)));
+    }
+
+    pub fn add_sheet_to_project(&mut self, document: &crate::state::Document) {
+        self.queue
+            .push(Sync(App(AddSheetToProject(document.source.to_owned()))));
+    }
+
+    pub fn remove_sheet_from_project(&mut self, document: &crate::state::Document) {
+        self.queue.push(Sync(App(RemoveSheetFromProject(
+            document.source.to_owned(),
+        ))));
+    }
+
+    pub fn set_project_export_settings(&mut self, document: &crate::state::Document) {
+        self.queue.push(Sync(App(SetProjectExportSettings(
+            document.source.to_owned(),
+        ))));
+    }
+
     pub fn save<T: AsRef<Path>>(&mut self, path: T, sheet: &Sheet, version: i32) {
         self.queue.push(Async(Save(
             path.as_ref().to_owned(),
@@ -83,10 +139,83 @@ impl CommandBuffer {
         )));
     }
 
-    pub fn mark_as_saved<T: AsRef<Path>>(&mut self, path: T, version: i32) {
+    pub fn mark_as_saved<T: AsRef<Path>>(&mut self, path: T, version: i32, modified: SystemTime) {
         self.queue.push(Sync(Document(MarkAsSaved(
             path.as_ref().to_owned(),
             version,
+            modified,
+        ))));
+    }
+
+    pub fn autosave(&mut self, app_state: &AppState) {
+        for document in app_state.documents_iter() {
+            if document.needs_autosave() {
+                self.queue.push(Async(Autosave(
+                    document.source.to_owned(),
+                    document.sheet.clone(),
+                    document.get_version(),
+                )));
+            }
+        }
+    }
+
+    pub fn mark_as_autosaved<T: AsRef<Path>>(&mut self, path: T, version: i32) {
+        self.queue.push(Sync(Document(MarkAsAutosaved(
+            path.as_ref().to_owned(),
+            version,
+        ))));
+    }
+
+    // Documents already waiting on a decision about their file are not checked again
+    pub fn check_for_external_changes(&mut self, app_state: &AppState) {
+        for document in app_state.documents_iter() {
+            if document.persistent.external_change.is_some() {
+                continue;
+            }
+            if let Some(modified) = document.get_disk_modified() {
+                self.queue.push(Async(CheckForExternalChanges(
+                    document.source.to_owned(),
+                    modified,
+                )));
+            }
+        }
+    }
+
+    pub fn offer_reload<T: AsRef<Path>>(&mut self, path: T, sheet: Sheet, modified: SystemTime) {
+        self.queue.push(Sync(Document(OfferReload(
+            path.as_ref().to_owned(),
+            sheet,
+            modified,
+        ))));
+    }
+
+    pub fn reload_from_disk(&mut self, document: &crate::state::Document) {
+        self.focus_document(document);
+        self.queue
+            .push(Sync(Document(ReloadFromDisk(document.source.to_owned()))));
+    }
+
+    pub fn keep_local_changes(&mut self, document: &crate::state::Document) {
+        self.queue
+            .push(Sync(Document(KeepLocalChanges(document.source.to_owned()))));
+    }
+
+    pub fn offer_recovered_sheets(&mut self, recovered_sheets: Vec<RecoveredSheet>) {
+        self.queue
+            .push(Sync(App(OfferRecoveredSheets(recovered_sheets))));
+    }
+
+    pub fn restore_recovered_sheet(&mut self, recovered_sheet: &RecoveredSheet) {
+        self.queue.push(Sync(App(RestoreRecoveredSheet(
+            recovered_sheet.source.to_owned(),
+        ))));
+    }
+
+    pub fn discard_recovered_sheet(&mut self, recovered_sheet: &RecoveredSheet) {
+        self.queue
+            .push(Async(DiscardRecovery(recovered_sheet.source.to_owned())));
+        self.queue.push(Sync(App(DiscardRecoveredSheet(
+            recovered_sheet.source.to_owned(),
         ))));
     }
 
@@ -98,6 +227,14 @@ impl CommandBuffer {
         self.queue.push(Sync(App(Redo)));
     }
 
+    pub fn jump_to_history(&mut self, index: usize) {
+        self.queue.push(Sync(App(JumpToHistory(index))));
+    }
+
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.queue.push(Sync(App(SetHistoryLimit(limit))));
+    }
+
     pub fn begin_export_as(&mut self) {
         self.queue.push(Sync(Document(BeginExportAs)));
     }
@@ -175,13 +312,66 @@ impl CommandBuffer {
         self.queue.push(Sync(Document(CancelExportAs)));
     }
 
-    pub fn end_export_as(&mut self, sheet: &Sheet) {
+    pub fn end_export_as(&mut self, app_state: &AppState, document: &crate::state::Document) {
         self.queue.push(Sync(Document(EndExportAs)));
-        self.queue.push(Async(Export(sheet.clone())));
+        self.export(app_state, document);
+    }
+
+    // Sheets of a project may share their texture with other sheets, which are exported along
+    pub fn export(&mut self, app_state: &AppState, document: &crate::state::Document) {
+        if let Some(project) = app_state.get_project() {
+            if project.has_sheet(&document.source) {
+                let sheet = project.with_default_export_settings(&document.source, &document.sheet);
+                if let Some(export_settings) = sheet.get_export_settings() {
+                    let texture_destination = export_settings.texture_destination.clone();
+                    self.push_export_project(app_state, project, Some(texture_destination));
+                    return;
+                }
+            }
+        }
+        self.queue.push(Async(Export(document.sheet.clone())));
+    }
+
+    pub fn export_project(&mut self, app_state: &AppState) {
+        if let Some(project) = app_state.get_project() {
+            self.push_export_project(app_state, project, None);
+        }
     }
 
-    pub fn export(&mut self, sheet: &Sheet) {
-        self.queue.push(Async(Export(sheet.clone())));
+    fn push_export_project(
+        &mut self,
+        app_state: &AppState,
+        project: &Project,
+        texture_destination: Option<PathBuf>,
+    ) {
+        let open_sheets = app_state
+            .documents_iter()
+            .filter(|d| project.has_sheet(&d.source))
+            .map(|d| (d.source.clone(), d.sheet.clone()))
+            .collect();
+        self.queue.push(Async(ExportProject(
+            project.clone(),
+            open_sheets,
+            texture_destination,
+        )));
+    }
+
+    pub fn validate(&mut self, document: &crate::state::Document) {
+        self.queue.push(Async(Validate(
+            document.source.to_owned(),
+            document.sheet.clone(),
+        )));
+    }
+
+    pub fn end_validate<T: AsRef<Path>>(&mut self, document_path: T, problems: Vec<Problem>) {
+        self.queue.push(Sync(Document(EndValidate(
+            document_path.as_ref().to_owned(),
+            problems,
+        ))));
+    }
+
+    pub fn close_problems(&mut self) {
+        self.queue.push(Sync(Document(CloseProblems)));
     }
 
     pub fn switch_to_content_tab(&mut self, tab: ContentTab) {
@@ -200,24 +390,203 @@ impl CommandBuffer {
         ))));
     }
 
+    pub fn end_import_batch<T: AsRef<Path>>(&mut self, into: T, batch: ImportBatch) {
+        self.queue.push(Sync(Document(EndImportBatch(
+            into.as_ref().to_owned(),
+            batch,
+        ))));
+    }
+
+    pub fn begin_relocate_frame(&mut self, document: &crate::state::Document, frame: &Frame) {
+        self.queue.push(Async(BeginRelocateFrame(
+            document.source.to_owned(),
+            frame.get_id(),
+        )));
+    }
+
+    pub fn end_relocate_frame<T: AsRef<Path>, U: AsRef<Path>>(
+        &mut self,
+        document_path: T,
+        frame: FrameId,
+        new_source: U,
+    ) {
+        self.queue.push(Sync(Document(EndRelocateFrame(
+            document_path.as_ref().to_owned(),
+            frame,
+            new_source.as_ref().to_owned(),
+        ))));
+    }
+
+    pub fn begin_relocate_directory<T: AsRef<Path>>(
+        &mut self,
+        document: &crate::state::Document,
+        directory: T,
+    ) {
+        self.queue.push(Async(BeginRelocateDirectory(
+            document.source.to_owned(),
+            directory.as_ref().to_owned(),
+        )));
+    }
+
+    pub fn end_relocate_directory<T: AsRef<Path>, U: AsRef<Path>, V: AsRef<Path>>(
+        &mut self,
+        document_path: T,
+        directory: U,
+        new_directory: V,
+    ) {
+        self.queue.push(Sync(Document(EndRelocateDirectory(
+            document_path.as_ref().to_owned(),
+            directory.as_ref().to_owned(),
+            new_directory.as_ref().to_owned(),
+        ))));
+    }
+
+    pub fn begin_replace_frame_image(&mut self, document: &crate::state::Document, frame: &Frame) {
+        self.queue.push(Async(BeginReplaceFrameImage(
+            document.source.to_owned(),
+            frame.get_id(),
+            frame.get_source().to_owned(),
+        )));
+    }
+
+    pub fn begin_rescale_hitboxes<T: AsRef<Path>>(
+        &mut self,
+        document_path: T,
+        replacement: ImageReplacement,
+    ) {
+        self.queue.push(Sync(Document(BeginRescaleHitboxes(
+            document_path.as_ref().to_owned(),
+            replacement,
+        ))));
+    }
+
+    pub fn cancel_replace_frame_image(&mut self) {
+        self.queue.push(Sync(Document(CancelReplaceFrameImage)));
+    }
+
+    pub fn end_replace_frame_image<T: AsRef<Path>>(
+        &mut self,
+        document_path: T,
+        replacement: ImageReplacement,
+        rescale_hitboxes: bool,
+    ) {
+        self.queue.push(Sync(Document(EndReplaceFrameImage(
+            document_path.as_ref().to_owned(),
+            replacement,
+            rescale_hitboxes,
+        ))));
+    }
+
+    pub fn begin_split_sheet(&mut self) {
+        self.queue.push(Sync(Document(BeginSplitSheet)));
+    }
+
+    pub fn begin_set_split_source(&mut self, document: &crate::state::Document) {
+        self.queue
+            .push(Async(BeginSetSplitSource(document.source.to_owned())));
+    }
+
+    pub fn end_set_split_source<T: AsRef<Path>, U: AsRef<Path>>(
+        &mut self,
+        document_path: T,
+        source: U,
+    ) {
+        self.queue.push(Sync(Document(EndSetSplitSource(
+            document_path.as_ref().to_owned(),
+            source.as_ref().to_owned(),
+        ))));
+    }
+
+    pub fn begin_set_split_destination(&mut self, document: &crate::state::Document) {
+        self.queue
+            .push(Async(BeginSetSplitDestination(document.source.to_owned())));
+    }
+
+    pub fn end_set_split_destination<T: AsRef<Path>, U: AsRef<Path>>(
+        &mut self,
+        document_path: T,
+        destination: U,
+    ) {
+        self.queue.push(Sync(Document(EndSetSplitDestination(
+            document_path.as_ref().to_owned(),
+            destination.as_ref().to_owned(),
+        ))));
+    }
+
+    pub fn update_split_settings(&mut self, settings: &SplitSettings) {
+        self.queue
+            .push(Sync(Document(UpdateSplitSettings(settings.clone()))));
+    }
+
+    pub fn cancel_split_sheet(&mut self) {
+        self.queue.push(Sync(Document(CancelSplitSheet)));
+    }
+
+    pub fn end_split_sheet(&mut self, document: &crate::state::Document, settings: &SplitSettings) {
+        self.queue.push(Sync(Document(EndSplitSheet)));
+        self.queue.push(Async(SplitSheet(
+            document.source.to_owned(),
+            settings.clone(),
+        )));
+    }
+
+    pub fn begin_import_folder(&mut self) {
+        self.queue.push(Sync(Document(BeginImportFolder)));
+    }
+
+    pub fn begin_set_import_folder(&mut self, document: &crate::state::Document) {
+        self.queue
+            .push(Async(BeginSetImportFolder(document.source.to_owned())));
+    }
+
+    pub fn end_set_import_folder<T: AsRef<Path>, U: AsRef<Path>>(
+        &mut self,
+        document_path: T,
+        folder: U,
+    ) {
+        self.queue.push(Sync(Document(EndSetImportFolder(
+            document_path.as_ref().to_owned(),
+            folder.as_ref().to_owned(),
+        ))));
+    }
+
+    pub fn update_folder_import_settings(&mut self, settings: &FolderImportSettings) {
+        self.queue
+            .push(Sync(Document(UpdateFolderImportSettings(settings.clone()))));
+    }
+
+    pub fn cancel_import_folder(&mut self) {
+        self.queue.push(Sync(Document(CancelImportFolder)));
+    }
+
+    pub fn end_import_folder(
+        &mut self,
+        document: &crate::state::Document,
+        settings: &FolderImportSettings,
+    ) {
+        self.queue.push(Sync(Document(EndImportFolder)));
+        self.queue.push(Async(ImportFolder(
+            document.source.to_owned(),
+            settings.clone(),
+        )));
+    }
+
     pub fn clear_selection(&mut self) {
         self.queue.push(Sync(Document(ClearSelection)));
     }
 
     pub fn select_frame(&mut self, frame: &Frame) {
-        self.queue
-            .push(Sync(Document(SelectFrame(frame.get_source().to_owned()))));
+        self.queue.push(Sync(Document(SelectFrame(frame.get_id()))));
     }
 
     pub fn select_animation(&mut self, animation: &Animation) {
-        self.queue.push(Sync(Document(SelectAnimation(
-            animation.get_name().to_owned(),
-        ))));
+        self.queue
+            .push(Sync(Document(SelectAnimation(animation.get_id()))));
     }
 
     pub fn select_hitbox(&mut self, hitbox: &Hitbox) {
         self.queue
-            .push(Sync(Document(SelectHitbox(hitbox.get_name().to_owned()))));
+            .push(Sync(Document(SelectHitbox(hitbox.get_id()))));
     }
 
     pub fn select_animation_frame(&mut self, animation_frame_index: usize) {
@@ -225,6 +594,25 @@ impl CommandBuffer {
             .push(Sync(Document(SelectAnimationFrame(animation_frame_index))));
     }
 
+    pub fn toggle_selection(&mut self, item: SelectedItem) {
+        self.queue.push(Sync(Document(ToggleSelection(item))));
+    }
+
+    pub fn select_range(&mut self, item: SelectedItem) {
+        self.queue.push(Sync(Document(SelectRange(item))));
+    }
+
+    pub fn select_hitboxes_in_rectangle(
+        &mut self,
+        corner: Vector2D<f32>,
+        opposite_corner: Vector2D<f32>,
+    ) {
+        self.queue.push(Sync(Document(SelectHitboxesInRectangle(
+            corner,
+            opposite_corner,
+        ))));
+    }
+
     pub fn select_previous(&mut self) {
         self.queue.push(Sync(Document(SelectPrevious)));
     }
@@ -234,14 +622,12 @@ impl CommandBuffer {
     }
 
     pub fn edit_frame(&mut self, frame: &Frame) {
-        self.queue
-            .push(Sync(Document(EditFrame(frame.get_source().to_owned()))));
+        self.queue.push(Sync(Document(EditFrame(frame.get_id()))));
     }
 
     pub fn edit_animation(&mut self, animation: &Animation) {
-        self.queue.push(Sync(Document(EditAnimation(
-            animation.get_name().to_owned(),
-        ))));
+        self.queue
+            .push(Sync(Document(EditAnimation(animation.get_id()))));
     }
 
     pub fn create_animation(&mut self) {
@@ -249,22 +635,17 @@ impl CommandBuffer {
     }
 
     pub fn begin_frame_drag(&mut self, frame: &Frame) {
-        self.queue.push(Sync(Document(BeginFrameDrag(
-            frame.get_source().to_owned(),
-        ))));
+        self.queue
+            .push(Sync(Document(BeginFrameDrag(frame.get_id()))));
     }
 
     pub fn end_frame_drag(&mut self) {
         self.queue.push(Sync(Document(EndFrameDrag)));
     }
 
-    pub fn insert_animation_frame_before<T: AsRef<Path>>(
-        &mut self,
-        frame: T,
-        animation_frame_index: usize,
-    ) {
+    pub fn insert_animation_frame_before(&mut self, frame: FrameId, animation_frame_index: usize) {
         self.queue.push(Sync(Document(InsertAnimationFrameBefore(
-            frame.as_ref().to_owned(),
+            frame,
             animation_frame_index,
         ))));
     }
@@ -350,10 +731,8 @@ impl CommandBuffer {
     }
 
     pub fn begin_hitbox_scale(&mut self, hitbox: &Hitbox, axis: ResizeAxis) {
-        self.queue.push(Sync(Document(BeginHitboxScale(
-            hitbox.get_name().to_owned(),
-            axis,
-        ))));
+        self.queue
+            .push(Sync(Document(BeginHitboxScale(hitbox.get_id(), axis))));
     }
 
     pub fn update_hitbox_scale(&mut self, mouse_delta: Vector2D<f32>, preserve_aspect_ratio: bool) {
@@ -368,9 +747,8 @@ impl CommandBuffer {
     }
 
     pub fn begin_hitbox_drag(&mut self, hitbox: &Hitbox) {
-        self.queue.push(Sync(Document(BeginHitboxDrag(
-            hitbox.get_name().to_owned(),
-        ))));
+        self.queue
+            .push(Sync(Document(BeginHitboxDrag(hitbox.get_id()))));
     }
 
     pub fn update_hitbox_drag(&mut self, mouse_delta: Vector2D<f32>, both_axis: bool) {
@@ -446,6 +824,79 @@ impl CommandBuffer {
         self.queue.push(Sync(Document(DeleteSelection)));
     }
 
+    pub fn copy(&mut self, document: &crate::state::Document) {
+        match document.copy_selection() {
+            Ok(Some(content)) => self.queue.push(Async(Copy(content))),
+            Ok(None) => (),
+            Err(e) => self.show_error(&e),
+        }
+    }
+
+    // Cut items are deleted by end_cut, once they made it to the clipboard
+    pub fn cut(&mut self, document: &crate::state::Document) {
+        match document.copy_selection() {
+            Ok(Some(content)) => {
+                let items = document.view.selection.items_iter().cloned().collect();
+                self.queue
+                    .push(Async(Cut(document.source.to_owned(), content, items)));
+            }
+            Ok(None) => (),
+            Err(e) => self.show_error(&e),
+        }
+    }
+
+    pub fn end_cut<T: AsRef<Path>>(&mut self, document_path: T, items: Vec<SelectedItem>) {
+        self.queue.push(Sync(Document(EndCut(
+            document_path.as_ref().to_owned(),
+            items,
+        ))));
+    }
+
+    pub fn paste(&mut self, document: &crate::state::Document) {
+        self.queue
+            .push(Async(BeginPaste(document.source.to_owned())));
+    }
+
+    pub fn end_paste<T: AsRef<Path>>(&mut self, document_path: T, content: ClipboardContent) {
+        self.queue.push(Sync(Document(EndPaste(
+            document_path.as_ref().to_owned(),
+            content,
+        ))));
+    }
+
+    // Frames are duplicated once copies of their images have been written to disk
+    pub fn duplicate_selection(&mut self, document: &crate::state::Document) {
+        let frames: Vec<(FrameId, PathBuf)> = document
+            .view
+            .selection
+            .items_iter()
+            .filter_map(|i| match i {
+                SelectedItem::Frame(f) => document.sheet.get_frame(*f),
+                _ => None,
+            })
+            .map(|f| (f.get_id(), f.get_source().to_owned()))
+            .collect();
+        if frames.is_empty() {
+            self.queue.push(Sync(Document(DuplicateSelection)));
+        } else {
+            self.queue.push(Async(BeginDuplicateFrames(
+                document.source.to_owned(),
+                frames,
+            )));
+        }
+    }
+
+    pub fn end_duplicate_frames<T: AsRef<Path>>(
+        &mut self,
+        document_path: T,
+        frames: Vec<(FrameId, PathBuf)>,
+    ) {
+        self.queue.push(Sync(Document(EndDuplicateFrames(
+            document_path.as_ref().to_owned(),
+            frames,
+        ))));
+    }
+
     pub fn begin_rename_selection(&mut self) {
         self.queue.push(Sync(Document(BeginRenameSelection)));
     }
@@ -475,4 +926,12 @@ impl CommandBuffer {
     pub fn cancel_exit(&mut self) {
         self.queue.push(Sync(App(CancelExit)));
     }
+
+    pub fn show_error(&mut self, error: &Error) {
+        self.queue.push(Sync(App(ShowError(error.to_string()))));
+    }
+
+    pub fn dismiss_error(&mut self) {
+        self.queue.push(Sync(App(DismissError)));
+    }
 }
@@ -1,24 +1,113 @@
 use euclid::*;
 use failure::Error;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
+use crate::import::{FolderImportSettings, ImportBatch, SplitSettings};
 use crate::sheet::*;
 use crate::state::*;
 
+// Oldest history entries are discarded past this many
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+// Frames and animations which are identical between history entries share the same allocation
+#[derive(Clone, Debug, Default, PartialEq)]
+struct SheetSnapshot {
+    frames: Vec<Arc<Frame>>,
+    animations: Vec<Arc<Animation>>,
+    export_settings: Option<ExportSettings>,
+}
+
+impl SheetSnapshot {
+    fn new(sheet: &Sheet, previous: &SheetSnapshot) -> SheetSnapshot {
+        let previous_frames: HashMap<FrameId, &Arc<Frame>> =
+            previous.frames.iter().map(|f| (f.id, f)).collect();
+        let previous_animations: HashMap<AnimationId, &Arc<Animation>> =
+            previous.animations.iter().map(|a| (a.id, a)).collect();
+        SheetSnapshot {
+            frames: sheet
+                .frames
+                .iter()
+                .map(|f| match previous_frames.get(&f.id) {
+                    Some(p) if ***p == *f => Arc::clone(p),
+                    _ => Arc::new(f.clone()),
+                })
+                .collect(),
+            animations: sheet
+                .animations
+                .iter()
+                .map(|a| match previous_animations.get(&a.id) {
+                    Some(p) if ***p == *a => Arc::clone(p),
+                    _ => Arc::new(a.clone()),
+                })
+                .collect(),
+            export_settings: sheet.export_settings.clone(),
+        }
+    }
+
+    fn matches(&self, sheet: &Sheet) -> bool {
+        self.frames.len() == sheet.frames.len()
+            && self.animations.len() == sheet.animations.len()
+            && self
+                .frames
+                .iter()
+                .zip(&sheet.frames)
+                .all(|(s, f)| **s == *f)
+            && self
+                .animations
+                .iter()
+                .zip(&sheet.animations)
+                .all(|(s, a)| **s == *a)
+            && self.export_settings == sheet.export_settings
+    }
+
+    fn to_sheet(&self) -> Sheet {
+        Sheet {
+            frames: self.frames.iter().map(|f| (**f).clone()).collect(),
+            animations: self.animations.iter().map(|a| (**a).clone()).collect(),
+            export_settings: self.export_settings.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 struct HistoryEntry {
     last_command: Option<DocumentCommand>,
-    sheet: Sheet,
+    sheet: SheetSnapshot,
     view: View,
     version: i32,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageReplacement {
+    pub frame: FrameId,
+    pub new_source: PathBuf,
+    pub old_size: Vector2D<u32>,
+    pub new_size: Vector2D<u32>,
+}
+
+// Newer version of the sheet found on disk, along with how it differs from the document
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalChange {
+    pub sheet: Sheet,
+    pub modified: SystemTime,
+    pub changes: Vec<SheetChange>,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Persistent {
     pub export_settings_edit: Option<ExportSettings>,
+    pub split_settings_edit: Option<SplitSettings>,
+    pub folder_import_settings_edit: Option<FolderImportSettings>,
+    pub problems: Option<Vec<Problem>>,
+    pub image_replacement: Option<ImageReplacement>,
+    pub external_change: Option<ExternalChange>,
     timeline_is_playing: bool,
     disk_version: i32,
+    disk_modified: Option<SystemTime>,
+    autosave_version: Option<i32>,
 }
 
 #[derive(Clone, Debug)]
@@ -29,8 +118,9 @@ pub struct Document {
     pub transient: Transient, // State preventing undo actions when not default, not recorded in history
     pub persistent: Persistent, // Other state, not recorded in history
     next_version: i32,
-    history: Vec<HistoryEntry>,
+    history: Vec<Arc<HistoryEntry>>,
     history_index: usize,
+    history_limit: usize,
 }
 
 impl Document {
@@ -38,13 +128,14 @@ impl Document {
         let history_entry: HistoryEntry = Default::default();
         Document {
             source: path.as_ref().to_owned(),
-            history: vec![history_entry.clone()],
-            sheet: history_entry.sheet.clone(),
+            sheet: history_entry.sheet.to_sheet(),
             view: history_entry.view.clone(),
             transient: Default::default(),
             persistent: Default::default(),
             next_version: history_entry.version,
+            history: vec![Arc::new(history_entry)],
             history_index: 0,
+            history_limit: DEFAULT_HISTORY_LIMIT,
         }
     }
 
@@ -56,8 +147,12 @@ impl Document {
         let sheet: Sheet = compat::read_sheet(path.as_ref())?;
         document.sheet = sheet.with_absolute_paths(&directory)?;
 
-        document.history[0].sheet = document.sheet.clone();
+        Arc::make_mut(&mut document.history[0]).sheet =
+            SheetSnapshot::new(&document.sheet, &Default::default());
         document.persistent.disk_version = document.next_version;
+        document.persistent.disk_modified = std::fs::metadata(path.as_ref())
+            .and_then(|m| m.modified())
+            .ok();
 
         Ok(document)
     }
@@ -78,11 +173,70 @@ impl Document {
         self.history[self.history_index].version
     }
 
+    pub fn needs_autosave(&self) -> bool {
+        !self.is_saved() && self.persistent.autosave_version != Some(self.get_version())
+    }
+
+    // Selection is cleared and the workbench only keeps its item if it still exists
+    fn replace_sheet(&mut self, sheet: &Sheet) {
+        self.sheet = sheet.clone();
+        self.view.selection.clear();
+        let has_workbench_item = match &self.view.workbench_item {
+            Some(WorkbenchItem::Frame(f)) => self.sheet.has_frame(*f),
+            Some(WorkbenchItem::Animation(a)) => self.sheet.has_animation(*a),
+            None => true,
+        };
+        if !has_workbench_item {
+            self.view.workbench_item = None;
+        }
+    }
+
+    // Changes which were already offered, or which bring nothing new, are not offered again
+    fn offer_reload(&mut self, sheet: &Sheet, modified: SystemTime) {
+        if self.persistent.disk_modified == Some(modified) {
+            return;
+        }
+        if self.sheet.with_canonical_order() == sheet.with_canonical_order() {
+            self.persistent.disk_modified = Some(modified);
+            self.persistent.disk_version = self.get_version();
+            return;
+        }
+        self.persistent.external_change = Some(ExternalChange {
+            sheet: sheet.clone(),
+            modified,
+            changes: diff_sheets(&self.sheet, sheet),
+        });
+    }
+
+    fn reload_from_disk(&mut self) -> Result<(), Error> {
+        if !self.can_use_undo_system() {
+            return Err(StateError::UndoOperationNowAllowed.into());
+        }
+        let external_change = self
+            .persistent
+            .external_change
+            .take()
+            .ok_or(StateError::NoExternalChange)?;
+        self.replace_sheet(&external_change.sheet);
+        self.persistent.disk_modified = Some(external_change.modified);
+        Ok(())
+    }
+
+    fn keep_local_changes(&mut self) -> Result<(), Error> {
+        let external_change = self
+            .persistent
+            .external_change
+            .take()
+            .ok_or(StateError::NoExternalChange)?;
+        self.persistent.disk_modified = Some(external_change.modified);
+        Ok(())
+    }
+
     pub fn tick(&mut self, delta: Duration) {
         if self.persistent.timeline_is_playing {
             self.view.timeline_clock += delta;
-            if let Some(WorkbenchItem::Animation(animation_name)) = &self.view.workbench_item {
-                if let Some(animation) = self.sheet.get_animation(animation_name) {
+            if let Some(WorkbenchItem::Animation(animation_id)) = &self.view.workbench_item {
+                if let Some(animation) = self.sheet.get_animation(*animation_id) {
                     match animation.get_duration() {
                         Some(d) if d > 0 => {
                             let clock_ms = self.view.timeline_clock.as_millis();
@@ -111,8 +265,25 @@ impl Document {
 
     fn push_undo_state(&mut self, entry: HistoryEntry) {
         self.history.truncate(self.history_index + 1);
-        self.history.push(entry);
+        self.history.push(Arc::new(entry));
         self.history_index = self.history.len() - 1;
+        self.trim_history();
+    }
+
+    // Only entries older than the current one are dropped
+    fn trim_history(&mut self) {
+        let excess = self
+            .history
+            .len()
+            .saturating_sub(self.history_limit)
+            .min(self.history_index);
+        self.history.drain(..excess);
+        self.history_index -= excess;
+    }
+
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history_limit = limit.max(1);
+        self.trim_history();
     }
 
     fn can_use_undo_system(&self) -> bool {
@@ -126,14 +297,19 @@ impl Document {
         self.persistent = new_document.persistent.clone();
 
         if self.can_use_undo_system() {
-            let has_sheet_changes = &self.history[self.history_index].sheet != &new_document.sheet;
+            let current_sheet = &self.history[self.history_index].sheet;
+            let has_sheet_changes = !current_sheet.matches(&new_document.sheet);
 
             if has_sheet_changes {
                 self.next_version += 1;
             }
 
             let new_undo_state = HistoryEntry {
-                sheet: new_document.sheet,
+                sheet: if has_sheet_changes {
+                    SheetSnapshot::new(&new_document.sheet, current_sheet)
+                } else {
+                    current_sheet.clone()
+                },
                 view: new_document.view,
                 last_command: Some(command.clone()),
                 version: self.next_version,
@@ -146,7 +322,7 @@ impl Document {
                     && self.history[self.history_index - 1].sheet
                         == self.history[self.history_index].sheet;
                 if merge {
-                    self.history[self.history_index].view = new_undo_state.view;
+                    Arc::make_mut(&mut self.history[self.history_index]).view = new_undo_state.view;
                 } else {
                     self.push_undo_state(new_undo_state);
                 }
@@ -154,30 +330,58 @@ impl Document {
         }
     }
 
-    pub fn undo(&mut self) -> Result<(), Error> {
+    pub fn jump_to_history(&mut self, index: usize) -> Result<(), Error> {
         if !self.can_use_undo_system() {
             return Err(StateError::UndoOperationNowAllowed.into());
         }
+        let entry = self
+            .history
+            .get(index)
+            .ok_or(StateError::InvalidHistoryIndex)?;
+        self.sheet = entry.sheet.to_sheet();
+        self.view = entry.view.clone();
+        self.history_index = index;
+        self.persistent.timeline_is_playing = false;
+        Ok(())
+    }
+
+    pub fn undo(&mut self) -> Result<(), Error> {
         if self.history_index > 0 {
-            self.history_index -= 1;
-            self.sheet = self.history[self.history_index].sheet.clone();
-            self.view = self.history[self.history_index].view.clone();
-            self.persistent.timeline_is_playing = false;
+            self.jump_to_history(self.history_index - 1)
+        } else if !self.can_use_undo_system() {
+            Err(StateError::UndoOperationNowAllowed.into())
+        } else {
+            Ok(())
         }
-        Ok(())
     }
 
     pub fn redo(&mut self) -> Result<(), Error> {
-        if !self.can_use_undo_system() {
-            return Err(StateError::UndoOperationNowAllowed.into());
-        }
         if self.history_index < self.history.len() - 1 {
-            self.history_index += 1;
-            self.sheet = self.history[self.history_index].sheet.clone();
-            self.view = self.history[self.history_index].view.clone();
-            self.persistent.timeline_is_playing = false;
+            self.jump_to_history(self.history_index + 1)
+        } else if !self.can_use_undo_system() {
+            Err(StateError::UndoOperationNowAllowed.into())
+        } else {
+            Ok(())
         }
-        Ok(())
+    }
+
+    // Commands which led to each history entry, along with the sheet version they produced
+    pub fn history_iter(&self) -> impl Iterator<Item = (Option<&DocumentCommand>, i32)> {
+        self.history
+            .iter()
+            .map(|e| (e.last_command.as_ref(), e.version))
+    }
+
+    pub fn get_history_index(&self) -> usize {
+        self.history_index
+    }
+
+    pub fn get_disk_version(&self) -> i32 {
+        self.persistent.disk_version
+    }
+
+    pub fn get_disk_modified(&self) -> Option<SystemTime> {
+        self.persistent.disk_modified
     }
 
     pub fn get_undo_command(&self) -> Option<&DocumentCommand> {
@@ -196,7 +400,7 @@ impl Document {
         match &self.view.workbench_item {
             Some(WorkbenchItem::Animation(n)) => Some(
                 self.sheet
-                    .get_animation(n)
+                    .get_animation(*n)
                     .ok_or(StateError::AnimationNotInDocument)?,
             ),
             _ => None,
@@ -208,7 +412,7 @@ impl Document {
         match &self.view.workbench_item {
             Some(WorkbenchItem::Animation(n)) => Some(
                 self.sheet
-                    .get_animation_mut(n)
+                    .get_animation_mut(*n)
                     .ok_or(StateError::AnimationNotInDocument)?,
             ),
             _ => None,
@@ -217,52 +421,50 @@ impl Document {
     }
 
     pub fn clear_selection(&mut self) {
-        self.view.selection = None;
+        self.view.selection.clear();
     }
 
-    pub fn select_frame<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
-        if !self.sheet.has_frame(&path) {
+    pub fn select_frame(&mut self, id: FrameId) -> Result<(), Error> {
+        if !self.sheet.has_frame(id) {
             return Err(StateError::FrameNotInDocument.into());
         }
-        self.view.selection = Some(Selection::Frame(path.as_ref().to_owned()));
+        self.view.selection.set(SelectedItem::Frame(id));
         Ok(())
     }
 
-    pub fn select_animation<T: AsRef<str>>(&mut self, name: T) -> Result<(), Error> {
-        if !self.sheet.has_animation(&name) {
+    pub fn select_animation(&mut self, id: AnimationId) -> Result<(), Error> {
+        if !self.sheet.has_animation(id) {
             return Err(StateError::AnimationNotInDocument.into());
         }
-        self.view.selection = Some(Selection::Animation(name.as_ref().to_owned()));
+        self.view.selection.set(SelectedItem::Animation(id));
         Ok(())
     }
 
-    pub fn select_hitbox<T: AsRef<str>>(&mut self, hitbox_name: T) -> Result<(), Error> {
-        let frame_path = match &self.view.workbench_item {
-            Some(WorkbenchItem::Frame(p)) => Some(p.to_owned()),
+    pub fn select_hitbox(&mut self, hitbox_id: HitboxId) -> Result<(), Error> {
+        let frame_id = match &self.view.workbench_item {
+            Some(WorkbenchItem::Frame(f)) => Some(*f),
             _ => None,
         }
         .ok_or(StateError::NotEditingAnyFrame)?;
         let frame = self
             .sheet
-            .get_frame(&frame_path)
+            .get_frame(frame_id)
             .ok_or(StateError::FrameNotInDocument)?;
-        let _hitbox = frame
-            .get_hitbox(&hitbox_name)
-            .ok_or(StateError::InvalidHitboxIndex)?;
-        self.view.selection = Some(Selection::Hitbox(
-            frame_path,
-            hitbox_name.as_ref().to_owned(),
-        ));
+        if !frame.has_hitbox(hitbox_id) {
+            return Err(StateError::InvalidHitboxIndex.into());
+        }
+        self.view
+            .selection
+            .set(SelectedItem::Hitbox(frame_id, hitbox_id));
         Ok(())
     }
 
     pub fn select_animation_frame(&mut self, frame_index: usize) -> Result<(), Error> {
-        let animation_name = {
-            let animation = self.get_workbench_animation()?;
-            animation.get_name().to_owned()
-        };
+        let animation_id = self.get_workbench_animation()?.get_id();
 
-        self.view.selection = Some(Selection::AnimationFrame(animation_name, frame_index));
+        self.view
+            .selection
+            .set(SelectedItem::AnimationFrame(animation_id, frame_index));
 
         let animation = self.get_workbench_animation()?;
 
@@ -287,52 +489,125 @@ impl Document {
         Ok(())
     }
 
-    fn advance_selection<F>(&mut self, advance: F) -> Result<(), Error>
-    where
-        F: Fn(usize) -> usize,
-    {
-        match &self.view.selection {
-            Some(Selection::Frame(p)) => {
+    // Items of the same kind as the given one, in the order they are listed in
+    fn get_selectable_items(&self, item: &SelectedItem) -> Result<Vec<SelectedItem>, Error> {
+        Ok(match item {
+            SelectedItem::Frame(_) => {
                 let mut frames: Vec<&Frame> = self.sheet.frames_iter().collect();
                 frames.sort_unstable();
-                let current_index = frames
+                frames
                     .iter()
-                    .position(|f| f.get_source() == p)
-                    .ok_or(StateError::FrameNotInDocument)?;
-                if let Some(f) = frames.get(advance(current_index)) {
-                    self.view.selection = Some(Selection::Frame(f.get_source().to_owned()));
-                }
+                    .map(|f| SelectedItem::Frame(f.get_id()))
+                    .collect()
             }
-            Some(Selection::Animation(n)) => {
+            SelectedItem::Animation(_) => {
                 let mut animations: Vec<&Animation> = self.sheet.animations_iter().collect();
                 animations.sort_unstable();
-                let current_index = animations
+                animations
                     .iter()
-                    .position(|a| a.get_name() == n)
-                    .ok_or(StateError::AnimationNotInDocument)?;
-                if let Some(n) = animations.get(advance(current_index)) {
-                    self.view.selection = Some(Selection::Animation(n.get_name().to_owned()));
-                }
+                    .map(|a| SelectedItem::Animation(a.get_id()))
+                    .collect()
             }
-            Some(Selection::Hitbox(p, n)) => {
+            SelectedItem::Hitbox(frame_id, _) => {
                 let frame = self
                     .sheet
-                    .frames_iter()
-                    .find(|f| f.get_source() == p)
+                    .get_frame(*frame_id)
                     .ok_or(StateError::FrameNotInDocument)?;
                 let mut hitboxes: Vec<&Hitbox> = frame.hitboxes_iter().collect();
                 hitboxes.sort_unstable();
-                let current_index = hitboxes
+                hitboxes
                     .iter()
-                    .position(|h| h.get_name() == n)
-                    .ok_or(StateError::InvalidHitboxIndex)?;
-                if let Some(h) = hitboxes.get(advance(current_index)) {
-                    self.view.selection =
-                        Some(Selection::Hitbox(p.to_owned(), h.get_name().to_owned()));
-                }
+                    .map(|h| SelectedItem::Hitbox(*frame_id, h.get_id()))
+                    .collect()
+            }
+            SelectedItem::AnimationFrame(animation_id, _) => {
+                let animation = self
+                    .sheet
+                    .get_animation(*animation_id)
+                    .ok_or(StateError::AnimationNotInDocument)?;
+                (0..animation.get_num_frames())
+                    .map(|i| SelectedItem::AnimationFrame(*animation_id, i))
+                    .collect()
             }
-            Some(Selection::AnimationFrame(_, _)) | None => (),
+        })
+    }
+
+    pub fn toggle_selection(&mut self, item: &SelectedItem) -> Result<(), Error> {
+        if !self.get_selectable_items(item)?.contains(item) {
+            return Err(StateError::SelectedItemNotInDocument.into());
+        }
+        self.view.selection.toggle(item.clone());
+        Ok(())
+    }
+
+    // Selects every item listed between the last selected item and this one
+    pub fn select_range(&mut self, item: &SelectedItem) -> Result<(), Error> {
+        let items = self.get_selectable_items(item)?;
+        let end = items
+            .iter()
+            .position(|i| i == item)
+            .ok_or(StateError::SelectedItemNotInDocument)?;
+        let start = self
+            .view
+            .selection
+            .get_last()
+            .and_then(|last| items.iter().position(|i| i == last))
+            .unwrap_or(end);
+        let range: Vec<&SelectedItem> = if start <= end {
+            items[start..=end].iter().collect()
+        } else {
+            items[end..=start].iter().rev().collect()
         };
+        for item in range {
+            self.view.selection.add(item.clone());
+        }
+        Ok(())
+    }
+
+    pub fn select_hitboxes_in_rectangle(
+        &mut self,
+        corner: Vector2D<f32>,
+        opposite_corner: Vector2D<f32>,
+    ) -> Result<(), Error> {
+        let frame_id = match &self.view.workbench_item {
+            Some(WorkbenchItem::Frame(f)) => Some(*f),
+            _ => None,
+        }
+        .ok_or(StateError::NotEditingAnyFrame)?;
+        let frame = self
+            .sheet
+            .get_frame(frame_id)
+            .ok_or(StateError::FrameNotInDocument)?;
+        let marquee = Rect::from_points(vec![corner.to_point(), opposite_corner.to_point()]);
+        self.view.selection.clear();
+        let mut hitboxes: Vec<&Hitbox> = frame.hitboxes_iter().collect();
+        hitboxes.sort_unstable();
+        for hitbox in hitboxes {
+            if hitbox.get_rectangle().to_f32().intersects(&marquee) {
+                self.view
+                    .selection
+                    .add(SelectedItem::Hitbox(frame_id, hitbox.get_id()));
+            }
+        }
+        Ok(())
+    }
+
+    fn advance_selection<F>(&mut self, advance: F) -> Result<(), Error>
+    where
+        F: Fn(usize) -> usize,
+    {
+        let current_item = match self.view.selection.get_last() {
+            Some(SelectedItem::AnimationFrame(_, _)) | None => return Ok(()),
+            Some(item) => item.clone(),
+        };
+        let items = self.get_selectable_items(&current_item)?;
+        let current_index = items
+            .iter()
+            .position(|i| *i == current_item)
+            .ok_or(StateError::SelectedItemNotInDocument)?;
+        if let Some(item) = items.get(advance(current_index)) {
+            self.view.selection.set(item.clone());
+        }
         Ok(())
     }
 
@@ -344,85 +619,82 @@ impl Document {
         self.advance_selection(|n| n.checked_add(1).unwrap_or(n))
     }
 
-    pub fn edit_frame<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
-        if !self.sheet.has_frame(&path) {
+    pub fn edit_frame(&mut self, id: FrameId) -> Result<(), Error> {
+        if !self.sheet.has_frame(id) {
             return Err(StateError::FrameNotInDocument.into());
         }
-        self.view.workbench_item = Some(WorkbenchItem::Frame(path.as_ref().to_owned()));
+        self.view.workbench_item = Some(WorkbenchItem::Frame(id));
         self.view.workbench_offset = Vector2D::zero();
         Ok(())
     }
 
-    pub fn edit_animation<T: AsRef<str>>(&mut self, name: T) -> Result<(), Error> {
-        if !self.sheet.has_animation(&name) {
+    pub fn edit_animation(&mut self, id: AnimationId) -> Result<(), Error> {
+        if !self.sheet.has_animation(id) {
             return Err(StateError::AnimationNotInDocument.into());
         }
-        self.view.workbench_item = Some(WorkbenchItem::Animation(name.as_ref().to_owned()));
+        self.view.workbench_item = Some(WorkbenchItem::Animation(id));
         self.view.workbench_offset = Vector2D::zero();
         self.view.timeline_clock = Duration::new(0, 0);
         self.persistent.timeline_is_playing = false;
         Ok(())
     }
 
-    pub fn begin_animation_rename<T: AsRef<str>>(&mut self, old_name: T) -> Result<(), Error> {
-        let _animation = self
+    pub fn begin_animation_rename(&mut self, id: AnimationId) -> Result<(), Error> {
+        let old_name = self
             .sheet
-            .get_animation(&old_name)
-            .ok_or(StateError::AnimationNotInDocument)?;
-        self.transient.item_being_renamed =
-            Some(RenameItem::Animation(old_name.as_ref().to_owned()));
-        self.transient.rename_buffer = Some(old_name.as_ref().to_owned());
+            .get_animation(id)
+            .ok_or(StateError::AnimationNotInDocument)?
+            .get_name()
+            .to_owned();
+        self.transient.item_being_renamed = Some(RenameItem::Animation(id));
+        self.transient.rename_buffer = Some(old_name);
         Ok(())
     }
 
-    fn begin_hitbox_rename<T: AsRef<Path>, U: AsRef<str>>(
-        &mut self,
-        frame_path: T,
-        old_name: U,
-    ) -> Result<(), Error> {
-        let _hitbox = self
+    fn begin_hitbox_rename(&mut self, frame_id: FrameId, hitbox_id: HitboxId) -> Result<(), Error> {
+        let old_name = self
             .sheet
-            .get_frame(&frame_path)
+            .get_frame(frame_id)
             .ok_or(StateError::FrameNotInDocument)?
-            .get_hitbox(old_name.as_ref())
-            .ok_or(StateError::HitboxNotInFrame)?;
-        self.transient.item_being_renamed = Some(RenameItem::Hitbox(
-            frame_path.as_ref().to_owned(),
-            old_name.as_ref().to_owned(),
-        ));
-        self.transient.rename_buffer = Some(old_name.as_ref().to_owned());
+            .get_hitbox(hitbox_id)
+            .ok_or(StateError::HitboxNotInFrame)?
+            .get_name()
+            .to_owned();
+        self.transient.item_being_renamed = Some(RenameItem::Hitbox(frame_id, hitbox_id));
+        self.transient.rename_buffer = Some(old_name);
         Ok(())
     }
 
     pub fn create_animation(&mut self) -> Result<(), Error> {
-        let animation_name = {
-            let animation = self.sheet.add_animation();
-            let animation_name = animation.get_name().to_owned();
-            self.begin_animation_rename(&animation_name)?;
-            animation_name
-        };
-        self.select_animation(&animation_name)?;
-        self.edit_animation(animation_name)
+        let animation_id = self.sheet.add_animation().get_id();
+        self.begin_animation_rename(animation_id)?;
+        self.select_animation(animation_id)?;
+        self.edit_animation(animation_id)
     }
 
-    pub fn begin_frame_drag<T: AsRef<Path>>(&mut self, frame: T) -> Result<(), Error> {
-        // TODO Validate that frame is in sheet
-        self.transient.content_frame_being_dragged = Some(frame.as_ref().to_owned());
+    pub fn begin_frame_drag(&mut self, frame: FrameId) -> Result<(), Error> {
+        if !self.sheet.has_frame(frame) {
+            return Err(StateError::FrameNotInDocument.into());
+        }
+        self.transient.content_frame_being_dragged = Some(frame);
         Ok(())
     }
 
-    pub fn insert_animation_frame_before<T: AsRef<Path>>(
+    pub fn insert_animation_frame_before(
         &mut self,
-        frame: T,
+        frame: FrameId,
         next_frame_index: usize,
     ) -> Result<(), Error> {
-        let animation_name = match &self.view.workbench_item {
-            Some(WorkbenchItem::Animation(animation_name)) => Some(animation_name.to_owned()),
+        let animation_id = match &self.view.workbench_item {
+            Some(WorkbenchItem::Animation(a)) => Some(*a),
             _ => None,
         }
         .ok_or(StateError::NotEditingAnyAnimation)?;
+        if !self.sheet.has_frame(frame) {
+            return Err(StateError::FrameNotInDocument.into());
+        }
         self.sheet
-            .get_animation_mut(animation_name)
+            .get_animation_mut(animation_id)
             .ok_or(StateError::AnimationNotInDocument)?
             .insert_frame(frame, next_frame_index)?;
         Ok(())
@@ -437,67 +709,87 @@ impl Document {
             return Ok(());
         }
 
-        let animation_name = match &self.view.workbench_item {
-            Some(WorkbenchItem::Animation(animation_name)) => Some(animation_name.to_owned()),
+        let animation_id = match &self.view.workbench_item {
+            Some(WorkbenchItem::Animation(a)) => Some(*a),
             _ => None,
         }
         .ok_or(StateError::NotEditingAnyAnimation)?;
 
         self.sheet
-            .get_animation_mut(&animation_name)
+            .get_animation_mut(animation_id)
             .ok_or(StateError::AnimationNotInDocument)?
             .reorder_frame(old_index, new_index)?;
 
-        match self.view.selection {
-            Some(Selection::AnimationFrame(ref n, i)) if n == &animation_name => {
-                if i == old_index {
-                    self.view.selection = Some(Selection::AnimationFrame(
-                        n.clone(),
-                        new_index - if old_index < new_index { 1 } else { 0 },
-                    ));
-                } else if i > old_index && i < new_index {
-                    self.view.selection = Some(Selection::AnimationFrame(n.clone(), i - 1));
-                } else if i >= new_index && i < old_index {
-                    self.view.selection = Some(Selection::AnimationFrame(n.clone(), i + 1));
+        for item in self.view.selection.items_iter_mut() {
+            match item {
+                SelectedItem::AnimationFrame(a, i) if *a == animation_id => {
+                    if *i == old_index {
+                        *i = new_index - if old_index < new_index { 1 } else { 0 };
+                    } else if *i > old_index && *i < new_index {
+                        *i -= 1;
+                    } else if *i >= new_index && *i < old_index {
+                        *i += 1;
+                    }
                 }
+                _ => (),
             }
-            _ => (),
         }
 
         Ok(())
     }
 
+    // Dragging the duration of a selected frame also changes the duration of other selected frames
     pub fn begin_animation_frame_duration_drag(&mut self, index: usize) -> Result<(), Error> {
-        let old_duration = {
-            let animation_name = match &self.view.workbench_item {
-                Some(WorkbenchItem::Animation(animation_name)) => Some(animation_name.to_owned()),
+        let initial_durations = {
+            let animation_id = match &self.view.workbench_item {
+                Some(WorkbenchItem::Animation(a)) => Some(*a),
                 _ => None,
             }
             .ok_or(StateError::NotEditingAnyAnimation)?;
 
             let animation = self
                 .sheet
-                .get_animation(animation_name)
+                .get_animation(animation_id)
                 .ok_or(StateError::AnimationNotInDocument)?;
 
-            let animation_frame = animation
-                .get_frame(index)
-                .ok_or(StateError::InvalidAnimationFrameIndex)?;
+            let mut indices = vec![index];
+            if self
+                .view
+                .selection
+                .contains(&SelectedItem::AnimationFrame(animation_id, index))
+            {
+                for item in self.view.selection.items_iter() {
+                    match item {
+                        SelectedItem::AnimationFrame(a, i) if *a == animation_id && *i != index => {
+                            indices.push(*i)
+                        }
+                        _ => (),
+                    }
+                }
+            }
 
-            animation_frame.get_duration()
+            indices
+                .into_iter()
+                .map(|i| {
+                    animation
+                        .get_frame(i)
+                        .map(|f| (i, f.get_duration()))
+                        .ok_or(StateError::InvalidAnimationFrameIndex)
+                })
+                .collect::<Result<Vec<(usize, u32)>, StateError>>()?
         };
 
         self.transient.timeline_frame_being_scaled = Some(index);
-        self.transient.timeline_frame_scale_initial_duration = old_duration;
+        self.transient.timeline_frame_scale_initial_durations = initial_durations;
         self.transient.timeline_frame_scale_initial_clock = self.view.timeline_clock;
 
         Ok(())
     }
 
     pub fn update_animation_frame_duration_drag(&mut self, new_duration: u32) -> Result<(), Error> {
-        let frame_start_time = {
-            let animation_name = match &self.view.workbench_item {
-                Some(WorkbenchItem::Animation(animation_name)) => Some(animation_name.to_owned()),
+        let clock_delta = {
+            let animation_id = match &self.view.workbench_item {
+                Some(WorkbenchItem::Animation(a)) => Some(*a),
                 _ => None,
             }
             .ok_or(StateError::NotEditingAnyAnimation)?;
@@ -507,22 +799,41 @@ impl Document {
                 .timeline_frame_being_scaled
                 .ok_or(StateError::NotDraggingATimelineFrame)?;
 
+            let initial_durations = &self.transient.timeline_frame_scale_initial_durations;
+            let initial_duration = initial_durations
+                .iter()
+                .find(|(i, _)| *i == index)
+                .map(|(_, d)| *d)
+                .ok_or(StateError::NotDraggingATimelineFrame)?;
+            let duration_delta = i64::from(new_duration) - i64::from(initial_duration);
+            let initial_clock = self
+                .transient
+                .timeline_frame_scale_initial_clock
+                .as_millis();
+
             let animation = self
                 .sheet
-                .get_animation_mut(&animation_name)
+                .get_animation_mut(animation_id)
                 .ok_or(StateError::AnimationNotInDocument)?;
 
-            let animation_frame = animation
-                .get_frame_mut(index)
-                .ok_or(StateError::InvalidAnimationFrameIndex)?;
-
-            animation_frame.set_duration(new_duration);
-
-            let frame_times = animation.get_frame_times();
-
-            *frame_times
-                .get(index)
-                .ok_or(StateError::InvalidAnimationFrameIndex)?
+            // Frames ending before the playhead push it back or forth
+            let mut clock_delta = 0;
+            let mut frame_start_time = 0;
+            for (i, animation_frame) in animation.frames_iter_mut().enumerate() {
+                let old_duration = match initial_durations.iter().find(|(f, _)| *f == i) {
+                    Some((_, d)) => {
+                        let new_duration = (i64::from(*d) + duration_delta).max(1);
+                        animation_frame.set_duration(new_duration as u32);
+                        if initial_clock >= frame_start_time + u128::from(*d) {
+                            clock_delta += new_duration - i64::from(*d);
+                        }
+                        *d
+                    }
+                    None => animation_frame.get_duration(),
+                };
+                frame_start_time += u128::from(old_duration);
+            }
+            clock_delta
         };
 
         if !self.persistent.timeline_is_playing {
@@ -530,12 +841,8 @@ impl Document {
                 .transient
                 .timeline_frame_scale_initial_clock
                 .as_millis();
-            let initial_duration = self.transient.timeline_frame_scale_initial_duration as u128;
-            if initial_clock >= frame_start_time as u128 + initial_duration {
-                self.view.timeline_clock = Duration::from_millis(
-                    initial_clock as u64 + new_duration as u64 - initial_duration as u64,
-                );
-            }
+            self.view.timeline_clock =
+                Duration::from_millis((initial_clock as i64 + clock_delta).max(0) as u64);
         }
 
         Ok(())
@@ -543,7 +850,7 @@ impl Document {
 
     pub fn end_animation_frame_duration_drag(&mut self) {
         self.transient.timeline_frame_being_scaled = None;
-        self.transient.timeline_frame_scale_initial_duration = 0;
+        self.transient.timeline_frame_scale_initial_durations = Vec::new();
         self.transient.timeline_frame_scale_initial_clock = Default::default();
     }
 
@@ -551,14 +858,14 @@ impl Document {
         &mut self,
         animation_frame_index: usize,
     ) -> Result<(), Error> {
-        let animation_name = match &self.view.workbench_item {
-            Some(WorkbenchItem::Animation(animation_name)) => Some(animation_name.to_owned()),
+        let animation_id = match &self.view.workbench_item {
+            Some(WorkbenchItem::Animation(a)) => Some(*a),
             _ => None,
         }
         .ok_or(StateError::NotEditingAnyAnimation)?;
         let animation = self
             .sheet
-            .get_animation(animation_name)
+            .get_animation(animation_id)
             .ok_or(StateError::AnimationNotInDocument)?;
         let _animation_frame = animation
             .get_frame(animation_frame_index)
@@ -571,8 +878,8 @@ impl Document {
         &mut self,
         index: usize,
     ) -> Result<(), Error> {
-        let animation_name = match &self.view.workbench_item {
-            Some(WorkbenchItem::Animation(animation_name)) => Some(animation_name.to_owned()),
+        let animation_id = match &self.view.workbench_item {
+            Some(WorkbenchItem::Animation(a)) => Some(*a),
             _ => None,
         }
         .ok_or(StateError::NotEditingAnyAnimation)?;
@@ -580,7 +887,7 @@ impl Document {
         {
             let animation = self
                 .sheet
-                .get_animation_mut(animation_name)
+                .get_animation_mut(animation_id)
                 .ok_or(StateError::AnimationNotInDocument)?;
 
             let animation_frame = animation
@@ -600,8 +907,8 @@ impl Document {
         both_axis: bool,
     ) -> Result<(), Error> {
         let zoom = self.view.get_workbench_zoom_factor();
-        let animation_name = match &self.view.workbench_item {
-            Some(WorkbenchItem::Animation(animation_name)) => Some(animation_name.to_owned()),
+        let animation_id = match &self.view.workbench_item {
+            Some(WorkbenchItem::Animation(a)) => Some(*a),
             _ => None,
         }
         .ok_or(StateError::NotEditingAnyAnimation)?;
@@ -623,7 +930,7 @@ impl Document {
 
         let animation_frame = self
             .sheet
-            .get_animation_mut(animation_name)
+            .get_animation_mut(animation_id)
             .ok_or(StateError::AnimationNotInDocument)?
             .get_frame_mut(animation_index)
             .ok_or(StateError::InvalidAnimationFrameIndex)?;
@@ -638,33 +945,29 @@ impl Document {
     }
 
     pub fn create_hitbox(&mut self, mouse_position: Vector2D<f32>) -> Result<(), Error> {
-        let hitbox_name = {
-            let frame_path = match &self.view.workbench_item {
-                Some(WorkbenchItem::Frame(s)) => Some(s.to_owned()),
+        let hitbox_id = {
+            let frame_id = match &self.view.workbench_item {
+                Some(WorkbenchItem::Frame(f)) => Some(*f),
                 _ => None,
             }
             .ok_or(StateError::NotEditingAnyFrame)?;
 
             let frame = self
                 .sheet
-                .get_frame_mut(frame_path)
+                .get_frame_mut(frame_id)
                 .ok_or(StateError::FrameNotInDocument)?;
 
             let hitbox = frame.add_hitbox();
             hitbox.set_position(mouse_position.floor().to_i32());
-            hitbox.get_name().to_owned()
+            hitbox.get_id()
         };
-        self.begin_hitbox_scale(&hitbox_name, ResizeAxis::SE)?;
-        self.select_hitbox(&hitbox_name)
+        self.begin_hitbox_scale(hitbox_id, ResizeAxis::SE)?;
+        self.select_hitbox(hitbox_id)
     }
 
-    pub fn begin_hitbox_scale<T: AsRef<str>>(
-        &mut self,
-        hitbox_name: T,
-        axis: ResizeAxis,
-    ) -> Result<(), Error> {
-        let frame_path = match &self.view.workbench_item {
-            Some(WorkbenchItem::Frame(s)) => Some(s.to_owned()),
+    pub fn begin_hitbox_scale(&mut self, hitbox_id: HitboxId, axis: ResizeAxis) -> Result<(), Error> {
+        let frame_id = match &self.view.workbench_item {
+            Some(WorkbenchItem::Frame(f)) => Some(*f),
             _ => None,
         }
         .ok_or(StateError::NotEditingAnyFrame)?;
@@ -675,16 +978,16 @@ impl Document {
         {
             let frame = self
                 .sheet
-                .get_frame(&frame_path)
+                .get_frame(frame_id)
                 .ok_or(StateError::FrameNotInDocument)?;
             hitbox = frame
-                .get_hitbox(&hitbox_name)
+                .get_hitbox(hitbox_id)
                 .ok_or(StateError::InvalidHitboxIndex)?;
             position = hitbox.get_position();
             size = hitbox.get_size();
         }
 
-        self.transient.workbench_hitbox_being_scaled = Some(hitbox_name.as_ref().to_owned());
+        self.transient.workbench_hitbox_being_scaled = Some(hitbox_id);
         self.transient.workbench_hitbox_scale_axis = axis;
         self.transient.workbench_hitbox_scale_initial_position = position;
         self.transient.workbench_hitbox_scale_initial_size = size;
@@ -699,8 +1002,8 @@ impl Document {
     ) -> Result<(), Error> {
         use ResizeAxis::*;
 
-        let frame_path = match &self.view.workbench_item {
-            Some(WorkbenchItem::Frame(s)) => Some(s.to_owned()),
+        let frame_id = match &self.view.workbench_item {
+            Some(WorkbenchItem::Frame(f)) => Some(*f),
             _ => None,
         }
         .ok_or(StateError::NotEditingAnyFrame)?;
@@ -783,17 +1086,16 @@ impl Document {
             ],
         });
 
-        let hitbox_name = self
+        let hitbox_id = self
             .transient
             .workbench_hitbox_being_scaled
-            .as_ref()
             .ok_or(StateError::NotDraggingAHitbox)?;
 
         let hitbox = self
             .sheet
-            .get_frame_mut(frame_path)
+            .get_frame_mut(frame_id)
             .ok_or(StateError::FrameNotInDocument)?
-            .get_hitbox_mut(&hitbox_name)
+            .get_hitbox_mut(hitbox_id)
             .ok_or(StateError::InvalidHitboxIndex)?;
 
         hitbox.set_position(new_hitbox.origin.to_vector());
@@ -803,8 +1105,8 @@ impl Document {
     }
 
     pub fn end_hitbox_scale(&mut self) -> Result<(), Error> {
-        if let Some(hitbox_name) = self.transient.workbench_hitbox_being_scaled.clone() {
-            self.select_hitbox(hitbox_name)?;
+        if let Some(hitbox_id) = self.transient.workbench_hitbox_being_scaled {
+            self.select_hitbox(hitbox_id)?;
         }
         self.transient.workbench_hitbox_scale_axis = ResizeAxis::N;
         self.transient.workbench_hitbox_scale_initial_position = Vector2D::<i32>::zero();
@@ -813,12 +1115,9 @@ impl Document {
         Ok(())
     }
 
-    pub fn begin_hitbox_drag<T: AsRef<str>>(
-        &mut self,
-        hitbox_name: T,
-    ) -> Result<(), Error> {
-        let frame_path = match &self.view.workbench_item {
-            Some(WorkbenchItem::Frame(s)) => Some(s.to_owned()),
+    pub fn begin_hitbox_drag(&mut self, hitbox_id: HitboxId) -> Result<(), Error> {
+        let frame_id = match &self.view.workbench_item {
+            Some(WorkbenchItem::Frame(f)) => Some(*f),
             _ => None,
         }
         .ok_or(StateError::NotEditingAnyFrame)?;
@@ -827,17 +1126,17 @@ impl Document {
         {
             let frame = self
                 .sheet
-                .get_frame(&frame_path)
+                .get_frame(frame_id)
                 .ok_or(StateError::FrameNotInDocument)?;
             let hitbox = frame
-                .get_hitbox(&hitbox_name)
+                .get_hitbox(hitbox_id)
                 .ok_or(StateError::InvalidHitboxIndex)?;
             hitbox_position = hitbox.get_position();
         }
 
-        self.transient.workbench_hitbox_being_dragged = Some(hitbox_name.as_ref().to_owned());
+        self.transient.workbench_hitbox_being_dragged = Some(hitbox_id);
         self.transient.workbench_hitbox_drag_initial_offset = hitbox_position;
-        self.select_hitbox(hitbox_name)?;
+        self.select_hitbox(hitbox_id)?;
 
         Ok(())
     }
@@ -849,17 +1148,15 @@ impl Document {
     ) -> Result<(), Error> {
         let zoom = self.view.get_workbench_zoom_factor();
 
-        let frame_path = match &self.view.workbench_item {
-            Some(WorkbenchItem::Frame(p)) => Some(p.to_owned()),
+        let frame_id = match &self.view.workbench_item {
+            Some(WorkbenchItem::Frame(f)) => Some(*f),
             _ => None,
         }
         .ok_or(StateError::NotEditingAnyFrame)?;
 
-        let hitbox_name = self
+        let hitbox_id = self
             .transient
             .workbench_hitbox_being_dragged
-            .as_ref()
-            .cloned()
             .ok_or(StateError::NotDraggingAHitbox)?;
 
         let old_offset = self.transient.workbench_hitbox_drag_initial_offset;
@@ -876,9 +1173,9 @@ impl Document {
 
         let hitbox = self
             .sheet
-            .get_frame_mut(frame_path)
+            .get_frame_mut(frame_id)
             .ok_or(StateError::FrameNotInDocument)?
-            .get_hitbox_mut(&hitbox_name)
+            .get_hitbox_mut(hitbox_id)
             .ok_or(StateError::InvalidHitboxIndex)?;
         hitbox.set_position(new_offset);
 
@@ -994,77 +1291,250 @@ impl Document {
     pub fn nudge_selection(&mut self, direction: Vector2D<i32>, large: bool) -> Result<(), Error> {
         let amplitude = if large { 10 } else { 1 };
         let offset = direction * amplitude;
-        match &self.view.selection {
-            Some(Selection::Animation(_)) => {}
-            Some(Selection::Frame(_)) => {}
-            Some(Selection::Hitbox(f, h)) => {
-                let hitbox = self
-                    .sheet
-                    .get_frame_mut(f)
-                    .ok_or(StateError::FrameNotInDocument)?
-                    .get_hitbox_mut(&h)
-                    .ok_or(StateError::InvalidHitboxIndex)?;
-                hitbox.set_position(hitbox.get_position() + offset);
-            }
-            Some(Selection::AnimationFrame(a, af)) => {
-                let animation_frame = self
-                    .sheet
-                    .get_animation_mut(a)
-                    .ok_or(StateError::AnimationNotInDocument)?
-                    .get_frame_mut(*af)
-                    .ok_or(StateError::InvalidAnimationFrameIndex)?;
-                animation_frame.set_offset(animation_frame.get_offset() + offset);
-            }
-            None => {}
-        };
+        for item in self.view.selection.items_iter() {
+            match *item {
+                SelectedItem::Animation(_) => {}
+                SelectedItem::Frame(_) => {}
+                SelectedItem::Hitbox(f, h) => {
+                    let hitbox = self
+                        .sheet
+                        .get_frame_mut(f)
+                        .ok_or(StateError::FrameNotInDocument)?
+                        .get_hitbox_mut(h)
+                        .ok_or(StateError::InvalidHitboxIndex)?;
+                    hitbox.set_position(hitbox.get_position() + offset);
+                }
+                SelectedItem::AnimationFrame(a, af) => {
+                    let animation_frame = self
+                        .sheet
+                        .get_animation_mut(a)
+                        .ok_or(StateError::AnimationNotInDocument)?
+                        .get_frame_mut(af)
+                        .ok_or(StateError::InvalidAnimationFrameIndex)?;
+                    animation_frame.set_offset(animation_frame.get_offset() + offset);
+                }
+            };
+        }
         Ok(())
     }
 
     pub fn delete_selection(&mut self) {
-        match &self.view.selection {
-            Some(Selection::Animation(a)) => {
-                self.sheet.delete_animation(&a);
-                if self.transient.item_being_renamed == Some(RenameItem::Animation(a.clone())) {
-                    self.transient.item_being_renamed = None;
-                    self.transient.rename_buffer = None;
+        let items: Vec<SelectedItem> = self.view.selection.items_iter().cloned().collect();
+        self.delete_items(items);
+    }
+
+    fn delete_items(&mut self, mut items: Vec<SelectedItem>) {
+        // Later animation frames go first so that indices of the others remain valid
+        items.sort_by_key(|item| match item {
+            SelectedItem::AnimationFrame(_, af) => std::cmp::Reverse(*af),
+            _ => std::cmp::Reverse(0),
+        });
+
+        for item in items {
+            match item {
+                SelectedItem::Animation(a) => {
+                    self.sheet.delete_animation(a);
+                    if self.transient.item_being_renamed == Some(RenameItem::Animation(a)) {
+                        self.transient.item_being_renamed = None;
+                        self.transient.rename_buffer = None;
+                    }
                 }
-            }
-            Some(Selection::Frame(f)) => {
-                self.sheet.delete_frame(&f);
-                if self.transient.content_frame_being_dragged == Some(f.clone()) {
-                    self.transient.content_frame_being_dragged = None;
+                SelectedItem::Frame(f) => {
+                    self.sheet.delete_frame(f);
+                    if self.transient.content_frame_being_dragged == Some(f) {
+                        self.transient.content_frame_being_dragged = None;
+                    }
                 }
-            }
-            Some(Selection::Hitbox(f, h)) => {
-                self.sheet.delete_hitbox(&f, &h);
-                if self.view.workbench_item == Some(WorkbenchItem::Frame(f.clone())) {
-                    if self.transient.workbench_hitbox_being_dragged == Some(h.to_owned()) {
-                        self.transient.workbench_hitbox_being_dragged = None;
+                SelectedItem::Hitbox(f, h) => {
+                    self.sheet.delete_hitbox(f, h);
+                    if self.view.workbench_item == Some(WorkbenchItem::Frame(f)) {
+                        if self.transient.workbench_hitbox_being_dragged == Some(h) {
+                            self.transient.workbench_hitbox_being_dragged = None;
+                        }
+                        if self.transient.workbench_hitbox_being_scaled == Some(h) {
+                            self.transient.workbench_hitbox_being_scaled = None;
+                        }
                     }
-                    if self.transient.workbench_hitbox_being_scaled == Some(h.to_owned()) {
-                        self.transient.workbench_hitbox_being_scaled = None;
+                }
+                SelectedItem::AnimationFrame(a, af) => {
+                    self.sheet.delete_animation_frame(a, af);
+                    if self.view.workbench_item == Some(WorkbenchItem::Animation(a))
+                        && self.transient.workbench_animation_frame_being_dragged == Some(af)
+                    {
+                        self.transient.workbench_animation_frame_being_dragged = None;
+                    }
+                }
+            };
+        }
+        self.view.selection.clear();
+    }
+
+    pub fn duplicate_selection(&mut self) -> Result<(), Error> {
+        let items: Vec<SelectedItem> = self.view.selection.items_iter().cloned().collect();
+        let mut duplicates = Vec::new();
+        match items.first() {
+            Some(SelectedItem::Animation(_)) => {
+                for item in &items {
+                    if let SelectedItem::Animation(a) = item {
+                        let duplicate = self.sheet.duplicate_animation(*a)?;
+                        duplicates.push(SelectedItem::Animation(duplicate));
                     }
                 }
             }
-            Some(Selection::AnimationFrame(a, af)) => {
-                self.sheet.delete_animation_frame(a, *af);
-                if self.view.workbench_item == Some(WorkbenchItem::Animation(a.clone()))
-                    && self.transient.workbench_animation_frame_being_dragged == Some(*af)
-                {
-                    self.transient.workbench_animation_frame_being_dragged = None;
+            Some(SelectedItem::AnimationFrame(a, _)) => {
+                let animation_id = *a;
+                let mut indices: Vec<usize> = items
+                    .iter()
+                    .filter_map(|i| match i {
+                        SelectedItem::AnimationFrame(owner, af) if *owner == animation_id => {
+                            Some(*af)
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                indices.sort();
+
+                // Each copy shifts the keyframes after it, so they are duplicated from last to first
+                let animation = self
+                    .sheet
+                    .get_animation_mut(animation_id)
+                    .ok_or(StateError::AnimationNotInDocument)?;
+                for index in indices.iter().rev() {
+                    animation.duplicate_frame(*index)?;
+                }
+                for (n, index) in indices.iter().enumerate() {
+                    duplicates.push(SelectedItem::AnimationFrame(animation_id, index + n + 1));
                 }
             }
-            None => {}
+            Some(SelectedItem::Frame(_)) | Some(SelectedItem::Hitbox(_, _)) | None => (),
         };
-        self.view.selection = None;
+
+        if !duplicates.is_empty() {
+            self.view.selection.clear();
+            for item in duplicates {
+                self.view.selection.add(item);
+            }
+        }
+        Ok(())
+    }
+
+    fn end_duplicate_frames(&mut self, frames: &[(FrameId, PathBuf)]) -> Result<(), Error> {
+        self.view.selection.clear();
+        for (frame, new_source) in frames {
+            let duplicate = self.sheet.duplicate_frame(*frame, new_source)?;
+            self.view.selection.add(SelectedItem::Frame(duplicate));
+        }
+        Ok(())
+    }
+
+    pub fn copy_selection(&self) -> Result<Option<ClipboardContent>, Error> {
+        let items: Vec<SelectedItem> = self.view.selection.items_iter().cloned().collect();
+        let content = match items.first() {
+            Some(SelectedItem::Hitbox(f, _)) => {
+                let frame = self
+                    .sheet
+                    .get_frame(*f)
+                    .ok_or(StateError::FrameNotInDocument)?;
+                let hitboxes: Vec<HitboxId> = items
+                    .iter()
+                    .filter_map(|i| match i {
+                        SelectedItem::Hitbox(owner, h) if owner == f => Some(*h),
+                        _ => None,
+                    })
+                    .collect();
+                Some(ClipboardContent::from_hitboxes(frame, &hitboxes)?)
+            }
+            Some(SelectedItem::AnimationFrame(a, _)) => {
+                let animation = self
+                    .sheet
+                    .get_animation(*a)
+                    .ok_or(StateError::AnimationNotInDocument)?;
+                let indices: Vec<usize> = items
+                    .iter()
+                    .filter_map(|i| match i {
+                        SelectedItem::AnimationFrame(owner, af) if owner == a => Some(*af),
+                        _ => None,
+                    })
+                    .collect();
+                Some(ClipboardContent::from_keyframes(
+                    &self.sheet,
+                    animation,
+                    &indices,
+                )?)
+            }
+            Some(SelectedItem::Animation(_)) => {
+                let animations: Vec<AnimationId> = items
+                    .iter()
+                    .filter_map(|i| match i {
+                        SelectedItem::Animation(a) => Some(*a),
+                        _ => None,
+                    })
+                    .collect();
+                Some(ClipboardContent::from_animations(&self.sheet, &animations)?)
+            }
+            Some(SelectedItem::Frame(_)) | None => None,
+        };
+        Ok(content)
+    }
+
+    fn end_paste(&mut self, content: &ClipboardContent) -> Result<(), Error> {
+        let pasted_items: Vec<SelectedItem> = match content {
+            ClipboardContent::Hitboxes(hitboxes) => {
+                let frame_id = match &self.view.workbench_item {
+                    Some(WorkbenchItem::Frame(f)) => Some(*f),
+                    _ => None,
+                }
+                .ok_or(StateError::NotEditingAnyFrame)?;
+                self.sheet
+                    .paste_hitboxes(frame_id, hitboxes)?
+                    .into_iter()
+                    .map(|h| SelectedItem::Hitbox(frame_id, h))
+                    .collect()
+            }
+            ClipboardContent::Keyframes(keyframes) => {
+                let animation = self.get_workbench_animation()?;
+                let animation_id = animation.get_id();
+
+                // Keyframes are pasted after the selected ones, or at the end of the timeline
+                let index = self
+                    .view
+                    .selection
+                    .items_iter()
+                    .filter_map(|i| match i {
+                        SelectedItem::AnimationFrame(a, af) if *a == animation_id => Some(af + 1),
+                        _ => None,
+                    })
+                    .max()
+                    .unwrap_or_else(|| animation.get_num_frames());
+
+                self.sheet.paste_keyframes(animation_id, index, keyframes)?;
+                (index..index + keyframes.len())
+                    .map(|af| SelectedItem::AnimationFrame(animation_id, af))
+                    .collect()
+            }
+            ClipboardContent::Animations(animations) => {
+                self.view.content_tab = ContentTab::Animations;
+                self.sheet
+                    .paste_animations(animations)
+                    .into_iter()
+                    .map(SelectedItem::Animation)
+                    .collect()
+            }
+        };
+
+        self.view.selection.clear();
+        for item in pasted_items {
+            self.view.selection.add(item);
+        }
+        Ok(())
     }
 
     pub fn begin_rename_selection(&mut self) -> Result<(), Error> {
-        match &self.view.selection {
-            Some(Selection::Animation(a)) => self.begin_animation_rename(a.clone())?,
-            Some(Selection::Hitbox(f, h)) => self.begin_hitbox_rename(f.clone(), h.clone())?,
-            Some(Selection::Frame(_f)) => (),
-            Some(Selection::AnimationFrame(_a, _af)) => (),
+        match self.view.selection.get_single().cloned() {
+            Some(SelectedItem::Animation(a)) => self.begin_animation_rename(a)?,
+            Some(SelectedItem::Hitbox(f, h)) => self.begin_hitbox_rename(f, h)?,
+            Some(SelectedItem::Frame(_f)) => (),
+            Some(SelectedItem::AnimationFrame(_a, _af)) => (),
             None => {}
         };
         Ok(())
@@ -1077,42 +1547,32 @@ impl Document {
             .clone()
             .ok_or(StateError::NotRenaming)?;
 
-        match self.transient.item_being_renamed.as_ref().cloned() {
-            Some(RenameItem::Animation(old_name)) => {
-                if old_name != new_name {
-                    if self.sheet.has_animation(&new_name) {
+        match self.transient.item_being_renamed {
+            Some(RenameItem::Animation(id)) => {
+                let animation = self
+                    .sheet
+                    .get_animation(id)
+                    .ok_or(StateError::AnimationNotInDocument)?;
+                if animation.get_name() != new_name {
+                    if self.sheet.has_animation_name(&new_name) {
                         return Err(StateError::AnimationAlreadyExists.into());
                     }
-                    self.sheet.rename_animation(&old_name, &new_name)?;
-                    if Some(Selection::Animation(old_name.clone())) == self.view.selection {
-                        self.view.selection = Some(Selection::Animation(new_name.clone()));
-                    }
-                    if Some(WorkbenchItem::Animation(old_name.clone())) == self.view.workbench_item
-                    {
-                        self.view.workbench_item = Some(WorkbenchItem::Animation(new_name.clone()));
-                    }
+                    self.sheet.rename_animation(id, &new_name)?;
                 }
             }
-            Some(RenameItem::Hitbox(frame_path, old_name)) => {
-                if old_name != new_name {
-                    if self
-                        .sheet
-                        .get_frame(&frame_path)
-                        .ok_or(StateError::FrameNotInDocument)?
-                        .has_hitbox(&new_name)
-                    {
+            Some(RenameItem::Hitbox(frame_id, hitbox_id)) => {
+                let frame = self
+                    .sheet
+                    .get_frame_mut(frame_id)
+                    .ok_or(StateError::FrameNotInDocument)?;
+                let hitbox = frame
+                    .get_hitbox(hitbox_id)
+                    .ok_or(StateError::HitboxNotInFrame)?;
+                if hitbox.get_name() != new_name {
+                    if frame.has_hitbox_name(&new_name) {
                         return Err(StateError::HitboxAlreadyExists.into());
                     }
-                    self.sheet
-                        .get_frame_mut(&frame_path)
-                        .ok_or(StateError::FrameNotInDocument)?
-                        .rename_hitbox(&old_name, &new_name)?;
-                    if Some(Selection::Hitbox(frame_path.clone(), old_name.clone()))
-                        == self.view.selection
-                    {
-                        self.view.selection =
-                            Some(Selection::Hitbox(frame_path.clone(), new_name.clone()));
-                    }
+                    frame.rename_hitbox(hitbox_id, &new_name)?;
                 }
             }
             None => (),
@@ -1183,14 +1643,173 @@ impl Document {
         Ok(())
     }
 
+    fn get_split_settings_edit_mut(&mut self) -> Result<&mut SplitSettings, Error> {
+        self.persistent
+            .split_settings_edit
+            .as_mut()
+            .ok_or(StateError::NotSplittingSheet.into())
+    }
+
+    fn begin_split_sheet(&mut self) {
+        if self.persistent.split_settings_edit.is_none() {
+            self.persistent.split_settings_edit = Some(SplitSettings::new());
+        }
+    }
+
+    fn cancel_split_sheet(&mut self) {
+        self.persistent.split_settings_edit = None;
+    }
+
+    fn end_set_split_source<T: AsRef<Path>>(&mut self, source: T) -> Result<(), Error> {
+        self.get_split_settings_edit_mut()?.source = source.as_ref().to_owned();
+        Ok(())
+    }
+
+    fn end_set_split_destination<T: AsRef<Path>>(&mut self, destination: T) -> Result<(), Error> {
+        self.get_split_settings_edit_mut()?.destination = destination.as_ref().to_owned();
+        Ok(())
+    }
+
+    fn update_split_settings(&mut self, settings: &SplitSettings) -> Result<(), Error> {
+        *self.get_split_settings_edit_mut()? = settings.clone();
+        Ok(())
+    }
+
+    fn end_split_sheet(&mut self) -> Result<(), Error> {
+        self.get_split_settings_edit_mut()?;
+        self.persistent.split_settings_edit = None;
+        Ok(())
+    }
+
+    fn get_folder_import_settings_edit_mut(&mut self) -> Result<&mut FolderImportSettings, Error> {
+        self.persistent
+            .folder_import_settings_edit
+            .as_mut()
+            .ok_or(StateError::NotImportingFolder.into())
+    }
+
+    fn begin_import_folder(&mut self) {
+        if self.persistent.folder_import_settings_edit.is_none() {
+            self.persistent.folder_import_settings_edit = Some(FolderImportSettings::new());
+        }
+    }
+
+    fn cancel_import_folder(&mut self) {
+        self.persistent.folder_import_settings_edit = None;
+    }
+
+    fn end_set_import_folder<T: AsRef<Path>>(&mut self, folder: T) -> Result<(), Error> {
+        self.get_folder_import_settings_edit_mut()?.folder = folder.as_ref().to_owned();
+        Ok(())
+    }
+
+    fn update_folder_import_settings(
+        &mut self,
+        settings: &FolderImportSettings,
+    ) -> Result<(), Error> {
+        *self.get_folder_import_settings_edit_mut()? = settings.clone();
+        Ok(())
+    }
+
+    fn end_import_folder(&mut self) -> Result<(), Error> {
+        self.get_folder_import_settings_edit_mut()?;
+        self.persistent.folder_import_settings_edit = None;
+        Ok(())
+    }
+
+    fn end_relocate_frame<T: AsRef<Path>>(
+        &mut self,
+        frame: FrameId,
+        new_source: T,
+    ) -> Result<(), Error> {
+        let source = self
+            .sheet
+            .get_frame(frame)
+            .ok_or(StateError::FrameNotInDocument)?
+            .get_source()
+            .to_owned();
+        self.sheet
+            .relocate_frames(&[(source, new_source.as_ref().to_owned())])
+    }
+
+    fn end_relocate_directory<T: AsRef<Path>, U: AsRef<Path>>(
+        &mut self,
+        directory: T,
+        new_directory: U,
+    ) -> Result<(), Error> {
+        let moves: Vec<(PathBuf, PathBuf)> = self
+            .sheet
+            .frames_iter()
+            .map(|f| f.get_source())
+            .filter(|p| p.parent() == Some(directory.as_ref()))
+            .filter_map(|p| {
+                p.file_name()
+                    .map(|n| (p.to_owned(), new_directory.as_ref().join(n)))
+            })
+            .collect();
+        self.sheet.relocate_frames(&moves)
+    }
+
+    fn end_replace_frame_image(
+        &mut self,
+        replacement: &ImageReplacement,
+        rescale_hitboxes: bool,
+    ) -> Result<(), Error> {
+        self.persistent.image_replacement = None;
+        self.end_relocate_frame(replacement.frame, &replacement.new_source)?;
+        if rescale_hitboxes {
+            self.sheet
+                .get_frame_mut(replacement.frame)
+                .ok_or(StateError::FrameNotInDocument)?
+                .scale_hitboxes(replacement.old_size, replacement.new_size);
+        }
+        Ok(())
+    }
+
+    fn end_import_batch(&mut self, batch: &ImportBatch) -> Result<(), Error> {
+        batch.apply(&mut self.sheet)
+    }
+
     pub fn process_command(&mut self, command: &DocumentCommand) -> Result<(), Error> {
         use DocumentCommand::*;
 
         let mut new_document = self.clone();
 
         match command {
-            MarkAsSaved(_, v) => new_document.persistent.disk_version = *v,
-            EndImport(_, f) => new_document.sheet.add_frame(f),
+            MarkAsSaved(_, v, m) => {
+                new_document.persistent.disk_version = *v;
+                new_document.persistent.disk_modified = Some(*m);
+                new_document.persistent.autosave_version = None;
+            }
+            MarkAsAutosaved(_, v) => new_document.persistent.autosave_version = Some(*v),
+            RestoreSheet(_, s) => new_document.replace_sheet(s),
+            OfferReload(_, s, m) => new_document.offer_reload(s, *m),
+            ReloadFromDisk(_) => new_document.reload_from_disk()?,
+            KeepLocalChanges(_) => new_document.keep_local_changes()?,
+            EndImport(_, f) => {
+                new_document.sheet.add_frame(f);
+            }
+            EndImportBatch(_, b) => new_document.end_import_batch(b)?,
+            EndRelocateFrame(_, f, s) => new_document.end_relocate_frame(*f, s)?,
+            EndRelocateDirectory(_, d, n) => new_document.end_relocate_directory(d, n)?,
+            BeginRescaleHitboxes(_, r) => {
+                new_document.persistent.image_replacement = Some(r.clone())
+            }
+            CancelReplaceFrameImage => new_document.persistent.image_replacement = None,
+            EndReplaceFrameImage(_, r, s) => new_document.end_replace_frame_image(r, *s)?,
+            BeginSplitSheet => new_document.begin_split_sheet(),
+            EndSetSplitSource(_, s) => new_document.end_set_split_source(s)?,
+            EndSetSplitDestination(_, d) => new_document.end_set_split_destination(d)?,
+            UpdateSplitSettings(s) => new_document.update_split_settings(s)?,
+            CancelSplitSheet => new_document.cancel_split_sheet(),
+            EndSplitSheet => new_document.end_split_sheet()?,
+            BeginImportFolder => new_document.begin_import_folder(),
+            EndSetImportFolder(_, f) => new_document.end_set_import_folder(f)?,
+            UpdateFolderImportSettings(s) => new_document.update_folder_import_settings(s)?,
+            CancelImportFolder => new_document.cancel_import_folder(),
+            EndImportFolder => new_document.end_import_folder()?,
+            EndValidate(_, p) => new_document.persistent.problems = Some(p.clone()),
+            CloseProblems => new_document.persistent.problems = None,
             BeginExportAs => new_document.begin_export_as(),
             CancelExportAs => new_document.cancel_export_as(),
             EndSetExportTextureDestination(_, d) => {
@@ -1206,19 +1825,22 @@ impl Document {
             EndExportAs => new_document.end_export_as()?,
             SwitchToContentTab(t) => new_document.view.content_tab = *t,
             ClearSelection => new_document.clear_selection(),
-            SelectFrame(p) => new_document.select_frame(&p)?,
-            SelectAnimation(a) => new_document.select_animation(&a)?,
-            SelectHitbox(h) => new_document.select_hitbox(&h)?,
+            SelectFrame(f) => new_document.select_frame(*f)?,
+            SelectAnimation(a) => new_document.select_animation(*a)?,
+            SelectHitbox(h) => new_document.select_hitbox(*h)?,
             SelectAnimationFrame(af) => new_document.select_animation_frame(*af)?,
+            ToggleSelection(i) => new_document.toggle_selection(i)?,
+            SelectRange(i) => new_document.select_range(i)?,
+            SelectHitboxesInRectangle(a, b) => new_document.select_hitboxes_in_rectangle(*a, *b)?,
             SelectPrevious => new_document.select_previous()?,
             SelectNext => new_document.select_next()?,
-            EditFrame(p) => new_document.edit_frame(&p)?,
-            EditAnimation(a) => new_document.edit_animation(&a)?,
+            EditFrame(f) => new_document.edit_frame(*f)?,
+            EditAnimation(a) => new_document.edit_animation(*a)?,
             CreateAnimation => new_document.create_animation()?,
-            BeginFrameDrag(f) => new_document.begin_frame_drag(f)?,
+            BeginFrameDrag(f) => new_document.begin_frame_drag(*f)?,
             EndFrameDrag => new_document.transient.content_frame_being_dragged = None,
             InsertAnimationFrameBefore(f, n) => {
-                new_document.insert_animation_frame_before(f, *n)?
+                new_document.insert_animation_frame_before(*f, *n)?
             }
             ReorderAnimationFrame(a, b) => new_document.reorder_animation_frame(*a, *b)?,
             BeginAnimationFrameDurationDrag(a) => {
@@ -1243,10 +1865,10 @@ impl Document {
             WorkbenchCenter => new_document.view.workbench_center(),
             Pan(delta) => new_document.view.pan(*delta),
             CreateHitbox(p) => new_document.create_hitbox(*p)?,
-            BeginHitboxScale(h, a) => new_document.begin_hitbox_scale(&h, *a)?,
+            BeginHitboxScale(h, a) => new_document.begin_hitbox_scale(*h, *a)?,
             UpdateHitboxScale(delta, ar) => new_document.update_hitbox_scale(*delta, *ar)?,
             EndHitboxScale => new_document.end_hitbox_scale()?,
-            BeginHitboxDrag(a) => new_document.begin_hitbox_drag(&a)?,
+            BeginHitboxDrag(h) => new_document.begin_hitbox_drag(*h)?,
             UpdateHitboxDrag(delta, b) => new_document.update_hitbox_drag(*delta, *b)?,
             EndHitboxDrag => new_document.end_hitbox_drag(),
             TogglePlayback => new_document.toggle_playback()?,
@@ -1264,10 +1886,284 @@ impl Document {
             BeginRenameSelection => new_document.begin_rename_selection()?,
             UpdateRenameSelection(n) => new_document.transient.rename_buffer = Some(n.to_owned()),
             EndRenameSelection => new_document.end_rename_selection()?,
+            EndCut(_, i) => new_document.delete_items(i.clone()),
+            EndPaste(_, c) => new_document.end_paste(c)?,
+            DuplicateSelection => new_document.duplicate_selection()?,
+            EndDuplicateFrames(_, f) => new_document.end_duplicate_frames(f)?,
         };
 
         self.record_command(command, new_document);
 
+        // The reloaded sheet is the one on disk
+        if let ReloadFromDisk(_) = command {
+            self.persistent.disk_version = self.get_version();
+        }
+
         Ok(())
     }
 }
+
+#[test]
+fn test_multiple_selection() {
+    let mut document = Document::new("test.tiger");
+    let walk_0 = document.sheet.add_frame("walk_0.png");
+    let walk_1 = document.sheet.add_frame("walk_1.png");
+    let animation = document.sheet.add_animation_with_name("walk");
+    for index in 0..4 {
+        let frame = if index % 2 == 0 { walk_0 } else { walk_1 };
+        animation.insert_frame(frame, index).unwrap();
+    }
+    let animation_id = animation.get_id();
+    document.edit_animation(animation_id).unwrap();
+
+    let keyframe = |i| SelectedItem::AnimationFrame(animation_id, i);
+    document
+        .process_command(&DocumentCommand::SelectAnimationFrame(0))
+        .unwrap();
+    document
+        .process_command(&DocumentCommand::SelectRange(keyframe(2)))
+        .unwrap();
+    document
+        .process_command(&DocumentCommand::ToggleSelection(keyframe(1)))
+        .unwrap();
+    assert_eq!(document.view.selection.len(), 2);
+
+    document
+        .process_command(&DocumentCommand::BeginAnimationFrameDurationDrag(2))
+        .unwrap();
+    document
+        .process_command(&DocumentCommand::UpdateAnimationFrameDurationDrag(150))
+        .unwrap();
+    document
+        .process_command(&DocumentCommand::EndAnimationFrameDurationDrag)
+        .unwrap();
+    let durations = |document: &Document| -> Vec<u32> {
+        let animation = document.sheet.get_animation(animation_id).unwrap();
+        animation.frames_iter().map(|f| f.get_duration()).collect()
+    };
+    assert_eq!(durations(&document), vec![150, 100, 150, 100]);
+
+    document
+        .process_command(&DocumentCommand::DeleteSelection)
+        .unwrap();
+    assert_eq!(durations(&document), vec![100, 100]);
+    assert!(document.view.selection.is_empty());
+
+    document.undo().unwrap();
+    assert_eq!(durations(&document), vec![150, 100, 150, 100]);
+
+    // Cut items are deleted even if the selection changed since
+    document
+        .process_command(&DocumentCommand::ClearSelection)
+        .unwrap();
+    document
+        .process_command(&DocumentCommand::EndCut(
+            "test.tiger".into(),
+            vec![keyframe(0), keyframe(2)],
+        ))
+        .unwrap();
+    assert_eq!(durations(&document), vec![100, 100]);
+}
+
+#[test]
+fn test_history_limit() {
+    let mut document = Document::new("test.tiger");
+    document.set_history_limit(10);
+    let frame = document.sheet.add_frame("walk_0.png");
+    for index in 0..20 {
+        document
+            .process_command(&DocumentCommand::EndImport(
+                "test.tiger".into(),
+                format!("walk_{}.png", index + 1).into(),
+            ))
+            .unwrap();
+    }
+    assert_eq!(document.history.len(), 10);
+    assert_eq!(document.sheet.frames_iter().count(), 21);
+    assert!(Arc::ptr_eq(
+        &document.history[0].sheet.frames[0],
+        &document.history[9].sheet.frames[0]
+    ));
+    assert_eq!(document.history[9].sheet.frames[0].get_id(), frame);
+
+    let version = document.get_version();
+    document
+        .process_command(&DocumentCommand::MarkAsSaved(
+            "test.tiger".into(),
+            version,
+            SystemTime::now(),
+        ))
+        .unwrap();
+    for _ in 0..20 {
+        document.undo().unwrap();
+    }
+    assert_eq!(document.sheet.frames_iter().count(), 12);
+    assert!(!document.is_saved());
+    for _ in 0..20 {
+        document.redo().unwrap();
+    }
+    assert!(document.is_saved());
+
+    document.undo().unwrap();
+    document.set_history_limit(5);
+    assert_eq!(document.history.len(), 5);
+    assert_eq!(document.sheet.frames_iter().count(), 20);
+    document.redo().unwrap();
+    assert!(document.is_saved());
+}
+
+#[test]
+fn test_jump_to_history() {
+    let mut document = Document::new("test.tiger");
+    for index in 0..3 {
+        document
+            .process_command(&DocumentCommand::EndImport(
+                "test.tiger".into(),
+                format!("walk_{}.png", index).into(),
+            ))
+            .unwrap();
+    }
+    let labels: Vec<Option<String>> = document
+        .history_iter()
+        .map(|(c, _)| c.map(|c| c.to_string()))
+        .collect();
+    assert_eq!(labels.len(), 4);
+    assert_eq!(labels[0], None);
+    assert_eq!(labels[3], Some("Import Image".to_owned()));
+
+    document.jump_to_history(1).unwrap();
+    assert_eq!(document.get_history_index(), 1);
+    assert_eq!(document.sheet.frames_iter().count(), 1);
+    document.jump_to_history(3).unwrap();
+    assert_eq!(document.sheet.frames_iter().count(), 3);
+    assert!(document.jump_to_history(4).is_err());
+}
+
+#[test]
+fn test_restore_sheet() {
+    let mut document = Document::new("test.tiger");
+    assert!(!document.needs_autosave());
+
+    let mut recovered_sheet = Sheet::default();
+    recovered_sheet.add_frame("walk_0.png");
+    document
+        .process_command(&DocumentCommand::RestoreSheet(
+            "test.tiger".into(),
+            recovered_sheet.clone(),
+        ))
+        .unwrap();
+    assert_eq!(document.sheet, recovered_sheet);
+    assert!(document.needs_autosave());
+
+    let version = document.get_version();
+    document
+        .process_command(&DocumentCommand::MarkAsAutosaved(
+            "test.tiger".into(),
+            version,
+        ))
+        .unwrap();
+    assert!(!document.needs_autosave());
+
+    document.undo().unwrap();
+    assert_eq!(document.sheet, Sheet::default());
+}
+
+#[test]
+fn test_reload_from_disk() {
+    let mut document = Document::new("test.tiger");
+    let modified = SystemTime::now();
+    document.persistent.disk_modified = Some(modified);
+
+    let mut disk_sheet = Sheet::default();
+    disk_sheet.add_frame("walk_0.png");
+    let new_modified = modified + Duration::from_secs(1);
+    document
+        .process_command(&DocumentCommand::OfferReload(
+            "test.tiger".into(),
+            disk_sheet.clone(),
+            new_modified,
+        ))
+        .unwrap();
+    let external_change = document.persistent.external_change.clone().unwrap();
+    assert_eq!(
+        external_change.changes,
+        vec![SheetChange::FrameAdded("walk_0.png".into())]
+    );
+
+    document
+        .process_command(&DocumentCommand::ReloadFromDisk("test.tiger".into()))
+        .unwrap();
+    assert_eq!(document.sheet, disk_sheet);
+    assert!(document.persistent.external_change.is_none());
+    assert_eq!(document.get_disk_modified(), Some(new_modified));
+    assert!(document.is_saved());
+
+    document
+        .process_command(&DocumentCommand::OfferReload(
+            "test.tiger".into(),
+            Sheet::default(),
+            new_modified,
+        ))
+        .unwrap();
+    assert!(document.persistent.external_change.is_none());
+
+    document.undo().unwrap();
+    assert_eq!(document.sheet, Sheet::default());
+    assert!(!document.is_saved());
+
+    // Saved files are sorted, which is not a change
+    document.redo().unwrap();
+    document
+        .process_command(&DocumentCommand::EndImport(
+            "test.tiger".into(),
+            "idle.png".into(),
+        ))
+        .unwrap();
+    document.persistent.disk_version = document.get_version();
+    let sorted_modified = new_modified + Duration::from_secs(1);
+    document
+        .process_command(&DocumentCommand::OfferReload(
+            "test.tiger".into(),
+            document.sheet.with_canonical_order(),
+            sorted_modified,
+        ))
+        .unwrap();
+    assert_ne!(document.sheet, document.sheet.with_canonical_order());
+    assert!(document.persistent.external_change.is_none());
+    assert_eq!(document.get_disk_modified(), Some(sorted_modified));
+}
+
+#[test]
+fn test_selection_spanning_animations() {
+    let mut document = Document::new("test.tiger");
+    let frame = document.sheet.add_frame("walk_0.png");
+    let mut animation_ids = Vec::new();
+    for name in &["walk", "run"] {
+        let animation = document.sheet.add_animation_with_name(name);
+        for index in 0..2 {
+            animation.insert_frame(frame, index).unwrap();
+        }
+        animation_ids.push(animation.get_id());
+    }
+    let (walk, run) = (animation_ids[0], animation_ids[1]);
+    document
+        .view
+        .selection
+        .set(SelectedItem::AnimationFrame(walk, 0));
+    document
+        .view
+        .selection
+        .add(SelectedItem::AnimationFrame(walk, 1));
+    *document.view.selection.items_iter_mut().last().unwrap() =
+        SelectedItem::AnimationFrame(run, 1);
+
+    match document.copy_selection().unwrap() {
+        Some(ClipboardContent::Keyframes(k)) => assert_eq!(k.len(), 1),
+        c => panic!("Unexpected clipboard content {:?}", c),
+    }
+
+    document.duplicate_selection().unwrap();
+    let num_frames = |a| document.sheet.get_animation(a).unwrap().get_num_frames();
+    assert_eq!(num_frames(walk), 3);
+    assert_eq!(num_frames(run), 2);
+}
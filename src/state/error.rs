@@ -4,10 +4,20 @@ pub enum StateError {
     NoDocumentOpen,
     #[fail(display = "Requested document was not found")]
     DocumentNotFound,
+    #[fail(display = "No project is open")]
+    NoProjectOpen,
+    #[fail(display = "Requested recovered sheet was not found")]
+    RecoveredSheetNotFound,
+    #[fail(display = "Sheet has not changed on disk")]
+    NoExternalChange,
+    #[fail(display = "Selected item is not part of the document")]
+    SelectedItemNotInDocument,
     #[fail(display = "Sheet has no export settings")]
     NoExistingExportSettings,
     #[fail(display = "Cannot perform undo operation")]
     UndoOperationNowAllowed,
+    #[fail(display = "Requested history entry does not exist")]
+    InvalidHistoryIndex,
     #[fail(display = "Requested frame is not in document")]
     FrameNotInDocument,
     #[fail(display = "Requested animation is not in document")]
@@ -36,4 +46,10 @@ pub enum StateError {
     NotExporting,
     #[fail(display = "Not currently renaming an item")]
     NotRenaming,
+    #[fail(display = "Not currently adjusting sheet splitting settings")]
+    NotSplittingSheet,
+    #[fail(display = "Not currently adjusting folder import settings")]
+    NotImportingFolder,
+    #[fail(display = "Could not access the clipboard")]
+    ClipboardUnavailable,
 }
@@ -1,11 +1,12 @@
 use euclid::*;
-use std::path::PathBuf;
 use std::time::Duration;
 
+use crate::sheet::{AnimationId, FrameId, HitboxId};
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum RenameItem {
-    Animation(String),
-    Hitbox(PathBuf, String),
+    Animation(AnimationId),
+    Hitbox(FrameId, HitboxId),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -37,19 +38,19 @@ impl ResizeAxis {
 // Reset when focusing different document
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Transient {
-    pub content_frame_being_dragged: Option<PathBuf>,
+    pub content_frame_being_dragged: Option<FrameId>,
     pub item_being_renamed: Option<RenameItem>,
     pub rename_buffer: Option<String>,
-    pub workbench_hitbox_being_dragged: Option<String>,
+    pub workbench_hitbox_being_dragged: Option<HitboxId>,
     pub workbench_hitbox_drag_initial_offset: Vector2D<i32>,
-    pub workbench_hitbox_being_scaled: Option<String>,
+    pub workbench_hitbox_being_scaled: Option<HitboxId>,
     pub workbench_hitbox_scale_axis: ResizeAxis,
     pub workbench_hitbox_scale_initial_position: Vector2D<i32>,
     pub workbench_hitbox_scale_initial_size: Vector2D<u32>,
     pub workbench_animation_frame_being_dragged: Option<usize>,
     pub workbench_animation_frame_drag_initial_offset: Vector2D<i32>,
     pub timeline_frame_being_scaled: Option<usize>,
-    pub timeline_frame_scale_initial_duration: u32,
+    pub timeline_frame_scale_initial_durations: Vec<(usize, u32)>,
     pub timeline_frame_scale_initial_clock: Duration,
     pub timeline_frame_being_dragged: Option<usize>,
     pub timeline_scrubbing: bool,
@@ -1,13 +1,90 @@
 use euclid::*;
-use std::path::PathBuf;
 use std::time::Duration;
 
+use crate::sheet::{AnimationId, FrameId, HitboxId};
+
 #[derive(Clone, Debug, PartialEq)]
-pub enum Selection {
-    Frame(PathBuf),
-    Animation(String),
-    Hitbox(PathBuf, String),
-    AnimationFrame(String, usize),
+pub enum SelectedItem {
+    Frame(FrameId),
+    Animation(AnimationId),
+    Hitbox(FrameId, HitboxId),
+    AnimationFrame(AnimationId, usize),
+}
+
+impl SelectedItem {
+    // Items selected together are of the same kind and belong to the same frame or animation
+    fn can_select_with(&self, other: &SelectedItem) -> bool {
+        use SelectedItem::*;
+        match (self, other) {
+            (Frame(_), Frame(_)) | (Animation(_), Animation(_)) => true,
+            (Hitbox(f1, _), Hitbox(f2, _)) => f1 == f2,
+            (AnimationFrame(a1, _), AnimationFrame(a2, _)) => a1 == a2,
+            _ => false,
+        }
+    }
+}
+
+// Items are kept in the order they were selected, range selections start from the last one
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Selection {
+    items: Vec<SelectedItem>,
+}
+
+impl Selection {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn contains(&self, item: &SelectedItem) -> bool {
+        self.items.contains(item)
+    }
+
+    pub fn items_iter(&self) -> std::slice::Iter<'_, SelectedItem> {
+        self.items.iter()
+    }
+
+    pub fn items_iter_mut(&mut self) -> std::slice::IterMut<'_, SelectedItem> {
+        self.items.iter_mut()
+    }
+
+    pub fn get_last(&self) -> Option<&SelectedItem> {
+        self.items.last()
+    }
+
+    pub fn get_single(&self) -> Option<&SelectedItem> {
+        match self.items.as_slice() {
+            [item] => Some(item),
+            _ => None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn set(&mut self, item: SelectedItem) {
+        self.items = vec![item];
+    }
+
+    pub fn add(&mut self, item: SelectedItem) {
+        if !self.items.iter().all(|i| i.can_select_with(&item)) {
+            self.items.clear();
+        }
+        self.items.retain(|i| *i != item);
+        self.items.push(item);
+    }
+
+    pub fn toggle(&mut self, item: SelectedItem) {
+        if self.contains(&item) {
+            self.items.retain(|i| *i != item);
+        } else {
+            self.add(item);
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -18,14 +95,14 @@ pub enum ContentTab {
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum WorkbenchItem {
-    Frame(PathBuf),
-    Animation(String),
+    Frame(FrameId),
+    Animation(AnimationId),
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct View {
     pub content_tab: ContentTab,
-    pub selection: Option<Selection>,
+    pub selection: Selection,
     pub workbench_item: Option<WorkbenchItem>,
     pub workbench_offset: Vector2D<f32>,
     pub timeline_clock: Duration,
@@ -37,7 +114,7 @@ impl Default for View {
     fn default() -> View {
         View {
             content_tab: ContentTab::Frames,
-            selection: None,
+            selection: Default::default(),
             workbench_item: None,
             workbench_offset: Vector2D::<f32>::zero(),
             workbench_zoom_level: 1,
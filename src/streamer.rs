@@ -10,14 +10,16 @@ use std::io::BufReader;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
 use crate::state::AppState;
 
 const MAX_TEXTURES_LOAD_TIME_PER_TICK: u128 = 250; // ms
+const MAX_RETAINED_PIXELS_SIZE: usize = 256 * 1024 * 1024; // bytes
 
 pub struct StreamerPayload {
     queued_textures: HashSet<PathBuf>,
-    new_textures: HashMap<PathBuf, image::ImageBuffer<image::Rgba<u8>, Vec<u8>>>,
+    new_textures: HashMap<PathBuf, (Arc<image::RgbaImage>, Option<SystemTime>)>,
     errored_textures: HashSet<PathBuf>,
     obsolete_textures: HashSet<PathBuf>,
 }
@@ -66,8 +68,9 @@ pub fn load_from_disk(
         if io_time.as_millis() < MAX_TEXTURES_LOAD_TIME_PER_TICK {
             let start = std::time::Instant::now();
             if let Ok(file) = File::open(&path) {
+                let modified = file.metadata().and_then(|m| m.modified()).ok();
                 if let Ok(image) = image::load(BufReader::new(file), image::PNG) {
-                    new_textures.insert(path.clone(), image.to_rgba());
+                    new_textures.insert(path.clone(), (Arc::new(image.to_rgba()), modified));
                 };
             } else {
                 // TODO Log
@@ -107,7 +110,7 @@ pub fn upload(
     receiver: &Receiver<StreamerPayload>,
 ) {
     if let Ok(payload) = receiver.try_recv() {
-        for (path, texture_data) in payload.new_textures {
+        for (path, (texture_data, modified)) in payload.new_textures {
             let sampler =
                 factory.create_sampler(SamplerInfo::new(FilterMethod::Scale, WrapMode::Clamp));
             let size: Vector2D<u32> = texture_data.dimensions().into();
@@ -119,7 +122,7 @@ pub fn upload(
                 &[&texture_data],
             ) {
                 let id = renderer.textures().insert((texture, sampler));
-                texture_cache.insert_entry(path, id, size);
+                texture_cache.insert_entry(path, id, size, texture_data, modified);
             } else {
                 texture_cache.insert_error(path);
             }
@@ -143,6 +146,8 @@ pub fn upload(
 struct TextureCacheImage {
     pub id: ImTexture,
     pub size: Vector2D<u32>,
+    pub pixels: Option<Arc<image::RgbaImage>>,
+    pub modified: Option<SystemTime>,
     // TODO dirty flag and file watches
 }
 
@@ -179,14 +184,27 @@ impl From<&TextureCacheEntry> for TextureCacheResult {
     }
 }
 
+// Decoded images are retained for exports, up to a total size past which exports read from disk
 pub struct TextureCache {
     cache: HashMap<PathBuf, TextureCacheEntry>,
+    retained_pixels_size: usize,
 }
 
 impl TextureCache {
     pub fn new() -> TextureCache {
         TextureCache {
             cache: HashMap::new(),
+            retained_pixels_size: 0,
+        }
+    }
+
+    fn release_pixels<T: AsRef<Path>>(&mut self, path: T) {
+        if let Some(TextureCacheEntry::Loaded(TextureCacheImage {
+            pixels: Some(pixels),
+            ..
+        })) = self.cache.get(path.as_ref())
+        {
+            self.retained_pixels_size -= pixels.len();
         }
     }
 
@@ -198,14 +216,48 @@ impl TextureCache {
         self.cache.get(path.as_ref()).map(|e| e.into())
     }
 
-    pub fn insert_entry<T: AsRef<Path>>(&mut self, path: T, id: ImTexture, size: Vector2D<u32>) {
+    pub fn get_pixels<T: AsRef<Path>>(
+        &self,
+        path: T,
+    ) -> Option<(Arc<image::RgbaImage>, SystemTime)> {
+        match self.cache.get(path.as_ref()) {
+            Some(TextureCacheEntry::Loaded(TextureCacheImage {
+                pixels: Some(pixels),
+                modified: Some(modified),
+                ..
+            })) => Some((pixels.clone(), *modified)),
+            _ => None,
+        }
+    }
+
+    pub fn insert_entry<T: AsRef<Path>>(
+        &mut self,
+        path: T,
+        id: ImTexture,
+        size: Vector2D<u32>,
+        pixels: Arc<image::RgbaImage>,
+        modified: Option<SystemTime>,
+    ) {
+        self.release_pixels(&path);
+        let pixels = if self.retained_pixels_size + pixels.len() <= MAX_RETAINED_PIXELS_SIZE {
+            self.retained_pixels_size += pixels.len();
+            Some(pixels)
+        } else {
+            None
+        };
         self.cache.insert(
             path.as_ref().to_owned(),
-            TextureCacheEntry::Loaded(TextureCacheImage { id, size }),
+            TextureCacheEntry::Loaded(TextureCacheImage {
+                id,
+                size,
+                pixels,
+                modified,
+            }),
         );
     }
 
     pub fn insert_error<T: AsRef<Path>>(&mut self, path: T) {
+        self.release_pixels(&path);
         self.cache
             .insert(path.as_ref().to_owned(), TextureCacheEntry::Missing);
     }
@@ -218,6 +270,7 @@ impl TextureCache {
     }
 
     pub fn remove<T: AsRef<Path>>(&mut self, path: T) {
+        self.release_pixels(&path);
         self.cache.remove(path.as_ref());
     }
 }
@@ -4,7 +4,8 @@ use std::ffi::OsStr;
 
 use crate::sheet::{Animation, Frame};
 use crate::state::*;
-use crate::ui::Rect;
+use crate::streamer::{TextureCache, TextureCacheResult};
+use crate::ui::{extend_selection, Rect};
 
 fn draw_tabs<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer) {
     if ui.small_button(im_str!("Frames")) {
@@ -16,10 +17,58 @@ fn draw_tabs<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer) {
     }
 }
 
-fn draw_frames<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, document: &Document) {
+fn is_missing(texture_cache: &TextureCache, frame: &Frame) -> bool {
+    match texture_cache.get(frame.get_source()) {
+        Some(TextureCacheResult::Missing) => true,
+        _ => false,
+    }
+}
+
+fn draw_frame_buttons<'a>(
+    ui: &Ui<'a>,
+    commands: &mut CommandBuffer,
+    document: &Document,
+    texture_cache: &TextureCache,
+) {
+    let frame = match document.view.selection.get_single() {
+        Some(SelectedItem::Frame(f)) => document.sheet.get_frame(*f),
+        _ => None,
+    };
+    if let Some(frame) = frame {
+        if is_missing(texture_cache, frame) {
+            if ui.small_button(im_str!("Relocate…")) {
+                commands.begin_relocate_frame(document, frame);
+            }
+            if let Some(directory) = frame.get_source().parent() {
+                ui.same_line(0.0);
+                if ui.small_button(im_str!("Relocate Folder…")) {
+                    commands.begin_relocate_directory(document, directory);
+                }
+            }
+        } else if ui.small_button(im_str!("Replace Image…")) {
+            commands.begin_replace_frame_image(document, frame);
+        }
+    }
+}
+
+fn draw_frames<'a>(
+    ui: &Ui<'a>,
+    commands: &mut CommandBuffer,
+    document: &Document,
+    texture_cache: &TextureCache,
+) {
     if ui.small_button(im_str!("Import…")) {
         commands.import(document);
     }
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Split Sheet…")) {
+        commands.begin_split_sheet();
+    }
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Import Folder…")) {
+        commands.begin_import_folder();
+    }
+    draw_frame_buttons(ui, commands, document, texture_cache);
     let mut frames: Vec<(&OsStr, &Frame)> = document
         .sheet
         .frames_iter()
@@ -33,22 +82,26 @@ fn draw_frames<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, document: &Documen
         .collect();
     frames.sort_unstable();
     for (name, frame) in frames.iter() {
-        let is_selected = match &document.view.selection {
-            Some(Selection::Frame(p)) => p == frame.get_source(),
-            _ => false,
+        let item = SelectedItem::Frame(frame.get_id());
+        let is_selected = document.view.selection.contains(&item);
+
+        let label = if is_missing(texture_cache, frame) {
+            format!("{} (missing)", name.to_string_lossy())
+        } else {
+            name.to_string_lossy().into_owned()
         };
 
         let mut flags = ImGuiSelectableFlags::empty();
         flags.set(ImGuiSelectableFlags::AllowDoubleClick, true);
         if ui.selectable(
-            &ImString::new(name.to_string_lossy()),
+            &ImString::new(label),
             is_selected,
             flags,
             ImVec2::new(0.0, 0.0),
         ) {
             if ui.imgui().is_mouse_double_clicked(ImMouseButton::Left) {
                 commands.edit_frame(frame);
-            } else {
+            } else if !extend_selection(ui, commands, item) {
                 commands.select_frame(frame);
             }
         }
@@ -69,10 +122,8 @@ fn draw_animations<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, document: &Doc
     let mut animations: Vec<&Animation> = document.sheet.animations_iter().collect();
     animations.sort_unstable();
     for animation in animations.iter() {
-        let is_selected = match &document.view.selection {
-            Some(Selection::Animation(a)) => a == animation.get_name(),
-            _ => false,
-        };
+        let item = SelectedItem::Animation(animation.get_id());
+        let is_selected = document.view.selection.contains(&item);
         let mut flags = ImGuiSelectableFlags::empty();
         flags.set(ImGuiSelectableFlags::AllowDoubleClick, true);
         if ui.selectable(
@@ -83,14 +134,20 @@ fn draw_animations<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, document: &Doc
         ) {
             if ui.imgui().is_mouse_double_clicked(ImMouseButton::Left) {
                 commands.edit_animation(animation);
-            } else {
+            } else if !extend_selection(ui, commands, item) {
                 commands.select_animation(animation);
             }
         }
     }
 }
 
-pub fn draw<'a>(ui: &Ui<'a>, rect: &Rect<f32>, app_state: &AppState, commands: &mut CommandBuffer) {
+pub fn draw<'a>(
+    ui: &Ui<'a>,
+    rect: &Rect<f32>,
+    app_state: &AppState,
+    commands: &mut CommandBuffer,
+    texture_cache: &TextureCache,
+) {
     ui.with_style_vars(&[WindowRounding(0.0), WindowBorderSize(0.0)], || {
         ui.window(im_str!("Content"))
             .position(rect.origin.to_tuple(), ImGuiCond::Always)
@@ -104,7 +161,7 @@ pub fn draw<'a>(ui: &Ui<'a>, rect: &Rect<f32>, app_state: &AppState, commands: &
                     draw_tabs(ui, commands);
                     ui.separator();
                     match document.view.content_tab {
-                        ContentTab::Frames => draw_frames(ui, commands, document),
+                        ContentTab::Frames => draw_frames(ui, commands, document, texture_cache),
                         ContentTab::Animations => draw_animations(ui, commands, document),
                     }
                 }
@@ -0,0 +1,59 @@
+use imgui::StyleVar::*;
+use imgui::*;
+
+use crate::state::*;
+use crate::ui::Rect;
+
+// Several entries can share the saved version, only the one nearest the current entry is marked
+fn get_saved_index(document: &Document) -> Option<usize> {
+    let disk_version = document.get_disk_version();
+    let current_index = document.get_history_index();
+    document
+        .history_iter()
+        .enumerate()
+        .filter(|(_, (_, version))| *version == disk_version)
+        .map(|(index, _)| index)
+        .min_by_key(|index| (*index as isize - current_index as isize).abs())
+}
+
+fn draw_history<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, document: &Document) {
+    let saved_index = get_saved_index(document);
+    for (index, (command, _)) in document.history_iter().enumerate() {
+        let mut label = match command {
+            Some(c) => c.to_string(),
+            None => "Initial State".to_owned(),
+        };
+        if Some(index) == saved_index {
+            label += " [Saved]";
+        }
+        let is_current = index == document.get_history_index();
+
+        ui.push_id(index as i32);
+        if ui.selectable(
+            &ImString::new(label),
+            is_current,
+            ImGuiSelectableFlags::empty(),
+            ImVec2::new(0.0, 0.0),
+        ) && !is_current
+        {
+            commands.jump_to_history(index);
+        }
+        ui.pop_id();
+    }
+}
+
+pub fn draw<'a>(ui: &Ui<'a>, rect: &Rect<f32>, app_state: &AppState, commands: &mut CommandBuffer) {
+    ui.with_style_vars(&[WindowRounding(0.0), WindowBorderSize(0.0)], || {
+        ui.window(im_str!("History"))
+            .position(rect.origin.to_tuple(), ImGuiCond::Always)
+            .size(rect.size.to_tuple(), ImGuiCond::Always)
+            .collapsible(false)
+            .resizable(false)
+            .movable(false)
+            .build(|| {
+                if let Some(document) = app_state.get_current_document() {
+                    draw_history(ui, commands, document);
+                }
+            });
+    });
+}
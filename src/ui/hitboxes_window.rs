@@ -3,7 +3,7 @@ use imgui::*;
 
 use crate::sheet::{Frame, Hitbox};
 use crate::state::*;
-use crate::ui::Rect;
+use crate::ui::{extend_selection, Rect};
 
 fn draw_hitboxes<'a>(
     ui: &Ui<'a>,
@@ -14,10 +14,8 @@ fn draw_hitboxes<'a>(
     let mut hitboxes: Vec<&Hitbox> = frame.hitboxes_iter().collect();
     hitboxes.sort_unstable();
     for hitbox in hitboxes.iter() {
-        let is_selected = match &document.view.selection {
-            Some(Selection::Hitbox(p, n)) => p == frame.get_source() && n == hitbox.get_name(),
-            _ => false,
-        };
+        let item = SelectedItem::Hitbox(frame.get_id(), hitbox.get_id());
+        let is_selected = document.view.selection.contains(&item);
 
         let flags = ImGuiSelectableFlags::empty();
         if ui.selectable(
@@ -25,7 +23,8 @@ fn draw_hitboxes<'a>(
             is_selected,
             flags,
             ImVec2::new(0.0, 0.0),
-        ) {
+        ) && !extend_selection(ui, commands, item)
+        {
             commands.select_hitbox(hitbox);
         }
     }
@@ -41,8 +40,8 @@ pub fn draw<'a>(ui: &Ui<'a>, rect: &Rect<f32>, app_state: &AppState, commands: &
             .movable(false)
             .build(|| {
                 if let Some(document) = app_state.get_current_document() {
-                    if let Some(WorkbenchItem::Frame(frame_path)) = &document.view.workbench_item {
-                        if let Some(frame) = document.sheet.get_frame(frame_path) {
+                    if let Some(WorkbenchItem::Frame(frame_id)) = &document.view.workbench_item {
+                        if let Some(frame) = document.sheet.get_frame(*frame_id) {
                             draw_hitboxes(ui, commands, document, frame);
                         }
                     }
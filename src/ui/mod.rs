@@ -5,6 +5,7 @@ use imgui::StyleVar::*;
 use imgui::*;
 use std::borrow::Borrow;
 
+use crate::import::SplitMode;
 use crate::sheet::constants::*;
 use crate::sheet::ExportFormat;
 use crate::state::*;
@@ -12,12 +13,17 @@ use crate::streamer::{TextureCache, TextureCacheResult};
 use crate::utils;
 
 mod content_window;
+mod history_window;
 mod hitboxes_window;
+mod problems_window;
 mod selection_window;
 mod spinner;
 mod timeline_window;
 mod workbench_window;
 
+// Undo steps kept per document, offered in the Edit menu
+const HISTORY_LIMIT_CHOICES: &[usize] = &[20, DEFAULT_HISTORY_LIMIT, 500];
+
 pub fn init(window: &glutin::Window) -> ImGui {
     let mut imgui_instance = ImGui::init();
     imgui_instance.set_ini_filename(None);
@@ -101,7 +107,15 @@ pub fn run<'a>(
 
     {
         let documents_rect = rect(content_width, menu_height, window_width, 0.0);
-        draw_documents_window(ui, &documents_rect, app_state, &mut commands);
+        let (_, documents_height) =
+            draw_documents_window(ui, &documents_rect, app_state, &mut commands);
+        let project_rect = rect(
+            content_width,
+            menu_height + documents_height,
+            window_width,
+            0.0,
+        );
+        draw_project_window(ui, &project_rect, app_state, &mut commands);
     }
 
     let panels_height = window_height - menu_height;
@@ -109,7 +123,7 @@ pub fn run<'a>(
 
     {
         let content_rect = rect(0.0, menu_height, content_width, content_height);
-        content_window::draw(ui, &content_rect, app_state, &mut commands);
+        content_window::draw(ui, &content_rect, app_state, &mut commands, texture_cache);
     }
 
     {
@@ -138,7 +152,7 @@ pub fn run<'a>(
     }
 
     {
-        let hitboxes_height = content_height;
+        let hitboxes_height = 0.5 * content_height;
         let hitboxes_rect = rect(
             window_width - hitboxes_width,
             menu_height,
@@ -146,11 +160,27 @@ pub fn run<'a>(
             hitboxes_height,
         );
         hitboxes_window::draw(ui, &hitboxes_rect, app_state, &mut commands);
+
+        let history_height = content_height - hitboxes_height;
+        let history_rect = rect(
+            window_width - hitboxes_width,
+            menu_height + hitboxes_height,
+            hitboxes_width,
+            history_height,
+        );
+        history_window::draw(ui, &history_rect, app_state, &mut commands);
     }
 
     draw_export_popup(ui, app_state, &mut commands);
+    draw_split_popup(ui, app_state, &mut commands);
+    draw_import_folder_popup(ui, app_state, &mut commands);
+    problems_window::draw(ui, app_state, &mut commands);
+    draw_rescale_hitboxes_popup(ui, app_state, &mut commands);
     draw_rename_popup(ui, app_state, &mut commands);
+    draw_external_change_popup(ui, app_state, &mut commands);
+    draw_recovery_popup(ui, app_state, &mut commands);
     draw_exit_popup(ui, app_state, &mut commands);
+    draw_error_popup(ui, app_state, &mut commands);
 
     update_drag_and_drop(ui, app_state, &mut commands);
     draw_drag_and_drop(ui, app_state, texture_cache);
@@ -159,6 +189,20 @@ pub fn run<'a>(
     Ok(commands)
 }
 
+// Ctrl-click adds or removes items from the selection, Shift-click selects a range of items.
+// Returns false for plain clicks, which callers handle by selecting only the clicked item.
+fn extend_selection<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, item: SelectedItem) -> bool {
+    if ui.imgui().key_ctrl() {
+        commands.toggle_selection(item);
+        true
+    } else if ui.imgui().key_shift() {
+        commands.select_range(item);
+        true
+    } else {
+        false
+    }
+}
+
 fn save_all(app_state: &AppState, commands: &mut CommandBuffer) {
     for document in app_state.documents_iter() {
         commands.save(&document.source, &document.sheet, document.get_version());
@@ -190,6 +234,9 @@ fn draw_main_menu<'a>(
                 {
                     commands.begin_open_document();
                 }
+                if ui.menu_item(im_str!("Import Aseprite Sheet…")).build() {
+                    commands.begin_import_aseprite();
+                }
                 ui.separator();
                 if ui
                     .menu_item(im_str!("Save"))
@@ -226,7 +273,7 @@ fn draw_main_menu<'a>(
                     .build()
                 {
                     if let Some(document) = app_state.get_current_document() {
-                        commands.export(&document.sheet);
+                        commands.export(app_state, document);
                     }
                 }
                 if ui
@@ -237,6 +284,15 @@ fn draw_main_menu<'a>(
                 {
                     commands.begin_export_as();
                 }
+                if ui
+                    .menu_item(im_str!("Check for Problems"))
+                    .enabled(has_document)
+                    .build()
+                {
+                    if let Some(document) = app_state.get_current_document() {
+                        commands.validate(document);
+                    }
+                }
                 ui.separator();
                 if ui
                     .menu_item(im_str!("Close"))
@@ -244,7 +300,7 @@ fn draw_main_menu<'a>(
                     .enabled(has_document)
                     .build()
                 {
-                    commands.close_current_document();
+                    commands.close_current_document(app_state);
                 }
                 if ui
                     .menu_item(im_str!("Close All"))
@@ -252,7 +308,70 @@ fn draw_main_menu<'a>(
                     .enabled(has_document)
                     .build()
                 {
-                    commands.close_all_documents();
+                    commands.close_all_documents(app_state);
+                }
+            });
+
+            ui.menu(im_str!("Project")).build(|| {
+                let project = app_state.get_project();
+                let document = app_state.get_current_document();
+                let is_in_project = match (project, document) {
+                    (Some(p), Some(d)) => Some(p.has_sheet(&d.source)),
+                    _ => None,
+                };
+                if ui.menu_item(im_str!("New Project…")).build() {
+                    commands.begin_new_project();
+                }
+                if ui.menu_item(im_str!("Open Project…")).build() {
+                    commands.begin_open_project();
+                }
+                ui.separator();
+                if ui
+                    .menu_item(im_str!("Add Sheet to Project"))
+                    .enabled(is_in_project == Some(false))
+                    .build()
+                {
+                    if let Some(document) = document {
+                        commands.add_sheet_to_project(document);
+                    }
+                }
+                if ui
+                    .menu_item(im_str!("Remove Sheet from Project"))
+                    .enabled(is_in_project == Some(true))
+                    .build()
+                {
+                    if let Some(document) = document {
+                        commands.remove_sheet_from_project(document);
+                    }
+                }
+                if ui
+                    .menu_item(im_str!("Use Sheet Export Options as Project Default"))
+                    .enabled(
+                        project.is_some()
+                            && document
+                                .map(|d| d.sheet.get_export_settings().is_some())
+                                .unwrap_or(false),
+                    )
+                    .build()
+                {
+                    if let Some(document) = document {
+                        commands.set_project_export_settings(document);
+                    }
+                }
+                ui.separator();
+                if ui
+                    .menu_item(im_str!("Export Project"))
+                    .enabled(project.is_some())
+                    .build()
+                {
+                    commands.export_project(app_state);
+                }
+                if ui
+                    .menu_item(im_str!("Close Project"))
+                    .enabled(project.is_some())
+                    .build()
+                {
+                    commands.close_project();
                 }
             });
 
@@ -286,6 +405,68 @@ fn draw_main_menu<'a>(
                 {
                     commands.redo();
                 }
+
+                ui.menu(im_str!("Undo History")).build(|| {
+                    for limit in HISTORY_LIMIT_CHOICES {
+                        let label = if *limit == DEFAULT_HISTORY_LIMIT {
+                            format!("{} Steps (Default)", limit)
+                        } else {
+                            format!("{} Steps", limit)
+                        };
+                        let mut selected = app_state.get_history_limit() == *limit;
+                        if ui
+                            .menu_item(&ImString::new(label))
+                            .selected(&mut selected)
+                            .build()
+                        {
+                            commands.set_history_limit(*limit);
+                        }
+                    }
+                });
+
+                ui.separator();
+
+                let has_document = app_state.get_current_document().is_some();
+                if ui
+                    .menu_item(im_str!("Cut"))
+                    .shortcut(im_str!("Ctrl+X"))
+                    .enabled(has_document)
+                    .build()
+                {
+                    if let Some(document) = app_state.get_current_document() {
+                        commands.cut(document);
+                    }
+                }
+                if ui
+                    .menu_item(im_str!("Copy"))
+                    .shortcut(im_str!("Ctrl+C"))
+                    .enabled(has_document)
+                    .build()
+                {
+                    if let Some(document) = app_state.get_current_document() {
+                        commands.copy(document);
+                    }
+                }
+                if ui
+                    .menu_item(im_str!("Paste"))
+                    .shortcut(im_str!("Ctrl+V"))
+                    .enabled(has_document)
+                    .build()
+                {
+                    if let Some(document) = app_state.get_current_document() {
+                        commands.paste(document);
+                    }
+                }
+                if ui
+                    .menu_item(im_str!("Duplicate"))
+                    .shortcut(im_str!("Ctrl+D"))
+                    .enabled(has_document)
+                    .build()
+                {
+                    if let Some(document) = app_state.get_current_document() {
+                        commands.duplicate_selection(document);
+                    }
+                }
             });
 
             ui.menu(im_str!("View")).build(|| {
@@ -387,6 +568,62 @@ fn draw_documents_window<'a>(
     *size
 }
 
+fn draw_project_window<'a>(
+    ui: &Ui<'a>,
+    rect: &Rect<f32>,
+    app_state: &AppState,
+    commands: &mut CommandBuffer,
+) {
+    let project = match app_state.get_project() {
+        Some(p) => p,
+        None => return,
+    };
+
+    ui.with_style_vars(&[WindowRounding(0.0), WindowBorderSize(0.0)], || {
+        ui.window(im_str!("Project"))
+            .position(rect.origin.to_tuple(), ImGuiCond::Always)
+            .always_auto_resize(true)
+            .collapsible(false)
+            .resizable(false)
+            .title_bar(false)
+            .menu_bar(false)
+            .movable(false)
+            .build(|| {
+                ui.tree_node(&ImString::new(project.get_name()))
+                    .opened(true, ImGuiCond::FirstUseEver)
+                    .build(|| {
+                        for (index, sheet) in project.sheets_iter().enumerate() {
+                            let document = app_state.documents_iter().find(|d| &d.source == sheet);
+                            let mut sheet_name = sheet
+                                .file_name()
+                                .map(|f| f.to_string_lossy().into_owned())
+                                .unwrap_or_else(|| "???".to_owned());
+                            if document.map(|d| !d.is_saved()).unwrap_or(false) {
+                                sheet_name += " [Modified]";
+                            }
+                            let is_current = app_state
+                                .get_current_document()
+                                .map(|d| &d.source == sheet)
+                                .unwrap_or(false);
+                            ui.push_id(index as i32);
+                            if ui.selectable(
+                                &ImString::new(sheet_name),
+                                is_current,
+                                ImGuiSelectableFlags::empty(),
+                                ImVec2::new(0.0, 0.0),
+                            ) {
+                                match document {
+                                    Some(d) => commands.focus_document(d),
+                                    None => commands.end_open_document(sheet),
+                                }
+                            }
+                            ui.pop_id();
+                        }
+                    });
+            });
+    });
+}
+
 fn update_drag_and_drop<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
     if let Some(document) = app_state.get_current_document() {
         if !ui.imgui().is_mouse_down(ImMouseButton::Left) {
@@ -421,11 +658,15 @@ fn update_drag_and_drop<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Co
 
 fn draw_drag_and_drop<'a>(ui: &Ui<'a>, app_state: &AppState, texture_cache: &TextureCache) {
     if let Some(document) = app_state.get_current_document() {
-        if let Some(ref path) = document.transient.content_frame_being_dragged {
+        let dragged_frame = document
+            .transient
+            .content_frame_being_dragged
+            .and_then(|f| document.sheet.get_frame(f));
+        if let Some(frame) = dragged_frame {
             if ui.imgui().is_mouse_dragging(ImMouseButton::Left) {
                 ui.tooltip(|| {
                     let tooltip_size = vec2(128.0, 128.0); // TODO hidpi?
-                    match texture_cache.get(path) {
+                    match texture_cache.get(frame.get_source()) {
                         Some(TextureCacheResult::Loaded(texture)) => {
                             if let Some(fill) = utils::fill(tooltip_size, texture.size) {
                                 ui.image(texture.id, fill.rect.size.to_tuple()).build();
@@ -515,7 +756,7 @@ fn draw_export_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
 
                     // TODO grey out and disable if bad settings
                     if ui.small_button(im_str!("Ok")) {
-                        commands.end_export_as(&document.sheet);
+                        commands.end_export_as(app_state, document);
                     }
                     ui.same_line(0.0);
                     if ui.small_button(im_str!("Cancel")) {
@@ -527,6 +768,188 @@ fn draw_export_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
     }
 }
 
+fn draw_split_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
+    if let Some(document) = app_state.get_current_document() {
+        if let Some(settings) = &document.persistent.split_settings_edit {
+            let popup_id = im_str!("Split Sheet");
+            ui.window(&popup_id)
+                .collapsible(false)
+                .resizable(true)
+                .always_auto_resize(true)
+                .build(|| {
+                    let mut new_settings = settings.clone();
+
+                    {
+                        ui.push_id(0);
+                        ui.label_text(
+                            &ImString::new(settings.source.to_string_lossy().borrow()),
+                            im_str!("Sheet image:"),
+                        );
+                        ui.same_line(0.0);
+                        if ui.small_button(im_str!("Browse…")) {
+                            commands.begin_set_split_source(document);
+                        }
+                        ui.pop_id();
+                    }
+
+                    {
+                        ui.push_id(1);
+                        ui.label_text(
+                            &ImString::new(settings.destination.to_string_lossy().borrow()),
+                            im_str!("Write frames to:"),
+                        );
+                        ui.same_line(0.0);
+                        if ui.small_button(im_str!("Browse…")) {
+                            commands.begin_set_split_destination(document);
+                        }
+                        ui.pop_id();
+                    }
+
+                    if ui.radio_button_bool(im_str!("Grid"), settings.mode == SplitMode::Grid) {
+                        new_settings.mode = SplitMode::Grid;
+                    }
+                    ui.same_line(0.0);
+                    if ui.radio_button_bool(
+                        im_str!("Opaque islands"),
+                        settings.mode == SplitMode::Islands,
+                    ) {
+                        new_settings.mode = SplitMode::Islands;
+                    }
+
+                    if settings.mode == SplitMode::Grid {
+                        let input_u32 = |label: &ImStr, value: &mut u32| {
+                            let mut as_i32 = *value as i32;
+                            if ui.input_int(label, &mut as_i32).build() {
+                                *value = as_i32.max(0) as u32;
+                            }
+                        };
+                        input_u32(im_str!("Cell width"), &mut new_settings.cell_size.x);
+                        input_u32(im_str!("Cell height"), &mut new_settings.cell_size.y);
+                        input_u32(im_str!("Margin X"), &mut new_settings.margin.x);
+                        input_u32(im_str!("Margin Y"), &mut new_settings.margin.y);
+                        input_u32(im_str!("Spacing X"), &mut new_settings.spacing.x);
+                        input_u32(im_str!("Spacing Y"), &mut new_settings.spacing.y);
+                        ui.checkbox(
+                            im_str!("Create one animation per row"),
+                            &mut new_settings.animation_per_row,
+                        );
+                    }
+
+                    if new_settings != *settings {
+                        commands.update_split_settings(&new_settings);
+                    }
+
+                    // TODO grey out and disable if bad settings
+                    if ui.small_button(im_str!("Ok")) {
+                        commands.end_split_sheet(document, settings);
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Cancel")) {
+                        commands.cancel_split_sheet();
+                    }
+                });
+            ui.open_popup(&popup_id);
+        }
+    }
+}
+
+fn draw_import_folder_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
+    if let Some(document) = app_state.get_current_document() {
+        if let Some(settings) = &document.persistent.folder_import_settings_edit {
+            let popup_id = im_str!("Import Folder");
+            ui.window(&popup_id)
+                .collapsible(false)
+                .resizable(true)
+                .always_auto_resize(true)
+                .build(|| {
+                    let mut new_settings = settings.clone();
+
+                    ui.label_text(
+                        &ImString::new(settings.folder.to_string_lossy().borrow()),
+                        im_str!("Folder:"),
+                    );
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Browse…")) {
+                        commands.begin_set_import_folder(document);
+                    }
+
+                    let mut pattern = ImString::with_capacity(128);
+                    pattern.push_str(&settings.pattern);
+                    if ui.input_text(im_str!("Name pattern"), &mut pattern).build() {
+                        new_settings.pattern = pattern.to_str().to_owned();
+                    }
+
+                    let mut duration = settings.frame_duration as i32;
+                    if ui
+                        .input_int(im_str!("Frame duration (ms)"), &mut duration)
+                        .build()
+                    {
+                        new_settings.frame_duration = duration.max(1) as u32;
+                    }
+
+                    if new_settings != *settings {
+                        commands.update_folder_import_settings(&new_settings);
+                    }
+
+                    if ui.small_button(im_str!("Ok")) {
+                        commands.end_import_folder(document, settings);
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Cancel")) {
+                        commands.cancel_import_folder();
+                    }
+                });
+            ui.open_popup(&popup_id);
+        }
+    }
+}
+
+fn draw_rescale_hitboxes_popup<'a>(
+    ui: &Ui<'a>,
+    app_state: &AppState,
+    commands: &mut CommandBuffer,
+) {
+    if let Some(document) = app_state.get_current_document() {
+        if let Some(replacement) = &document.persistent.image_replacement {
+            let popup_id = im_str!("Replace Image");
+            ui.popup_modal(&popup_id)
+                .title_bar(true)
+                .resizable(false)
+                .always_auto_resize(true)
+                .build(|| {
+                    ui.text(&ImString::new(format!(
+                        "The new image is {}x{} pixels but the previous one was {}x{}.",
+                        replacement.new_size.x,
+                        replacement.new_size.y,
+                        replacement.old_size.x,
+                        replacement.old_size.y
+                    )));
+                    ui.text(im_str!("Would you like to rescale hitboxes to match?"));
+                    if ui.small_button(im_str!("Rescale Hitboxes")) {
+                        commands.end_replace_frame_image(
+                            &document.source,
+                            replacement.clone(),
+                            true,
+                        );
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Keep Hitboxes")) {
+                        commands.end_replace_frame_image(
+                            &document.source,
+                            replacement.clone(),
+                            false,
+                        );
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Cancel")) {
+                        commands.cancel_replace_frame_image();
+                    }
+                });
+            ui.open_popup(&popup_id);
+        }
+    }
+}
+
 fn draw_rename_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
     if let Some(document) = app_state.get_current_document() {
         let max_length = match document.transient.item_being_renamed {
@@ -559,6 +982,102 @@ fn draw_rename_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
     }
 }
 
+fn draw_external_change_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
+    let document = match app_state
+        .documents_iter()
+        .find(|d| d.persistent.external_change.is_some())
+    {
+        Some(d) => d,
+        None => return,
+    };
+    let external_change = match &document.persistent.external_change {
+        Some(e) => e,
+        None => return,
+    };
+    let popup_id = im_str!("Sheet Changed On Disk");
+    ui.popup_modal(&popup_id)
+        .title_bar(true)
+        .resizable(false)
+        .always_auto_resize(true)
+        .build(|| {
+            ui.text(&ImString::new(format!(
+                "{} was modified by another program.",
+                document.source.to_string_lossy()
+            )));
+            if document.is_saved() {
+                ui.text(im_str!("Would you like to reload it?"));
+            } else {
+                ui.text(im_str!(
+                    "Reloading it will replace your unsaved changes, which you can undo."
+                ));
+                ui.tree_node(im_str!("View Diff")).build(|| {
+                    for change in &external_change.changes {
+                        ui.text(&ImString::new(change.to_string()));
+                    }
+                });
+            }
+            if ui.small_button(im_str!("Reload")) {
+                commands.reload_from_disk(document);
+            }
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("Keep Local Copy")) {
+                commands.keep_local_changes(document);
+            }
+        });
+    ui.open_popup(&popup_id);
+}
+
+fn draw_recovery_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
+    if app_state.recovered_sheets_iter().next().is_none() {
+        return;
+    }
+    let popup_id = im_str!("Recover Unsaved Changes");
+    ui.popup_modal(&popup_id)
+        .title_bar(true)
+        .resizable(false)
+        .always_auto_resize(true)
+        .build(|| {
+            ui.text(im_str!(
+                "Tiger did not exit properly. These sheets have unsaved changes:"
+            ));
+            for (index, recovered_sheet) in app_state.recovered_sheets_iter().enumerate() {
+                ui.push_id(index as i32);
+                ui.text(&ImString::new(
+                    recovered_sheet.source.to_string_lossy().into_owned(),
+                ));
+                ui.same_line(0.0);
+                if ui.small_button(im_str!("Restore")) {
+                    commands.restore_recovered_sheet(recovered_sheet);
+                }
+                ui.same_line(0.0);
+                if ui.small_button(im_str!("Discard")) {
+                    commands.discard_recovered_sheet(recovered_sheet);
+                }
+                ui.pop_id();
+            }
+        });
+    ui.open_popup(&popup_id);
+}
+
+fn draw_error_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
+    let error = match app_state.get_error() {
+        Some(e) => e,
+        None => return,
+    };
+    let popup_id = im_str!("Error");
+    ui.popup_modal(&popup_id)
+        .title_bar(true)
+        .resizable(false)
+        .always_auto_resize(true)
+        .build(|| {
+            ui.text(&ImString::new(error.clone()));
+            if ui.small_button(im_str!("OK")) {
+                commands.dismiss_error();
+            }
+        });
+    ui.open_popup(&popup_id);
+}
+
 fn draw_exit_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
     match app_state.get_exit_state() {
         Some(ExitState::Requested) => {
@@ -669,6 +1188,21 @@ fn process_shortcuts<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
             }
         }
 
+        if let Some(document) = app_state.get_current_document() {
+            if ui.imgui().is_key_pressed(VirtualKeyCode::X as _) {
+                commands.cut(document);
+            }
+            if ui.imgui().is_key_pressed(VirtualKeyCode::C as _) {
+                commands.copy(document);
+            }
+            if ui.imgui().is_key_pressed(VirtualKeyCode::V as _) {
+                commands.paste(document);
+            }
+            if ui.imgui().is_key_pressed(VirtualKeyCode::D as _) {
+                commands.duplicate_selection(document);
+            }
+        }
+
         if ui.imgui().is_key_pressed(VirtualKeyCode::N as _) {
             commands.begin_new_document();
         }
@@ -690,14 +1224,14 @@ fn process_shortcuts<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
             if ui.imgui().key_shift() {
                 commands.begin_export_as();
             } else if let Some(document) = app_state.get_current_document() {
-                commands.export(&document.sheet);
+                commands.export(app_state, document);
             }
         }
         if ui.imgui().is_key_pressed(VirtualKeyCode::W as _) {
             if ui.imgui().key_shift() {
-                commands.close_all_documents();
+                commands.close_all_documents(app_state);
             } else {
-                commands.close_current_document();
+                commands.close_current_document(app_state);
             }
         }
         if ui.imgui().is_key_pressed(VirtualKeyCode::Add as _)
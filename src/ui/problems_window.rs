@@ -0,0 +1,102 @@
+use imgui::*;
+
+use crate::sheet::{AnimationId, FrameId, HitboxId, Problem};
+use crate::state::*;
+
+fn reveal_frame(commands: &mut CommandBuffer, document: &Document, frame_id: FrameId) {
+    if let Some(frame) = document.sheet.get_frame(frame_id) {
+        commands.switch_to_content_tab(ContentTab::Frames);
+        commands.edit_frame(frame);
+        commands.select_frame(frame);
+    }
+}
+
+fn reveal_hitbox(
+    commands: &mut CommandBuffer,
+    document: &Document,
+    frame_id: FrameId,
+    hitbox_id: HitboxId,
+) {
+    if let Some(frame) = document.sheet.get_frame(frame_id) {
+        commands.switch_to_content_tab(ContentTab::Frames);
+        commands.edit_frame(frame);
+        if let Some(hitbox) = frame.get_hitbox(hitbox_id) {
+            commands.select_hitbox(hitbox);
+        }
+    }
+}
+
+fn reveal_animation(
+    commands: &mut CommandBuffer,
+    document: &Document,
+    animation_id: AnimationId,
+    frame_index: Option<usize>,
+) {
+    if let Some(animation) = document.sheet.get_animation(animation_id) {
+        commands.switch_to_content_tab(ContentTab::Animations);
+        commands.edit_animation(animation);
+        match frame_index {
+            Some(i) => commands.select_animation_frame(i),
+            None => commands.select_animation(animation),
+        }
+    }
+}
+
+fn reveal_problem(commands: &mut CommandBuffer, document: &Document, problem: &Problem) {
+    use Problem::*;
+    match problem {
+        MissingImage(f, _) | UnreadableImage(f, _) | DuplicateFrame(f, _) => {
+            reveal_frame(commands, document, *f)
+        }
+        HitboxOutOfBounds(f, h, _, _) | DuplicateHitboxName(f, h, _, _) => {
+            reveal_hitbox(commands, document, *f, *h)
+        }
+        EmptyAnimation(a, _) | DuplicateAnimationName(a, _) => {
+            reveal_animation(commands, document, *a, None)
+        }
+        FrameNotInSheet(a, _, i, _) | ZeroDurationKeyframe(a, _, i) => {
+            reveal_animation(commands, document, *a, Some(*i))
+        }
+        InvalidTextureDestination(_)
+        | InvalidMetadataDestination(_)
+        | InvalidMetadataPathsRoot(_)
+        | MissingTemplate(_) => commands.begin_export_as(),
+    }
+}
+
+pub fn draw<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
+    if let Some(document) = app_state.get_current_document() {
+        if let Some(problems) = &document.persistent.problems {
+            ui.window(im_str!("Problems"))
+                .collapsible(false)
+                .resizable(true)
+                .always_auto_resize(true)
+                .build(|| {
+                    if problems.is_empty() {
+                        ui.text(im_str!("No problems found in this sheet."));
+                    }
+                    for (index, problem) in problems.iter().enumerate() {
+                        ui.push_id(index as i32);
+                        let flags = ImGuiSelectableFlags::empty();
+                        if ui.selectable(
+                            &ImString::new(problem.to_string()),
+                            false,
+                            flags,
+                            ImVec2::new(0.0, 0.0),
+                        ) {
+                            reveal_problem(commands, document, problem);
+                        }
+                        ui.pop_id();
+                    }
+                    ui.separator();
+                    if ui.small_button(im_str!("Refresh")) {
+                        commands.validate(document);
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Close")) {
+                        commands.close_problems();
+                    }
+                });
+        }
+    }
+}
@@ -70,11 +70,12 @@ fn draw_animation<'a>(
     ui: &Ui<'a>,
     app_state: &AppState,
     texture_cache: &TextureCache,
+    sheet: &Sheet,
     animation: &Animation,
 ) {
     ui.text(&ImString::new(animation.get_name().to_owned()));
     let space = ui.get_content_region_avail().into();
-    match utils::get_bounding_box(animation, texture_cache) {
+    match utils::get_bounding_box(sheet, animation, texture_cache) {
         Ok(mut bbox) => {
             bbox.center_on_origin();
             if let Some(fill) = utils::fill(space, bbox.rect.size.to_f32().to_vector()) {
@@ -83,7 +84,10 @@ fn draw_animation<'a>(
                     app_state.get_clock().as_millis() as u64 % u64::from(duration),
                 ); // TODO pause on first and last frame for non looping animation?
                 let (_, animation_frame) = animation.get_frame_at(time).unwrap(); // TODO no unwrap
-                match texture_cache.get(animation_frame.get_frame()) {
+                let texture = sheet
+                    .get_frame(animation_frame.get_frame())
+                    .and_then(|f| texture_cache.get(f.get_source()));
+                match texture {
                     Some(TextureCacheResult::Loaded(texture)) => {
                         let cursor_pos: Vector2D<f32> = ui.get_cursor_pos().into();
                         let frame_offset = animation_frame.get_offset().to_f32();
@@ -116,9 +120,10 @@ fn draw_animation<'a>(
 fn draw_animation_frame<'a>(
     ui: &Ui<'a>,
     texture_cache: &TextureCache,
+    frame: &Frame,
     animation_frame: &AnimationFrame,
 ) {
-    let frame = animation_frame.get_frame();
+    let frame = frame.get_source();
     if let Some(name) = frame.file_name() {
         ui.text(&ImString::new(name.to_string_lossy()));
         ui.text(&ImString::new(format!(
@@ -155,27 +160,48 @@ pub fn draw<'a>(ui: &Ui<'a>, rect: &Rect<f32>, app_state: &AppState, texture_cac
             .movable(false)
             .build(|| {
                 if let Some(document) = app_state.get_current_document() {
-                    match &document.view.selection {
-                        Some(Selection::Frame(path)) => {
-                            if let Some(frame) = document.sheet.get_frame(path) {
+                    if document.view.selection.len() > 1 {
+                        ui.text(&ImString::new(format!(
+                            "{} items selected",
+                            document.view.selection.len()
+                        )));
+                    }
+                    match document.view.selection.get_single() {
+                        Some(SelectedItem::Frame(frame_id)) => {
+                            if let Some(frame) = document.sheet.get_frame(*frame_id) {
                                 draw_frame(ui, texture_cache, frame);
                             }
                         }
-                        Some(Selection::Animation(name)) => {
-                            if let Some(animation) = document.sheet.get_animation(name) {
-                                draw_animation(ui, app_state, texture_cache, animation);
+                        Some(SelectedItem::Animation(animation_id)) => {
+                            if let Some(animation) = document.sheet.get_animation(*animation_id) {
+                                draw_animation(
+                                    ui,
+                                    app_state,
+                                    texture_cache,
+                                    &document.sheet,
+                                    animation,
+                                );
                             }
                         }
-                        Some(Selection::AnimationFrame(name, index)) => {
-                            if let Some(animation) = document.sheet.get_animation(name) {
+                        Some(SelectedItem::AnimationFrame(animation_id, index)) => {
+                            if let Some(animation) = document.sheet.get_animation(*animation_id) {
                                 if let Some(animation_frame) = animation.get_frame(*index) {
-                                    draw_animation_frame(ui, texture_cache, animation_frame);
+                                    if let Some(frame) =
+                                        document.sheet.get_frame(animation_frame.get_frame())
+                                    {
+                                        draw_animation_frame(
+                                            ui,
+                                            texture_cache,
+                                            frame,
+                                            animation_frame,
+                                        );
+                                    }
                                 }
                             }
                         }
-                        Some(Selection::Hitbox(path, name)) => {
-                            if let Some(frame) = document.sheet.get_frame(path) {
-                                if let Some(hitbox) = frame.get_hitbox(name) {
+                        Some(SelectedItem::Hitbox(frame_id, hitbox_id)) => {
+                            if let Some(frame) = document.sheet.get_frame(*frame_id) {
+                                if let Some(hitbox) = frame.get_hitbox(*hitbox_id) {
                                     draw_hitbox(ui, hitbox);
                                 }
                             }
@@ -4,7 +4,7 @@ use std::time::Duration;
 
 use crate::sheet::{Animation, AnimationFrame};
 use crate::state::*;
-use crate::ui::Rect;
+use crate::ui::{extend_selection, Rect};
 
 fn draw_timeline_ticks<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, document: &Document) {
     let zoom = document.view.get_timeline_zoom_factor();
@@ -127,11 +127,8 @@ fn draw_animation_frame<'a>(
         .min(resize_handle_size_right)
         .max(1.0);
 
-    let is_selected = &document.view.selection
-        == &Some(Selection::AnimationFrame(
-            animation.get_name().to_string(),
-            animation_frame_index,
-        ));
+    let item = SelectedItem::AnimationFrame(animation.get_id(), animation_frame_index);
+    let is_selected = document.view.selection.contains(&item);
 
     let draw_list = ui.get_window_draw_list();
     let mut cursor_pos = ui.get_cursor_screen_pos();
@@ -173,7 +170,11 @@ fn draw_animation_frame<'a>(
         );
 
         // Draw name
-        if let Some(name) = animation_frame.get_frame().file_name() {
+        let source = document
+            .sheet
+            .get_frame(animation_frame.get_frame())
+            .map(|f| f.get_source());
+        if let Some(name) = source.and_then(|s| s.file_name()) {
             draw_list.with_clip_rect_intersect(fill_top_left, fill_bottom_right, || {
                 let text_color = outline_color; // TODO.style
                 let text_position = (fill_top_left.0 + text_padding, fill_top_left.1);
@@ -191,7 +192,8 @@ fn draw_animation_frame<'a>(
                     bottom_right.0 - top_left.0 - resize_handle_size * 2.0,
                     bottom_right.1 - top_left.1,
                 ),
-            ) {
+            ) && !extend_selection(ui, commands, item)
+            {
                 commands.select_animation_frame(animation_frame_index);
             }
         }
@@ -356,7 +358,7 @@ fn handle_drag_and_drop<'a>(
                     } else {
                         animation.get_num_frames()
                     };
-                    commands.insert_animation_frame_before(dragged_frame, index);
+                    commands.insert_animation_frame_before(*dragged_frame, index);
                 }
                 (None, None, Some(ref dragged_animation_frame)) => {
                     let index = if mouse_pos.0 <= cursor_start.0 {
@@ -367,7 +369,7 @@ fn handle_drag_and_drop<'a>(
                     commands.reorder_animation_frame(*dragged_animation_frame, index);
                 }
                 (Some((index, _)), Some(ref dragged_frame), None) => {
-                    commands.insert_animation_frame_before(dragged_frame, index);
+                    commands.insert_animation_frame_before(*dragged_frame, index);
                 }
                 (Some((index, _)), None, Some(ref dragged_animation_frame)) => {
                     commands.reorder_animation_frame(*dragged_animation_frame, index);
@@ -389,10 +391,10 @@ pub fn draw<'a>(ui: &Ui<'a>, rect: &Rect<f32>, app_state: &AppState, commands: &
             .always_horizontal_scrollbar(true)
             .build(|| {
                 if let Some(document) = app_state.get_current_document() {
-                    if let Some(WorkbenchItem::Animation(animation_name)) =
+                    if let Some(WorkbenchItem::Animation(animation_id)) =
                         &document.view.workbench_item
                     {
-                        if let Some(animation) = document.sheet.get_animation(animation_name) {
+                        if let Some(animation) = document.sheet.get_animation(*animation_id) {
                             if ui.small_button(im_str!("Play/Pause")) {
                                 commands.toggle_playback();
                             }
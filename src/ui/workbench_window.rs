@@ -5,6 +5,7 @@ use imgui::*;
 use crate::sheet::{Animation, AnimationFrame, Frame, Hitbox};
 use crate::state::*;
 use crate::streamer::{TextureCache, TextureCacheResult};
+use crate::ui::extend_selection;
 use crate::ui::spinner::*;
 
 fn screen_to_workbench<'a>(
@@ -142,18 +143,16 @@ fn draw_hitbox<'a>(
     let top_left: Vector2D<f32> = ui.get_cursor_screen_pos().into();
     let bottom_right = top_left + rectangle.size.to_f32().to_vector() * zoom;
 
-    let is_selected = document.view.selection
-        == Some(Selection::Hitbox(
-            frame.get_source().to_owned(),
-            hitbox.get_name().to_owned(),
-        ));
+    let item = SelectedItem::Hitbox(frame.get_id(), hitbox.get_id());
+    let is_selected = document.view.selection.contains(&item);
 
     let (is_hovered, is_active) = if is_selectable && !rectangle.size.is_empty_or_negative() {
-        let hitbox_id = ImString::new(format!("hitbox_button_{}", hitbox.get_name()));
+        let hitbox_id = ImString::new(format!("hitbox_button_{}", hitbox.get_id()));
         if ui.invisible_button(
             &hitbox_id,
             (rectangle.size.to_f32().to_vector() * zoom).to_tuple(),
-        ) {
+        ) && !extend_selection(ui, commands, item)
+        {
             commands.select_hitbox(hitbox);
         }
         ui.set_item_allow_overlap();
@@ -183,10 +182,11 @@ fn draw_hitbox<'a>(
     }
 
     if *is_dragging {
-        match &document.transient.workbench_hitbox_being_dragged {
-            Some(n) if n == hitbox.get_name() => {
+        match document.transient.workbench_hitbox_being_dragged {
+            Some(id) if id == hitbox.get_id() => {
                 ui.imgui().set_mouse_cursor(ImGuiMouseCursor::ResizeAll);
-                if is_mouse_dragging { // TODO this check is a workaround https://github.com/ocornut/imgui/issues/2419
+                if is_mouse_dragging {
+                    // TODO this check is a workaround https://github.com/ocornut/imgui/issues/2419
                     commands.update_hitbox_drag(drag_delta, !is_shift_down);
                 }
             }
@@ -195,11 +195,12 @@ fn draw_hitbox<'a>(
     }
 
     if *is_scaling {
-        match &document.transient.workbench_hitbox_being_scaled {
-            Some(n) if n == hitbox.get_name() => {
+        match document.transient.workbench_hitbox_being_scaled {
+            Some(id) if id == hitbox.get_id() => {
                 let axis = document.transient.workbench_hitbox_scale_axis;
                 ui.imgui().set_mouse_cursor(axis_to_cursor(axis));
-                if is_mouse_dragging { // TODO this check is a workaround https://github.com/ocornut/imgui/issues/2419
+                if is_mouse_dragging {
+                    // TODO this check is a workaround https://github.com/ocornut/imgui/issues/2419
                     commands.update_hitbox_scale(drag_delta, is_shift_down);
                 }
             }
@@ -243,7 +244,7 @@ fn draw_frame<'a>(
             let mut is_dragging_hitbox =
                 document.transient.workbench_hitbox_being_dragged.is_some();
 
-            let mouse_pos = ui.imgui().mouse_pos().into();
+            let mouse_pos: Vector2D<f32> = ui.imgui().mouse_pos().into();
             let mouse_position_in_workbench = screen_to_workbench(ui, mouse_pos, document);
 
             for hitbox in frame.hitboxes_iter() {
@@ -265,8 +266,23 @@ fn draw_frame<'a>(
                 && ui.is_window_hovered()
                 && is_mouse_dragging
             {
-                let drag_delta: Vector2D<f32> =  ui.imgui().mouse_drag_delta(ImMouseButton::Left).into();
-                commands.create_hitbox(mouse_position_in_workbench - drag_delta / zoom);
+                let drag_delta: Vector2D<f32> =
+                    ui.imgui().mouse_drag_delta(ImMouseButton::Left).into();
+                let drag_origin = mouse_position_in_workbench - drag_delta / zoom;
+                if ui.imgui().key_ctrl() {
+                    let draw_list = ui.get_window_draw_list();
+                    draw_list
+                        .add_rect(
+                            (mouse_pos - drag_delta).to_tuple(),
+                            mouse_pos.to_tuple(),
+                            [0.0, 0.9, 0.9, 1.0], // TODO.style
+                        )
+                        .thickness(1.0) // TODO dpi
+                        .build();
+                    commands.select_hitboxes_in_rectangle(drag_origin, mouse_position_in_workbench);
+                } else {
+                    commands.create_hitbox(drag_origin);
+                }
             }
         }
         Some(TextureCacheResult::Loading) => {
@@ -291,7 +307,11 @@ fn draw_animation_frame<'a>(
     let zoom = document.view.get_workbench_zoom_factor();
     let offset = document.view.workbench_offset;
     let space: Vector2D<f32> = ui.get_window_size().into();
-    match texture_cache.get(&animation_frame.get_frame()) {
+    let texture = document
+        .sheet
+        .get_frame(animation_frame.get_frame())
+        .and_then(|f| texture_cache.get(f.get_source()));
+    match texture {
         Some(TextureCacheResult::Loaded(texture)) => {
             let frame_offset = animation_frame.get_offset().to_f32();
             let draw_size = texture.size * zoom;
@@ -361,9 +381,11 @@ fn draw_animation<'a>(
 ) {
     let now = document.view.timeline_clock;
     if let Some((frame_index, animation_frame)) = animation.get_frame_at(now) {
-        let is_selected = document.view.selection
-            == Some(Selection::AnimationFrame(
-                animation.get_name().to_owned(),
+        let is_selected = document
+            .view
+            .selection
+            .contains(&SelectedItem::AnimationFrame(
+                animation.get_id(),
                 frame_index,
             ));
 
@@ -521,11 +543,11 @@ fn handle_drag_and_drop<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Co
 
     if is_window_hovered && !is_mouse_down {
         if let Some(document) = app_state.get_current_document() {
-            if let Some(WorkbenchItem::Animation(animation_name)) = &document.view.workbench_item {
-                if let Some(animation) = document.sheet.get_animation(animation_name) {
+            if let Some(WorkbenchItem::Animation(animation_id)) = &document.view.workbench_item {
+                if let Some(animation) = document.sheet.get_animation(*animation_id) {
                     if let Some(dragged_frame) = &document.transient.content_frame_being_dragged {
                         let index = animation.get_num_frames();
-                        commands.insert_animation_frame_before(dragged_frame, index);
+                        commands.insert_animation_frame_before(*dragged_frame, index);
                     }
                 }
             }
@@ -564,8 +586,8 @@ pub fn draw<'a>(
 
                 if let Some(document) = app_state.get_current_document() {
                     match &document.view.workbench_item {
-                        Some(WorkbenchItem::Frame(path)) => {
-                            if let Some(frame) = document.sheet.get_frame(path) {
+                        Some(WorkbenchItem::Frame(frame_id)) => {
+                            if let Some(frame) = document.sheet.get_frame(*frame_id) {
                                 draw_frame(ui, commands, texture_cache, document, frame);
                                 let name = frame
                                     .get_source()
@@ -575,8 +597,8 @@ pub fn draw<'a>(
                                 draw_item_name(ui, name);
                             }
                         }
-                        Some(WorkbenchItem::Animation(name)) => {
-                            if let Some(animation) = document.sheet.get_animation(name) {
+                        Some(WorkbenchItem::Animation(animation_id)) => {
+                            if let Some(animation) = document.sheet.get_animation(*animation_id) {
                                 draw_animation(ui, commands, texture_cache, document, animation);
                                 draw_origin(ui, document);
                                 draw_item_name(ui, animation.get_name());
@@ -1,6 +1,7 @@
 use euclid::*;
+use std::hash::Hasher;
 
-use crate::sheet::Animation;
+use crate::sheet::{Animation, Sheet};
 use crate::streamer::{TextureCache, TextureCacheResult};
 
 pub struct Fill {
@@ -66,6 +67,7 @@ impl BoundingBox {
 }
 
 pub fn get_bounding_box(
+    sheet: &Sheet,
     animation: &Animation,
     texture_cache: &TextureCache,
 ) -> Result<BoundingBox, BoundingBoxError> {
@@ -73,9 +75,12 @@ pub fn get_bounding_box(
         return Err(BoundingBoxError::EmptyAnimation);
     }
     let mut bbox_rectangle = Rect::<i32>::zero();
-    for frame in animation.frames_iter() {
-        if let Some(TextureCacheResult::Loaded(texture)) = texture_cache.get(frame.get_frame()) {
-            let frame_offset = frame.get_offset();
+    for animation_frame in animation.frames_iter() {
+        let texture = sheet
+            .get_frame(animation_frame.get_frame())
+            .and_then(|f| texture_cache.get(f.get_source()));
+        if let Some(TextureCacheResult::Loaded(texture)) = texture {
+            let frame_offset = animation_frame.get_offset();
             let frame_rectangle =
                 Rect::<i32>::new(frame_offset.to_point(), texture.size.to_i32().to_size());
             bbox_rectangle = bbox_rectangle.union(&frame_rectangle);
@@ -88,6 +93,29 @@ pub fn get_bounding_box(
     })
 }
 
+// FNV-1a, which unlike the standard hashers is guaranteed to give the same results across releases.
+// Hashes which end up in files must use it.
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> StableHasher {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
 #[test]
 fn test_center_on_origin() {
     {